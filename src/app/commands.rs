@@ -0,0 +1,176 @@
+// src/app/commands.rs
+use eframe::egui;
+
+use crate::app::gui::app_main::{AppMode, MyApp};
+use crate::app::gui::{caster_ui, receiver_ui};
+use crate::app::hotkey_action::HotkeyAction;
+use log::{debug, info};
+
+/// Azioni dell'applicazione. Pulsanti e hotkey passano tutti da `execute`, così ogni azione
+/// ha un solo percorso e si comporta in base alla modalità (caster o receiver).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    SetMode(AppMode),
+    ToggleBroadcast,
+    ToggleRecording,
+    ToggleReceiving,
+    ToggleScreenLock,
+    ToggleAnnotationTools,
+    SelectCaptureArea,
+    SwitchMonitor,
+    ToggleStatsPanel,
+    EndSession,
+}
+
+impl From<HotkeyAction> for Command {
+    fn from(action: HotkeyAction) -> Self {
+        match action {
+            HotkeyAction::StartPauseBroadcast => Command::ToggleBroadcast,
+            HotkeyAction::StartStopRecording => Command::ToggleRecording,
+            HotkeyAction::LockUnlockScreen => Command::ToggleScreenLock,
+            HotkeyAction::ToggleAnnotation => Command::ToggleAnnotationTools,
+            HotkeyAction::QuickCaptureSelection => Command::SelectCaptureArea,
+            HotkeyAction::EndSession => Command::EndSession,
+            HotkeyAction::SwitchMonitor => Command::SwitchMonitor,
+        }
+    }
+}
+
+impl Command {
+    /// Se il comando ha senso nello stato attuale. I pulsanti dei comandi non disponibili
+    /// vengono disabilitati e le hotkey corrispondenti ignorate.
+    pub fn is_available(&self, app: &MyApp) -> bool {
+        let caster = app.is_caster();
+        match self {
+            // Cambiare modalità con una sessione attiva lascerebbe thread senza pulsanti per fermarli
+            Command::SetMode(mode) => {
+                app.mode == *mode
+                    || !(app.flags.is_broadcasting() || app.flags.is_recording() || app.flags.is_receiving())
+            }
+            Command::ToggleBroadcast => caster,
+            // Il receiver registra i frame ricevuti, quindi solo mentre riceve; fermare è sempre possibile
+            Command::ToggleRecording => app.flags.is_recording() || caster || app.flags.is_receiving(),
+            Command::ToggleReceiving => !caster,
+            Command::ToggleAnnotationTools | Command::SelectCaptureArea | Command::SwitchMonitor => caster,
+            Command::ToggleScreenLock | Command::ToggleStatsPanel | Command::EndSession => true,
+        }
+    }
+
+    pub fn label(&self, app: &MyApp) -> &'static str {
+        match self {
+            Command::SetMode(AppMode::Caster) => "🎥 Caster",
+            Command::SetMode(AppMode::Receiver) => "📡 Receiver",
+            Command::ToggleBroadcast if app.flags.is_broadcasting() => "Stop Broadcasting",
+            Command::ToggleBroadcast => "Start Broadcasting",
+            Command::ToggleRecording if app.flags.is_recording() => "Stop Recording",
+            Command::ToggleRecording => "Start Recording",
+            Command::ToggleReceiving if app.flags.is_receiving() => "Stop Receiving",
+            Command::ToggleReceiving => "Start Receiving",
+            Command::ToggleScreenLock => "Lock/Unlock Screen",
+            Command::ToggleAnnotationTools => "Toggle Annotation",
+            Command::SelectCaptureArea => "Select Capture Area",
+            Command::SwitchMonitor => "Multi-Monitor Support",
+            Command::ToggleStatsPanel if app.ui_state.is_showing_stats_panel() => "Hide Stats",
+            Command::ToggleStatsPanel => "Show Stats",
+            Command::EndSession => "End Session",
+        }
+    }
+}
+
+/// Esegue il comando se è disponibile; restituisce false se è stato ignorato.
+pub fn execute(app: &mut MyApp, ctx: &egui::Context, command: Command) -> bool {
+    if !command.is_available(app) {
+        debug!("Command {:?} not available in the current mode, ignored", command);
+        return false;
+    }
+
+    match command {
+        Command::SetMode(mode) => app.mode = mode,
+        Command::ToggleBroadcast => {
+            if app.flags.is_broadcasting() {
+                caster_ui::stop_broadcast(app);
+            } else {
+                caster_ui::start_broadcast(app);
+            }
+        }
+        Command::ToggleRecording => {
+            if app.flags.is_recording() {
+                stop_recording(app);
+            } else {
+                // La nuova registrazione sovrascrive lo stesso file: ffmpeg deve prima aver chiuso il precedente
+                finish_previous_recording(app);
+                if app.is_caster() {
+                    caster_ui::start_record(app);
+                } else {
+                    receiver_ui::start_record(app);
+                }
+            }
+        }
+        Command::ToggleReceiving => {
+            if app.flags.is_receiving() {
+                // Senza frame in arrivo la registrazione del receiver non avrebbe più nulla da scrivere
+                if app.flags.is_recording() {
+                    stop_recording(app);
+                }
+                receiver_ui::stop_receiving(app);
+            } else {
+                receiver_ui::start_receiving(app);
+            }
+        }
+        Command::ToggleScreenLock => {
+            let new_state = !app.flags.is_screen_locked();
+            app.flags.set_screen_locked(new_state);
+            info!("Screen lock toggled: {}", new_state);
+        }
+        Command::ToggleAnnotationTools => {
+            app.flags.set_annotation_tools_active(!app.flags.is_annotation_tools_active());
+        }
+        Command::SelectCaptureArea => app.ui_state.set_selecting_area(true),
+        Command::SwitchMonitor => app.ui_state.set_showing_monitor_selection(true),
+        Command::ToggleStatsPanel => {
+            app.ui_state.set_showing_stats_panel(!app.ui_state.is_showing_stats_panel());
+        }
+        Command::EndSession => {
+            end_session(app);
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+    true
+}
+
+/// Ferma la registrazione in corso, sia del caster che del receiver
+pub(crate) fn stop_recording(app: &mut MyApp) {
+    info!("Stopping recording...");
+    app.flags.set_recording(false);
+
+    // Non attende ffmpeg: la registrazione resta in `app.recorder` finché non ne viene avviata
+    // un'altra o la sessione non viene chiusa
+    if let Some(recorder) = &app.recorder {
+        recorder.cancellation_token().cancel();
+    }
+}
+
+/// Attende che ffmpeg chiuda il file di una registrazione già fermata con `stop_recording`
+fn finish_previous_recording(app: &mut MyApp) {
+    if let Some(recorder) = app.recorder.take() {
+        debug!("Waiting for the previous recording to be finalized...");
+        recorder.join();
+    }
+}
+
+/// Ferma trasmissione, ricezione e preview, e aspetta che ffmpeg chiuda il file della registrazione
+fn end_session(app: &mut MyApp) {
+    info!("Ending session...");
+    if app.flags.is_broadcasting() {
+        caster_ui::stop_broadcast(app);
+    }
+    if app.flags.is_recording() {
+        stop_recording(app);
+    }
+    if app.flags.is_receiving() {
+        receiver_ui::stop_receiving(app);
+    }
+    app.stop_caster_preview();
+
+    finish_previous_recording(app);
+}
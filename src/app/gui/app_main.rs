@@ -1,5 +1,5 @@
 use eframe::{egui, App, CreationContext};
-use crate::app::{gui::caster_ui, hotkey_module::{HotkeyAction, HotkeySettings}};
+use crate::app::{commands, gui::{caster_ui, receiver_ui, stats_panel, toasts::Toasts}, hotkey_action::HotkeyAction, hotkey_module::HotkeySettings};
 use crate::app::gui::visuals::{configure_visuals, central_panel, capture_area_panel, monitor_selection_panel, render_screen_lock_overlay};
 use std::sync::{mpsc, Arc, Mutex};
 use crate::app::state::{
@@ -10,14 +10,30 @@ use crate::app::state::{
     user_settings::UserSettings,
 };
 use eframe::NativeOptions;
-use crate::app::capture::ScreenCapture;
-use std::thread;
-use crate::app::gui::receiver_ui::start_record_thread_for_receiver;
+use crate::core::capture::ScreenCapture;
+use crate::core::caster::Caster;
+use crate::core::error::{ErrorSender, Worker, WorkerError};
+use crate::core::worker::WorkerStatus;
+use crate::core::receiver::Receiver;
+use crate::core::recorder::Recorder;
 use std::collections::VecDeque;
-use crate::utils::annotations::Annotation;
-use crate::app::capture::ScreenCapturer;
-use std::time::Duration;
-
+use crate::utils::annotations::{Annotation, AnnotationEvent};
+use crate::utils::editor::Editor;
+use crate::utils::ephemeral::EphemeralLayer;
+use crate::utils::snapshot::{self, SnapshotError};
+use crate::utils::svg;
+use crate::utils::multi_monitor::{monitor_id, monitor_size};
+use crate::utils::redaction::MaskStyle;
+use crate::core::capture::CaptureSource;
+use crate::core::capture_pipeline::{CapturePipeline, FrameSubscriber};
+use std::path::PathBuf;
+use crate::core::capture::CaptureArea;
+use crate::app::settings_file::{AnnotationDefaults, SettingsFile, Theme};
+use log::{debug, error, warn};
+
+
+// Framerate della preview dell'editor: per disegnare le annotazioni non serve di più
+const PREVIEW_FPS: u32 = 5;
 
 pub fn initialize() -> Result<(), eframe::Error> {
     let options = NativeOptions::default();
@@ -28,27 +44,11 @@ pub fn initialize() -> Result<(), eframe::Error> {
     )
 }
 
-pub enum Theme {
-    Light,
-    Dark,
-}
-
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum AppMode {
     Caster,
     Receiver,
 }
-#[derive(Debug, Clone, Copy)]
-pub enum AnnotationTool {
-    Segment,
-    Circle,
-    Rectangle,
-    Arrow,
-    Pencil,
-    Highlighter,
-    Text,
-    Eraser,
-    Crop,
-}
 pub struct MyApp {
     pub mode: AppMode,
     pub network: NetworkState,
@@ -58,33 +58,93 @@ pub struct MyApp {
     pub hotkeys: HotkeySettings,
     pub user_settings: UserSettings,
     pub frame_receiver: Option<mpsc::Receiver<ScreenCapture>>,
+    pub annotation_receiver: Option<mpsc::Receiver<AnnotationEvent>>,
     pub texture: Option<egui::TextureHandle>,
-    pub receiving_flag: Arc<Mutex<bool>>,
 
     pub frame_buffer: Arc<Mutex<VecDeque<ScreenCapture>>>, // Buffer condiviso per i frame
 
-    // Lista delle annotazioni create
-    pub annotations: Vec<Annotation>,
-
-    // Quando l'utente sta tracciando un'annotazione in tempo reale (drag in corso)
-    pub annotation_in_progress: Option<Annotation>,
-
-    // Tipo di strumento di annotazione selezionato
-    pub selected_tool: Option<AnnotationTool>,
-
-    // Canale su cui il thread di preview invia i frame al main thread
-    caster_preview_rx: Option<mpsc::Receiver<crate::app::capture::ScreenCapture>>,
-    // Per fermare il thread di preview
-    caster_preview_stop: Option<mpsc::Sender<()>>,
-    // Per aspettare che il thread finisca (opzionale)
-    caster_preview_handle: Option<thread::JoinHandle<()>>,
+    // Editor delle annotazioni del caster: contiene il frame di preview,
+    // le annotazioni (in coordinate di cattura), lo strumento attivo e la cronologia undo/redo
+    pub editor: Editor,
+
+    // Annotazioni ricevute dal caster (in coordinate di cattura)
+    pub received_annotations: Vec<Annotation>,
+    // Puntatore laser e inchiostro a scomparsa ricevuti dal caster
+    pub received_ephemeral: EphemeralLayer,
+
+    // File delle sessioni di annotazioni salvate/caricate dal caster
+    pub session_path: String,
+    // Se true, una sessione disegnata a un'altra risoluzione viene riscalata invece che rifiutata
+    pub rescale_session: bool,
+    // Esito dell'ultimo salvataggio/caricamento, mostrato nella toolbar
+    pub session_status: Option<String>,
+
+    // File (.png o .jpg) in cui salvare lo snapshot annotato, ed esito dell'ultimo snapshot
+    pub snapshot_path: String,
+    pub snapshot_status: Option<String>,
+    // Esito dell'ultima copia del log diagnostico
+    pub diagnostics_status: Option<String>,
+    // File SVG per l'esportazione vettoriale, con o senza il frame come sfondo
+    pub svg_path: String,
+    pub svg_background: bool,
+
+    // Monitor catturato: il principale se None, altrimenti l'indice in `Display::all()`
+    pub monitor: Option<usize>,
+    // Chiave del monitor catturato, a cui sono associate le maschere di privacy nelle impostazioni
+    pub monitor_id: String,
+    // Se true, trascinare sulla preview disegna maschere di privacy invece di annotazioni
+    pub editing_privacy_masks: bool,
+    pub privacy_mask_style: MaskStyle,
+    // Angolo di partenza della maschera in corso di disegno (in coordinate del monitor)
+    pub privacy_mask_drag: Option<egui::Pos2>,
+
+    // Azione a cui assegnare la prossima combinazione premuta nella finestra delle shortcut
+    pub capturing_hotkey: Option<HotkeyAction>,
+    // Combinazione scritta a mano, per i modificatori che la finestra non riceve (Super fuori da macOS)
+    pub hotkey_text: String,
+
+    // Trasmissione, ricezione e registrazione in corso
+    pub caster: Option<Caster>,
+    pub receiver: Option<Receiver>,
+    // Registrazione (caster o receiver), attesa alla chiusura della sessione anche dopo lo stop
+    pub recorder: Option<Recorder>,
+
+    // Notifiche in basso a destra, ad esempio per gli errori dei thread di lavoro
+    pub toasts: Toasts,
+    // Canale su cui trasmissione, ricezione, registrazione e preview segnalano di essersi interrotte per un errore
+    pub worker_errors_tx: ErrorSender,
+    worker_errors_rx: mpsc::Receiver<WorkerError>,
+
+    // Unica cattura dello schermo del caster, condivisa da preview, trasmissione, registrazione e snapshot
+    capture_pipeline: Option<CapturePipeline>,
+    // Frame della pipeline mostrati nella preview dell'editor
+    caster_preview: Option<FrameSubscriber>,
+    // True se la cattura per la preview non è partita o si è interrotta: si riprova cambiando modalità
+    caster_preview_failed: bool,
+
+    // File delle impostazioni (None se non va scritto, ad esempio perché creato da una build più recente)
+    settings_path: Option<PathBuf>,
+    // Ultime impostazioni salvate, per riscrivere il file solo quando qualcosa cambia
+    saved_settings: SettingsFile,
 }
 
 impl MyApp {
     pub fn new(_cc: &CreationContext<'_>) -> Self {
-        let hotkeys = HotkeySettings::new().expect("Failed to initialize hotkeys");
+        let (settings, settings_path) = match SettingsFile::default_path() {
+            Ok(path) => SettingsFile::load_or_default(path),
+            Err(e) => {
+                warn!("Settings will not be saved: {}", e);
+                (SettingsFile::default(), None)
+            }
+        };
+
+        // Le azioni senza una combinazione salvata usano quella predefinita
+        let mut bindings = HotkeyAction::default_bindings();
+        bindings.extend(settings.hotkeys.clone());
+        let hotkeys = HotkeySettings::new(&bindings).expect("Failed to initialize hotkeys");
 
-        Self {
+        let (worker_errors_tx, worker_errors_rx) = mpsc::channel();
+        let mut app = Self {
             mode: AppMode::Receiver,
             network: NetworkState::new(),
             capture: CaptureState::new(),
@@ -93,27 +153,182 @@ impl MyApp {
             hotkeys,
             user_settings: UserSettings::new(), // Inizializzazione di user_settings
             frame_receiver: None,
+            annotation_receiver: None,
             texture: None,
-            receiving_flag: Arc::new(Mutex::new(false)),
 
             frame_buffer: Arc::new(Mutex::new(VecDeque::new())), // Inizializza il buffer
             
-            annotations: Vec::new(),
-            annotation_in_progress: None,
-            selected_tool: None,
-            caster_preview_rx: None,
-            caster_preview_stop: None,
-            caster_preview_handle: None,
+            editor: Editor::default(),
+            received_annotations: Vec::new(),
+            received_ephemeral: EphemeralLayer::default(),
+            session_path: "annotations.json".to_string(),
+            rescale_session: false,
+            session_status: None,
+            snapshot_path: "snapshot.png".to_string(),
+            snapshot_status: None,
+            diagnostics_status: None,
+            svg_path: "annotations.svg".to_string(),
+            svg_background: true,
+            monitor: None,
+            monitor_id: monitor_id(None),
+            editing_privacy_masks: false,
+            privacy_mask_style: MaskStyle::Solid,
+            privacy_mask_drag: None,
+            capturing_hotkey: None,
+            hotkey_text: String::new(),
+            caster: None,
+            receiver: None,
+            recorder: None,
+            toasts: Toasts::default(),
+            worker_errors_tx,
+            worker_errors_rx,
+            settings_path,
+            saved_settings: settings.clone(),
+            capture_pipeline: None,
+            caster_preview: None,
+            caster_preview_failed: false,
+        };
+        app.apply_settings(&settings);
+        app.saved_settings = app.settings_snapshot();
+        app
+    }
+
+    /// Impostazioni correnti, nel formato del file di impostazioni
+    pub fn settings_snapshot(&self) -> SettingsFile {
+        SettingsFile {
+            theme: *self.user_settings.get_theme(),
+            caster_address: self.network.get_address().to_string(),
+            auto_detect_ip: self.network.auto_detect_ip,
+            port: self.network.get_port(),
+            monitor: self.monitor,
+            capture_area: self
+                .capture
+                .get_capture_area()
+                .filter(|area| area.is_valid())
+                .map(|area| [area.x, area.y, area.width, area.height]),
+            stream: *self.user_settings.get_stream_quality(),
+            recording: self.user_settings.get_recording_profile().clone(),
+            annotations: AnnotationDefaults::from_editor(&self.editor),
+            hotkeys: self.hotkeys.get_bindings().clone(),
+            privacy_masks: self.user_settings.get_all_privacy_masks().clone(),
+            log_level: self.user_settings.get_log_level(),
+            ..SettingsFile::default()
         }
     }
 
+    fn apply_settings(&mut self, settings: &SettingsFile) {
+        self.user_settings.set_theme(settings.theme);
+        self.network.auto_detect_ip = settings.auto_detect_ip;
+        if !settings.auto_detect_ip {
+            self.network.set_address(settings.caster_address.clone());
+        }
+        self.network.set_port(settings.port);
+        // Un monitor salvato che non è più collegato lascia il posto al principale
+        if settings.monitor.is_some() && monitor_size(settings.monitor).is_some() {
+            self.select_monitor(settings.monitor);
+        }
+        // Un'area salvata che non sta più nello schermo (ad esempio dopo un cambio di risoluzione) viene ignorata
+        if let Some([x, y, width, height]) = settings.capture_area {
+            let fits = monitor_size(self.monitor)
+                .is_some_and(|(display_width, display_height)| x + width <= display_width && y + height <= display_height);
+            if fits {
+                self.capture.set_capture_area(Some(CaptureArea::new(x, y, width, height)));
+            }
+        }
+        *self.user_settings.get_stream_quality_mut() = settings.stream;
+        self.user_settings.set_recording_profile(settings.recording.clone());
+        settings.annotations.apply_to(&mut self.editor);
+        self.user_settings.set_all_privacy_masks(settings.privacy_masks.clone());
+        self.user_settings.set_log_level(settings.log_level);
+    }
+
+    /// Riscrive il file delle impostazioni se qualcosa è cambiato dall'ultimo salvataggio
+    fn save_settings_if_changed(&mut self) {
+        let Some(path) = &self.settings_path else {
+            return;
+        };
+        let settings = self.settings_snapshot();
+        if settings == self.saved_settings {
+            return;
+        }
+        if let Err(e) = settings.save(path) {
+            error!("Failed to save settings to {}: {}", path.display(), e);
+        }
+        self.saved_settings = settings;
+    }
+
     pub fn set_frame_receiver(&mut self, receiver: Option<mpsc::Receiver<ScreenCapture>>) {
         self.frame_receiver = receiver;
     }
 
+    pub fn set_annotation_receiver(&mut self, receiver: Option<mpsc::Receiver<AnnotationEvent>>) {
+        self.annotation_receiver = receiver;
+        self.received_annotations.clear();
+        self.received_ephemeral.clear();
+    }
+
+    /// Frame corrente e annotazioni disegnate sopra (in coordinate di cattura): per il caster
+    /// l'ultimo frame della pipeline di cattura, oscurato come nella trasmissione (o l'anteprima
+    /// dell'editor se la cattura è ferma), per il receiver l'ultimo frame ricevuto.
+    fn annotated_frame(&self) -> Option<(egui::ColorImage, &[Annotation])> {
+        match self.mode {
+            AppMode::Caster => {
+                let latest = self.capture_pipeline.as_ref().and_then(CapturePipeline::latest_frame);
+                let frame = match latest {
+                    Some(latest) => {
+                        let mut frame = ScreenCapture::clone(&latest);
+                        frame.redact(&self.capture.get_redactions().lock().unwrap());
+                        egui::ColorImage::from_rgba_unmultiplied([frame.width as usize, frame.height as usize], &frame.data)
+                    }
+                    None => self.editor.captured_image.lock().unwrap().clone()?,
+                };
+                Some((frame, &self.editor.annotations))
+            }
+            AppMode::Receiver => {
+                let buffer = self.frame_buffer.lock().unwrap();
+                let last = buffer.back()?;
+                let frame = egui::ColorImage::from_rgba_unmultiplied(
+                    [last.width as usize, last.height as usize],
+                    &last.data,
+                );
+                Some((frame, &self.received_annotations))
+            }
+        }
+    }
+
+    /// Frame corrente con le annotazioni disegnate a piena risoluzione e il ritaglio applicato
+    pub fn take_snapshot(&self, ctx: &egui::Context) -> Result<image::RgbaImage, SnapshotError> {
+        let (frame, annotations) = self.annotated_frame().ok_or(SnapshotError::NoFrame)?;
+        Ok(snapshot::compose(ctx, &frame, annotations))
+    }
+
+    /// Esporta le annotazioni in SVG, con il frame corrente come sfondo se `svg_background` è attivo
+    pub fn export_svg(&self) -> Result<(), SnapshotError> {
+        let (frame, annotations) = self.annotated_frame().ok_or(SnapshotError::NoFrame)?;
+        let background = self.svg_background.then_some(&frame);
+        let document = svg::to_svg(annotations, frame.size, background)?;
+        std::fs::write(&self.svg_path, document)?;
+        Ok(())
+    }
+
     pub fn update_receiver_ui(&mut self, ctx: &egui::Context) {
+        if let Some(ref receiver) = self.annotation_receiver {
+            for event in receiver.try_iter() {
+                match event {
+                    AnnotationEvent::Ephemeral(event) => self.received_ephemeral.apply(event),
+                    event => event.apply(&mut self.received_annotations),
+                }
+            }
+        }
+
         if let Some(ref receiver) = self.frame_receiver {
-            if let Ok(frame) = receiver.try_recv() {
+            // Si svuota il canale a ogni repaint: tutti i frame vanno nel buffer, ma si mostra solo l'ultimo
+            let frames: Vec<ScreenCapture> = receiver.try_iter().collect();
+            // I frame arrivati tra due repaint sono rimasti in coda: il caster lo usa per adattare la qualità
+            if let Some(network) = &self.receiver {
+                network.set_queue_depth(frames.len().saturating_sub(1));
+            }
+            if let Some(frame) = frames.last() {
                 let texture = ctx.load_texture(
                     "received_frame",
                     egui::ColorImage::from_rgba_unmultiplied(
@@ -123,9 +338,11 @@ impl MyApp {
                     egui::TextureOptions::LINEAR,
                 );
                 self.texture = Some(texture);
+            }
 
-                // Aggiungi il frame al buffer
-                let mut buffer = self.frame_buffer.lock().unwrap();
+            // Aggiungi i frame al buffer
+            let mut buffer = self.frame_buffer.lock().unwrap();
+            for frame in frames {
                 buffer.push_back(frame);
                 if buffer.len() > 20 { // Mantieni solo gli ultimi 10 frame
                     buffer.pop_front();
@@ -177,225 +394,160 @@ impl MyApp {
         status
     }
     
-    pub fn set_caster(&mut self, value: bool) {
-        self.mode = if value { AppMode::Caster } else { AppMode::Receiver };
-    }
-
     pub fn is_caster(&self) -> bool {
         matches!(self.mode, AppMode::Caster)
     }
 
-    pub fn start_broadcast(&mut self) {
-        println!("Starting broadcast...");
-        self.flags.set_broadcasting(true);
-
-        let capture_area = self.capture.get_capture_area().cloned().filter(|area| area.is_valid());
-        let broadcast_flag = Arc::new(Mutex::new(true));
-
-        let (tx, rx) = mpsc::channel();
-        self.network.set_broadcast_stop_tx(Some(tx));
-
-        std::thread::spawn(move || {
-            caster_ui::start_broadcast_thread(broadcast_flag, rx, capture_area);
-        });
-    }
-
-    pub fn stop_broadcast(&mut self) {
-        println!("Stopping broadcast...");
-        self.flags.set_broadcasting(false);
-
-        if let Some(tx) = self.network.get_broadcast_stop_tx() {
-            if let Err(e) = tx.send(()) {
-                println!("Failed to send stop signal: {:?}", e);
-            }
+    /// Monitor e area da catturare secondo le impostazioni correnti
+    fn capture_source(&self) -> CaptureSource {
+        CaptureSource {
+            display: self.monitor,
+            area: self.capture.get_capture_area().cloned().filter(|area| area.is_valid()),
         }
     }
 
-    pub fn start_recording(&mut self) {
-        println!("Starting recording...");
-        self.flags.set_recording(true);
-
-        let capture_area = self.capture.get_capture_area().cloned().filter(|area| area.is_valid());
-        let record_flag = Arc::new(Mutex::new(true));
-
-        let (tx, rx) = mpsc::channel();
-        self.network.set_record_stop_tx(Some(tx));
-
-        let (width, height) = caster_ui::get_capture_dimensions(&capture_area);
-        caster_ui::create_recording_directory("recordings");
-
-        std::thread::spawn(move || {
-            caster_ui::start_record_thread(record_flag, rx, capture_area, width, height);
-        });
+    /// Cattura il monitor `display` (il principale se None); le maschere di privacy seguono il monitor
+    pub fn select_monitor(&mut self, display: Option<usize>) {
+        self.monitor = display;
+        self.monitor_id = monitor_id(display);
     }
 
-    pub fn stop_recording(&mut self) {
-        println!("Stopping recording...");
-        self.flags.set_recording(false);
+    /// True se trasmissione o registrazione dello schermo stanno usando la pipeline di cattura
+    fn is_capture_pipeline_in_use(&self) -> bool {
+        self.flags.is_broadcasting() || (self.flags.is_recording() && self.is_caster())
+    }
 
-        if let Some(tx) = self.network.get_record_stop_tx() {
-            if let Err(e) = tx.send(()) {
-                println!("Failed to send stop signal: {:?}", e);
+    /// Pipeline di cattura condivisa da preview, trasmissione, registrazione e snapshot.
+    /// Viene (ri)avviata se non esiste, se si è fermata o se l'area di cattura è cambiata;
+    /// con una trasmissione o una registrazione in corso la nuova area vale dal prossimo avvio.
+    pub fn capture_pipeline(&mut self) -> crate::core::error::Result<&CapturePipeline> {
+        let source = self.capture_source();
+        let in_use = self.is_capture_pipeline_in_use();
+        let reusable = self
+            .capture_pipeline
+            .as_ref()
+            .is_some_and(|pipeline| pipeline.is_running() && (in_use || pipeline.source().same_region(&source)));
+        if !reusable {
+            // La preview si ricollega alla nuova pipeline al prossimo aggiornamento
+            self.caster_preview = None;
+            if let Some(pipeline) = self.capture_pipeline.take() {
+                pipeline.stop();
             }
         }
-    }
-
-    pub fn start_recording_receiver(&mut self) {
-        println!("Starting recording in receiver mode...");
-        self.flags.set_recording(true);
 
-        let record_flag = Arc::new(Mutex::new(true));
-        let (tx, rx) = mpsc::channel();
-        self.network.set_record_stop_tx(Some(tx));
-
-        let (width, height) = if let Some(texture) = &self.texture {
-            (texture.size()[0], texture.size()[1])
-        } else {
-            (1920, 1080) // Dimensioni predefinite
+        let pipeline = match self.capture_pipeline.take() {
+            Some(pipeline) => pipeline,
+            None => CapturePipeline::start(source, self.capture.get_privacy_masks(), Some(self.worker_errors_tx.clone()))?,
         };
-
-        let frame_buffer = Arc::clone(&self.frame_buffer);
-
-        thread::spawn(move || {
-            start_record_thread_for_receiver(record_flag, rx, frame_buffer, width, height);
-        });
+        Ok(self.capture_pipeline.insert(pipeline))
     }
-    
 
-    pub fn stop_recording_receiver(&mut self) {
-        println!("Stopping recording in receiver mode...");
-        self.flags.set_recording(false);
+    /// Passa all'editor l'ultimo frame della pipeline di cattura, collegando la preview se serve
+    fn update_caster_preview(&mut self, ctx: &egui::Context) {
+        // Se l'area di cattura è cambiata, la preview si ricollega a una pipeline con la nuova area
+        let source = self.capture_source();
+        let area_changed = self
+            .capture_pipeline
+            .as_ref()
+            .is_some_and(|pipeline| !pipeline.source().same_region(&source));
+        if area_changed && !self.is_capture_pipeline_in_use() {
+            self.caster_preview = None;
+        }
 
-        if let Some(tx) = self.network.get_record_stop_tx() {
-            if let Err(e) = tx.send(()) {
-                println!("Failed to send stop signal: {:?}", e);
+        // Se la cattura non si può avviare la preview resta vuota, senza riprovare a ogni repaint
+        if self.caster_preview.is_none() && !self.caster_preview_failed {
+            match self.capture_pipeline().map(|pipeline| pipeline.subscribe(PREVIEW_FPS)) {
+                Ok(preview) => self.caster_preview = Some(preview),
+                Err(e) => {
+                    error!("Failed to start preview: {}", e);
+                    self.toasts.error(format!("Preview unavailable: {}", e));
+                    self.caster_preview_failed = true;
+                }
             }
         }
-    }
 
-    pub fn stop_receiving(&mut self) {
-        println!("Stopping receiving...");
-        self.flags.set_receiving(false);
-
-        if let Some(tx) = self.network.get_stop_tx() {
-            if let Err(e) = tx.send(()) {
-                println!("Failed to send stop signal: {:?}", e);
+        let Some(preview) = &self.caster_preview else {
+            return;
+        };
+        match preview.try_latest() {
+            Ok(Some(shared_frame)) => {
+                // La preview mostra il frame già oscurato, come lo vedono i receiver
+                let mut frame = ScreenCapture::clone(&shared_frame.frame);
+                frame.redact(&self.capture.get_redactions().lock().unwrap());
+                let color_image = egui::ColorImage::from_rgba_unmultiplied(
+                    [frame.width as usize, frame.height as usize],
+                    &frame.data,
+                );
+                self.editor.set_frame(ctx, color_image);
+            }
+            Ok(None) => {}
+            Err(_) => {
+                // La pipeline si è fermata per un errore, già mostrato come notifica
+                debug!("Capture pipeline stopped, preview disconnected");
+                self.caster_preview = None;
+                self.caster_preview_failed = true;
             }
         }
     }
 
-    fn handle_hotkey_action(&mut self, action: HotkeyAction) {
-        match action {
-            HotkeyAction::StartPauseBroadcast => {
-                if self.flags.is_broadcasting() {
-                    self.stop_broadcast();
-                } else {
-                    self.start_broadcast();
-                }
-            }
-            HotkeyAction::StartStopRecording => {
-                if self.flags.is_recording() {
-                    self.stop_recording();
-                } else {
-                    self.start_recording();
-                }
-            }
-            HotkeyAction::LockUnlockScreen => {
-                let new_state = !self.flags.is_screen_locked();
-                self.flags.set_screen_locked(new_state);
-                println!("Screen lock toggled: {}", new_state);
-            }
-            HotkeyAction::ToggleAnnotation => {
-                self.flags.set_annotation_tools_active(!self.flags.is_annotation_tools_active());
-            }
-            HotkeyAction::QuickCaptureSelection => {
-                self.ui_state.set_selecting_area(true);
-            }
-            HotkeyAction::EndSession => {
-                std::process::exit(0);
-            }
-            HotkeyAction::SwitchMonitor => {
-                self.ui_state.set_showing_monitor_selection(true);
+    /// Scollega la preview e, se nessuna trasmissione o registrazione la usa, ferma la cattura
+    pub fn stop_caster_preview(&mut self) {
+        self.caster_preview = None;
+        self.caster_preview_failed = false;
+        if !self.is_capture_pipeline_in_use() {
+            if let Some(pipeline) = self.capture_pipeline.take() {
+                pipeline.stop();
             }
         }
     }
 
-
-    pub fn start_caster_preview_thread(&mut self) {
-        // Se stiamo già girando, non ricreare il thread
-        if self.caster_preview_rx.is_some() {
-            return;
+    /// Aggiorna lo stato quando trasmissione, registrazione o ricezione terminano da sole
+    /// (per un errore o perché il caster ha chiuso) e mostra gli errori dei thread di lavoro
+    fn poll_workers(&mut self) {
+        if self.flags.is_broadcasting() && !self.caster.as_ref().is_some_and(Caster::is_running) {
+            caster_ui::stop_broadcast(self);
         }
-
-        // Prende l'area di cattura (se impostata)
-        let capture_area = self
-            .capture
-            .get_capture_area()
-            .cloned()
-            .filter(|area| area.is_valid());
-
-        // Crea un canale per fermare il thread
-        let (stop_tx, stop_rx) = mpsc::channel::<()>();
-        self.caster_preview_stop = Some(stop_tx);
-
-        // Crea un canale per inviare i frame al main thread
-        let (frame_tx, frame_rx) = mpsc::channel::<ScreenCapture>();
-        self.caster_preview_rx = Some(frame_rx);
-
-        // Avvia il thread vero e proprio
-        let handle = std::thread::spawn(move || {
-            // Crea un capturer (puoi riutilizzare la stessa area)
-            let mut capturer = ScreenCapturer::new(capture_area);
-
-            // Thread loop
-            loop {
-                // Controlla se c’è un segnale di stop
-                if stop_rx.try_recv().is_ok() {
-                    println!("Caster preview thread: Received stop signal, exiting...");
-                    break;
-                }
-
-                // Cattura frame
-                if let Some(frame) = capturer.capture_frame() {
-                    // println!(
-                    //     "Captured preview frame: {}x{}, data.len = {}",
-                    //     frame.width,
-                    //     frame.height,
-                    //     frame.data.len()
-                    // );
-                    // Manda il frame al main thread
-                    if frame_tx.send(frame).is_err() {
-                        // Se non c’è più nessuno in ascolto, termina
-                        break;
-                    }
-                }else {
-                    println!("Failed to capture frame (preview).");
-                }
-        
-
-                // Limitiamo a 5 fps (200ms)
-                std::thread::sleep(Duration::from_millis(200));
+        if self.flags.is_recording() && !self.recorder.as_ref().is_some_and(Recorder::is_running) {
+            self.flags.set_recording(false);
+            if let Some(recorder) = self.recorder.take() {
+                recorder.join();
             }
+        }
+        let receiver_status = self.receiver.as_ref().map(Receiver::status);
+        let receiver_ended = self.flags.is_receiving() && !self.receiver.as_ref().is_some_and(Receiver::is_running);
+        if receiver_ended {
+            // Senza frame in arrivo la registrazione del receiver non avrebbe più nulla da scrivere
+            if self.flags.is_recording() {
+                commands::stop_recording(self);
+            }
+            receiver_ui::stop_receiving(self);
+        }
 
-            println!("Caster preview thread: finished");
-        });
-
-        // Salviamo l’handle se vogliamo poter fare .join() in futuro
-        self.caster_preview_handle = Some(handle);
+        for error in self.worker_errors_rx.try_iter() {
+            self.toasts.error(error.to_string());
+        }
+        // Una ricezione terminata senza errori è stata chiusa dal caster
+        if receiver_ended && receiver_status == Some(WorkerStatus::Finished) {
+            self.toasts.info("The caster closed the connection");
+        }
     }
 
-    pub fn stop_caster_preview_thread(&mut self) {
-        if let Some(stop_tx) = self.caster_preview_stop.take() {
-            let _ = stop_tx.send(()); // Manda segnale di stop
+    /// Thread di lavoro attivi o terminati ma non ancora raccolti, mostrati nella diagnostica
+    pub fn worker_statuses(&self) -> Vec<(Worker, WorkerStatus)> {
+        let mut statuses = Vec::new();
+        if let Some(caster) = &self.caster {
+            statuses.push((Worker::Broadcast, caster.status()));
         }
-
-        if let Some(handle) = self.caster_preview_handle.take() {
-            let _ = handle.join(); // aspetta che il thread finisca
+        if let Some(recorder) = &self.recorder {
+            statuses.push((Worker::Recording, recorder.status()));
         }
-
-        // Svuota il receiver
-        self.caster_preview_rx = None;
-        //println!("Caster preview thread stopped");
+        if let Some(receiver) = &self.receiver {
+            statuses.push((Worker::Receiving, receiver.status()));
+        }
+        if let Some(pipeline) = &self.capture_pipeline {
+            statuses.push((Worker::Capture, pipeline.status()));
+        }
+        statuses
     }
 
 
@@ -413,9 +565,14 @@ impl App for MyApp {
                 caster_ui::render_caster_preview_window(ctx, self);
             }
 
+            self.poll_workers();
             if self.flags.is_receiving() {
                 self.update_receiver_ui(ctx);
             }
+
+            if self.ui_state.is_showing_stats_panel() {
+                stats_panel::render_stats_overlay(ctx, self);
+            }
     
             egui::CentralPanel::default().show(ctx, |ui| {
                 if self.ui_state.is_selecting_area() {
@@ -429,8 +586,10 @@ impl App for MyApp {
             });
     
             if let Ok(event) = global_hotkey::GlobalHotKeyEvent::receiver().try_recv() {
-                if let Some(action) = self.hotkeys.hotkey_map.get(&event.id).cloned() {
-                    self.handle_hotkey_action(action);
+                // Mentre si sta registrando una nuova combinazione le hotkey non eseguono azioni
+                let capturing = self.capturing_hotkey.is_some();
+                if let Some(action) = self.hotkeys.hotkey_map.get(&event.id).cloned().filter(|_| !capturing) {
+                    commands::execute(self, ctx, action.into());
                 }
             }
     
@@ -439,31 +598,20 @@ impl App for MyApp {
 
             //logica per mostrare preview con annotazioni lato caster
             if self.is_caster() {
-                // Se NON c’è ancora un thread di preview, avvialo
-                if self.caster_preview_rx.is_none() {
-                    self.start_caster_preview_thread();
-                }
-            
-                // Prova a ricevere un frame
-                if let Some(rx) = &self.caster_preview_rx {
-                    if let Ok(frame) = rx.try_recv() {
-                        // Converti in texture
-                        let color_image = egui::ColorImage::from_rgba_unmultiplied(
-                            [frame.width as usize, frame.height as usize],
-                            &frame.data,
-                        );
-                        let texture = ctx.load_texture(
-                            "caster_preview_texture",
-                            color_image,
-                            egui::TextureOptions::LINEAR,
-                        );
-                        self.texture = Some(texture);
-                    }
-                }
+                // Collega la preview alla pipeline di cattura e passa l'ultimo frame all'editor
+                self.update_caster_preview(ctx);
+
+                // Invia ai receiver le annotazioni modificate in questo frame
+                caster_ui::forward_annotation_events(self);
+                self.capture.set_privacy_masks(self.user_settings.get_privacy_masks(&self.monitor_id));
+                self.capture.set_stream_quality(*self.user_settings.get_stream_quality());
             } else {
-                // Non sei caster, quindi se c’è un thread di preview attivo, ferma tutto
-                self.stop_caster_preview_thread();
+                // Non sei caster, quindi scollega la preview e ferma la cattura
+                self.stop_caster_preview();
             }
+
+            self.toasts.show(ctx);
+            self.save_settings_if_changed();
             
 
         }
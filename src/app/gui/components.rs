@@ -2,6 +2,17 @@ use eframe::egui;
 use crate::app::gui::caster_ui;
 use crate::app::gui::receiver_ui;
 use super::app_main::MyApp;
+use crate::core::adaptive::AdaptiveBounds;
+use crate::core::network::FrameEncoding;
+use crate::core::quality::{OutputScale, StreamQuality, MAX_FRAMERATE, MIN_FRAMERATE};
+use crate::utils::logging::{self, LogLevel};
+use crate::utils::snapshot;
+use std::path::Path;
+
+// Larghezza proposta quando si passa a "Max width"
+const DEFAULT_MAX_WIDTH: u32 = 1280;
+// Qualità proposta quando si passa a JPEG
+const DEFAULT_JPEG_QUALITY: u8 = 80;
 // Funzione principale per il rendering della UI del Caster
 pub fn render_caster_ui(ui: &mut egui::Ui, app: &mut MyApp) {
     ui.group(|ui| {
@@ -12,12 +23,15 @@ pub fn render_caster_ui(ui: &mut egui::Ui, app: &mut MyApp) {
         caster_ui::render_multi_monitor_support_button(ui, app);
         caster_ui::render_broadcast_button(ui, app); // Pulsante per gestire il broadcasting
         caster_ui::render_record_button(ui, app); // Pulsante per gestire la registrazione
+        render_quality_settings(ui, app);
+        render_diagnostics(ui, app);
 
         ui.separator();
         if ui.button("Open Preview Window").clicked() {
             app.ui_state.set_showing_caster_preview_window(true);
         }
         caster_ui::render_annotation_toolbar(ui, app);
+        render_snapshot_controls(ui, app);
 
         ui.add_space(20.0);
         ui.separator();
@@ -34,6 +48,190 @@ pub fn render_receiver_ui(ui: &mut egui::Ui, app: &mut MyApp) {
 
         receiver_ui::render_receiver_address_input(ui, app);
         receiver_ui::render_receiving_button(ui, app);
+        render_quality_settings(ui, app);
+        render_diagnostics(ui, app);
+    });
+}
+
+
+/// Qualità della trasmissione (solo per il caster: framerate, risoluzione, formato dei frame e adattamento
+/// alla rete) e della registrazione.
+/// La trasmissione applica le modifiche subito, la registrazione dalla prossima avviata.
+pub fn render_quality_settings(ui: &mut egui::Ui, app: &mut MyApp) {
+    egui::CollapsingHeader::new("Quality").show(ui, |ui| {
+        egui::Grid::new("quality_settings").num_columns(3).show(ui, |ui| {
+            if app.is_caster() {
+                let stream = app.user_settings.get_stream_quality_mut();
+                ui.label("Broadcast:");
+                render_quality_row(ui, "broadcast", &mut stream.quality);
+                ui.end_row();
+                ui.label("Encoding:");
+                render_encoding_row(ui, &mut stream.encoding);
+                ui.end_row();
+                ui.label("Adaptive:");
+                render_adaptive_row(ui, &mut stream.adaptive, stream.encoding);
+                ui.end_row();
+            }
+            ui.label("Recording:");
+            ui.add_enabled_ui(!app.flags.is_recording(), |ui| {
+                render_quality_row(ui, "recording", &mut app.user_settings.get_recording_profile_mut().quality);
+            });
+            ui.end_row();
+        });
+
+        // Qualità scelta dalla trasmissione in corso, eventualmente ridotta rispetto alle impostazioni
+        if let Some(caster) = &app.caster {
+            if let Some(applied) = caster.stats().lock().unwrap().applied_quality {
+                ui.label(format!("Current broadcast quality: {}", applied));
+            }
+        }
+    });
+}
+
+fn render_encoding_row(ui: &mut egui::Ui, encoding: &mut FrameEncoding) {
+    ui.horizontal(|ui| {
+        let jpeg = matches!(encoding, FrameEncoding::Jpeg(_));
+        if ui.radio(!jpeg, "Raw").clicked() {
+            *encoding = FrameEncoding::Raw;
+        }
+        if ui.radio(jpeg, "JPEG").on_hover_text("Much less bandwidth, at some CPU cost").clicked() && !jpeg {
+            *encoding = FrameEncoding::Jpeg(DEFAULT_JPEG_QUALITY);
+        }
+        if let FrameEncoding::Jpeg(quality) = encoding {
+            ui.add(egui::Slider::new(quality, 1..=100).text("quality"));
+        }
+    });
+}
+
+/// Limiti minimi entro cui la trasmissione riduce la qualità quando i receiver non stanno al passo
+fn render_adaptive_row(ui: &mut egui::Ui, bounds: &mut AdaptiveBounds, encoding: FrameEncoding) {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut bounds.enabled, "Adapt to network")
+            .on_hover_text("Lower framerate, size and JPEG quality when receivers fall behind");
+        ui.add_enabled_ui(bounds.enabled, |ui| {
+            ui.label("Min:");
+            ui.add(egui::DragValue::new(&mut bounds.min_fps).range(MIN_FRAMERATE..=MAX_FRAMERATE).suffix(" fps"));
+            ui.add(egui::DragValue::new(&mut bounds.min_scale_percent).range(1..=100).suffix("% size"));
+            if matches!(encoding, FrameEncoding::Jpeg(_)) {
+                ui.add(egui::DragValue::new(&mut bounds.min_jpeg_quality).range(1..=100).prefix("JPEG "));
+            }
+        });
+    });
+}
+
+fn render_quality_row(ui: &mut egui::Ui, id: &str, quality: &mut StreamQuality) {
+    ui.add(egui::DragValue::new(&mut quality.fps).range(MIN_FRAMERATE..=MAX_FRAMERATE).suffix(" fps"));
+    ui.horizontal(|ui| {
+        let max_width = matches!(quality.scale, OutputScale::MaxWidth(_));
+        egui::ComboBox::from_id_source(id)
+            .selected_text(if max_width { "Max width".to_string() } else { quality.scale.to_string() })
+            .show_ui(ui, |ui| {
+                for preset in OutputScale::PRESETS {
+                    ui.selectable_value(&mut quality.scale, preset, preset.to_string());
+                }
+                if ui.selectable_label(max_width, "Max width").clicked() && !max_width {
+                    quality.scale = OutputScale::MaxWidth(DEFAULT_MAX_WIDTH);
+                }
+            });
+        if let OutputScale::MaxWidth(width) = &mut quality.scale {
+            ui.add(egui::DragValue::new(width).range(1..=u16::MAX as u32).suffix(" px"));
+        }
     });
 }
 
+/// Verbosità dei log e copia negli appunti del file di log, da allegare alle segnalazioni di bug
+pub fn render_diagnostics(ui: &mut egui::Ui, app: &mut MyApp) {
+    egui::CollapsingHeader::new("Diagnostics").show(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Log level:");
+            let from_env = logging::is_level_from_env();
+            let mut level = app.user_settings.get_log_level();
+            ui.add_enabled_ui(!from_env, |ui| {
+                egui::ComboBox::from_id_source("log_level")
+                    .selected_text(level.to_string())
+                    .show_ui(ui, |ui| {
+                        for option in LogLevel::ALL {
+                            ui.selectable_value(&mut level, option, option.to_string());
+                        }
+                    });
+            })
+            .response
+            .on_disabled_hover_text(format!("Set by the {} environment variable", logging::LOG_ENV_VAR));
+            if level != app.user_settings.get_log_level() {
+                app.user_settings.set_log_level(level);
+            }
+        });
+
+        if let Some(path) = logging::log_file_path() {
+            ui.label(format!("Log file: {}", path.display()));
+        }
+
+        // Stato dei thread di trasmissione, ricezione, registrazione e preview
+        let workers = app.worker_statuses();
+        if workers.is_empty() {
+            ui.label("Workers: none");
+        } else {
+            let workers: Vec<String> = workers.iter().map(|(worker, status)| format!("{} {}", worker, status)).collect();
+            ui.label(format!("Workers: {}", workers.join(", ")));
+        }
+        if ui.button("Copy Diagnostic Log").clicked() {
+            let result = logging::read_diagnostic_log().map_err(|e| e.to_string()).and_then(|log| {
+                let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+                clipboard.set_text(log).map_err(|e| e.to_string())
+            });
+            app.diagnostics_status = Some(match result {
+                Ok(()) => "Diagnostic log copied to the clipboard".to_string(),
+                Err(e) => format!("Failed to copy the diagnostic log: {}", e),
+            });
+        }
+        if let Some(status) = &app.diagnostics_status {
+            ui.label(status);
+        }
+    });
+}
+
+/// Snapshot del frame corrente con le annotazioni: salvataggio in PNG/JPEG, copia negli appunti
+/// o esportazione vettoriale in SVG
+pub fn render_snapshot_controls(ui: &mut egui::Ui, app: &mut MyApp) {
+    ui.horizontal(|ui| {
+        ui.label("Snapshot:");
+        ui.text_edit_singleline(&mut app.snapshot_path);
+
+        if ui.button("Save").clicked() {
+            let result = app
+                .take_snapshot(ui.ctx())
+                .and_then(|image| snapshot::save(&image, Path::new(&app.snapshot_path)));
+            app.snapshot_status = Some(match result {
+                Ok(()) => format!("Snapshot saved to {}", app.snapshot_path),
+                Err(e) => format!("Snapshot failed: {}", e),
+            });
+        }
+
+        if ui.button("Copy to clipboard").clicked() {
+            let result = app
+                .take_snapshot(ui.ctx())
+                .and_then(|image| snapshot::copy_to_clipboard(&image));
+            app.snapshot_status = Some(match result {
+                Ok(()) => "Snapshot copied to the clipboard".to_string(),
+                Err(e) => format!("Snapshot failed: {}", e),
+            });
+        }
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("SVG:");
+        ui.text_edit_singleline(&mut app.svg_path);
+        ui.checkbox(&mut app.svg_background, "Include frame");
+
+        if ui.button("Export").clicked() {
+            app.snapshot_status = Some(match app.export_svg() {
+                Ok(()) => format!("Annotations exported to {}", app.svg_path),
+                Err(e) => format!("SVG export failed: {}", e),
+            });
+        }
+    });
+
+    if let Some(status) = &app.snapshot_status {
+        ui.label(status);
+    }
+}
@@ -3,3 +3,5 @@ pub mod visuals;
 pub mod components;
 pub mod caster_ui;
 pub mod receiver_ui;
+pub mod stats_panel;
+pub mod toasts;
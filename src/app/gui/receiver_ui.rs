@@ -1,16 +1,14 @@
 use eframe::egui;
-use std::sync::{mpsc, Arc, Mutex};
-use std::thread;
-use crate::app::network::start_client;
-use crate::app::capture::ScreenCapture;
+use std::sync::{mpsc, Arc};
+use crate::core::network::StreamMessage;
+use crate::core::receiver::Receiver;
+use crate::core::recorder::Recorder;
+use crate::utils::annotations::Annotation;
+use eframe::emath::RectTransform;
 use super::app_main::MyApp;
-use std::process::Command;
-use crate::app::gui::caster_ui::{TARGET_FRAMERATE, FRAME_DURATION};
-use std::process::Stdio;
-use std::io::Write;
-use std::time::Instant;
-use std::collections::VecDeque;
+use crate::app::commands;
 use local_ip_address::local_ip;
+use log::{debug, error, info};
 
 
 pub fn render_receiver_address_input(ui: &mut egui::Ui, app: &mut MyApp) {
@@ -34,6 +32,13 @@ pub fn render_receiver_address_input(ui: &mut egui::Ui, app: &mut MyApp) {
             ui.add_enabled_ui(!app.network.auto_detect_ip, |ui| {
                 ui.text_edit_singleline(&mut app.network.address);
             });
+
+            ui.label("Porta:");
+            ui.add(egui::DragValue::new(&mut app.network.port).range(1..=u16::MAX));
+
+            // Vuota se il caster non richiede una password
+            ui.label("Password:");
+            ui.add(egui::TextEdit::singleline(&mut app.network.password).password(true).desired_width(100.0));
         });
     });
     ui.add_space(10.0);
@@ -60,127 +65,92 @@ pub fn render_receiving_button(ui: &mut egui::Ui, app: &mut MyApp) {
                 .strong()
         ).fill(button_color)
     ).clicked() {
-        handle_receiving_button_click(app);
-    }
-}
-
-fn handle_receiving_button_click(app: &mut MyApp) {
-    if app.flags.is_receiving() {
-        stop_receiving(app);
-    } else {
-        start_receiving(app);
+        commands::execute(app, ui.ctx(), commands::Command::ToggleReceiving);
     }
 }
 
-fn start_receiving(app: &mut MyApp) {
-    
-    println!("Starting receiving...");
-    app.flags.set_receiving(true);
-
-    let receiving_flag = Arc::new(Mutex::new(true));
-    let receiving_flag_clone = Arc::clone(&receiving_flag);
+pub fn start_receiving(app: &mut MyApp) {
+    info!("Starting receiving...");
 
     let (frame_tx, frame_rx) = mpsc::channel();
-    app.set_frame_receiver(Some(frame_rx));
-
-    let receiver_address = app.network.get_address().to_string();
-
-    thread::spawn(move || {
-        start_client(&receiver_address, receiving_flag_clone, move |frame: ScreenCapture| {
-            if frame_tx.send(frame).is_err() {
-                return;
-            }
-        });
+    let (annotation_tx, annotation_rx) = mpsc::channel();
+
+    let password = Some(app.network.password.as_str()).filter(|password| !password.is_empty());
+    let result = Receiver::connect(app.network.get_address(), app.network.get_port(), password, Some(app.worker_errors_tx.clone()), move |message| {
+        let _ = match message {
+            StreamMessage::Frame(frame) => frame_tx.send(frame).is_ok(),
+            StreamMessage::Annotation(event) => annotation_tx.send(event).is_ok(),
+        };
     });
 
-    println!("Receiving thread started");
+    match result {
+        Ok(receiver) => {
+            app.receiver = Some(receiver);
+            app.set_frame_receiver(Some(frame_rx));
+            app.set_annotation_receiver(Some(annotation_rx));
+            app.flags.set_receiving(true);
+            debug!("Receiving thread started");
+        }
+        Err(e) => {
+            error!("Failed to connect to caster: {}", e);
+            app.toasts.error(format!("Cannot start receiving: {}", e));
+        }
+    }
 }
 
-fn stop_receiving(app: &mut MyApp) {
-    println!("Stopping receiving...");
+pub fn stop_receiving(app: &mut MyApp) {
+    info!("Stopping receiving...");
     app.flags.set_receiving(false);
 
-    if let Some(tx) = app.network.get_stop_tx() {
-        if let Err(e) = tx.send(()) {
-            println!("Failed to send stop signal: {:?}", e);
-        }
+    if let Some(receiver) = app.receiver.take() {
+        receiver.stop();
     }
     app.set_frame_receiver(None);
+    app.set_annotation_receiver(None);
 }
 
-pub fn start_record_thread_for_receiver(
-    record_flag: Arc<Mutex<bool>>,
-    rx: mpsc::Receiver<()>,
-    frame_buffer: Arc<Mutex<VecDeque<ScreenCapture>>>, // Usa il buffer condiviso
-    width: usize,
-    height: usize,
-) {
-    println!("Record thread for receiver started");
-
-    let mut child = Command::new("ffmpeg")
-        .args(&[
-            "-loglevel", "quiet",
-            "-f", "rawvideo",
-            "-pixel_format", "rgb0",
-            "-video_size", &format!("{}x{}", width, height),
-            "-framerate", &TARGET_FRAMERATE.to_string(),
-            "-i", "-",
-            "-c:v", "libx264",
-            "-preset", "ultrafast",
-            "-crf", "0",
-            "-pix_fmt", "yuv420p",
-            "-r", &TARGET_FRAMERATE.to_string(),
-            "-y", "recordings/receiver_recorded_video.mp4"
-        ])
-        .stdin(Stdio::piped())
-        .spawn()
-        .expect("Failed to start ffmpeg process");
-
-    let mut out = child.stdin.take().expect("Failed to open stdin");
-    let mut start_time = Instant::now();
-
-    while *record_flag.lock().unwrap() {
-        if rx.try_recv().is_ok() {
-            println!("Received stop signal, stopping recording...");
-            break;
-        }
+/// Registra i frame ricevuti dal caster
+pub fn start_record(app: &mut MyApp) {
+    info!("Starting recording in receiver mode...");
 
-        if let Some(frame) = frame_buffer.lock().unwrap().pop_front() {
-            let stride = frame.data.len() / height;
-            let rowlen = 4 * width;
-            for row in frame.data.chunks(stride) {
-                let row = &row[..rowlen];
-                if out.write_all(row).is_err() {
-                    println!("Failed to write frame to ffmpeg");
-                    break;
-                }
-            }
-        } else {
-            // Attendi un breve periodo se non ci sono frame disponibili
-            std::thread::sleep(std::time::Duration::from_millis(10));
-        }
-
-        // Sincronizza il framerate
-        let elapsed = start_time.elapsed();
-        if elapsed < FRAME_DURATION {
-            std::thread::sleep(FRAME_DURATION - elapsed);
-        }
-        start_time = Instant::now();
-    }
+    let (width, height) = if let Some(texture) = &app.texture {
+        (texture.size()[0], texture.size()[1])
+    } else {
+        (1920, 1080) // Dimensioni predefinite
+    };
 
-    println!("Flushing and closing ffmpeg...");
-    drop(out);
+    let profile = app.user_settings.get_recording_profile().clone();
+    let output = profile.output_path("receiver_recorded_video.mp4");
 
-    match child.wait() {
-        Ok(status) => {
-            println!("ffmpeg exited with status: {:?}", status);
+    match Recorder::record_frames(&profile, &output, Arc::clone(&app.frame_buffer), width, height, Some(app.worker_errors_tx.clone())) {
+        Ok(recorder) => {
+            app.recorder = Some(recorder);
+            app.flags.set_recording(true);
         }
         Err(e) => {
-            println!("Failed to wait on ffmpeg child process: {}", e);
+            error!("Failed to start recording: {}", e);
+            app.toasts.error(format!("Cannot start recording: {}", e));
         }
     }
-
-    println!("Record thread for receiver exiting");
 }
 
-
+/// Disegna le annotazioni ricevute dal caster sopra il frame mostrato in `rect`.
+/// Le annotazioni sono in coordinate di cattura, quindi restano nitide a qualsiasi zoom.
+pub fn render_received_annotations(ui: &mut egui::Ui, app: &MyApp, rect: egui::Rect) {
+    let Some(texture) = &app.texture else { return; };
+    let src_rect = egui::Rect::from_min_size(egui::Pos2::ZERO, texture.size_vec2());
+    let to_screen = RectTransform::from_to(src_rect, rect);
+
+    let painter = ui.painter_at(rect);
+    let shapes: Vec<egui::Shape> = app
+        .received_annotations
+        .iter()
+        .filter(|ann| !matches!(ann, Annotation::Crop(_)))
+        .map(|ann| ann.render(to_screen.scale()[0], to_screen, &painter, false))
+        .collect();
+    painter.extend(shapes);
+    painter.extend(app.received_ephemeral.render(to_screen));
+    if app.received_ephemeral.is_active() {
+        ui.ctx().request_repaint();
+    }
+}
@@ -0,0 +1,137 @@
+// src/app/gui/stats_panel.rs
+
+use eframe::egui;
+use std::time::Duration;
+
+use super::app_main::MyApp;
+use crate::core::stats::{CasterStats, History, ReceiverStats};
+
+const SPARKLINE_SIZE: egui::Vec2 = egui::vec2(120.0, 24.0);
+// Le metriche cambiano a ogni frame, ma basta aggiornare il pannello qualche volta al secondo
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Pannello sovrapposto (in alto a destra) con le metriche della trasmissione o della ricezione in corso
+pub fn render_stats_overlay(ctx: &egui::Context, app: &MyApp) {
+    egui::Area::new(egui::Id::new("stats_overlay"))
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10.0, 10.0))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(egui::RichText::new("Stream Statistics").strong());
+                ui.separator();
+
+                if let Some(caster) = &app.caster {
+                    render_caster_stats(ui, &caster.stats().lock().unwrap());
+                } else if let Some(receiver) = &app.receiver {
+                    render_receiver_stats(ui, &receiver.stats().lock().unwrap());
+                } else {
+                    ui.label("No active stream.");
+                }
+            });
+        });
+    ctx.request_repaint_after(REFRESH_INTERVAL);
+}
+
+fn render_caster_stats(ui: &mut egui::Ui, stats: &CasterStats) {
+    egui::Grid::new("caster_stats").num_columns(3).show(ui, |ui| {
+        render_fps_row(ui, &stats.framerate.fps, Some(stats.target_fps));
+        render_metric_row(ui, "Capture", &stats.capture_ms, "ms");
+        render_metric_row(ui, "Serialize", &stats.serialize_ms, "ms");
+        render_bytes_row(ui, &stats.frame_bytes);
+        for (addr, send_ms) in &stats.send_ms {
+            render_metric_row(ui, &format!("Send {}", addr), send_ms, "ms");
+        }
+    });
+
+    if let Some(applied) = stats.applied_quality {
+        ui.label(format!("Quality: {}", applied));
+    }
+    if stats.send_ms.is_empty() {
+        ui.label("No receivers connected.");
+    }
+    let overload = if stats.overloaded_frames > 0 { egui::Color32::YELLOW } else { ui.visuals().text_color() };
+    ui.colored_label(
+        overload,
+        format!("Frames over budget: {} of {}", stats.overloaded_frames, stats.frames),
+    );
+}
+
+fn render_receiver_stats(ui: &mut egui::Ui, stats: &ReceiverStats) {
+    egui::Grid::new("receiver_stats").num_columns(3).show(ui, |ui| {
+        render_fps_row(ui, &stats.framerate.fps, None);
+        render_metric_row(ui, "Decode", &stats.decode_ms, "ms");
+        render_bytes_row(ui, &stats.frame_bytes);
+        render_metric_row(ui, "Latency", &stats.latency_ms, "ms")
+            .on_hover_text("Measured with the caster's capture timestamps: requires synchronized clocks");
+    });
+    ui.label(format!("Frames received: {}", stats.frames));
+}
+
+/// Framerate ottenuto, confrontato con quello richiesto quando è noto (il receiver non lo conosce)
+fn render_fps_row(ui: &mut egui::Ui, fps: &History, target: Option<u32>) {
+    let value = fps.average().unwrap_or(0.0);
+    // Sotto il 90% del framerate target il valore viene evidenziato
+    let color = match target {
+        Some(target) if !fps.samples().is_empty() && value < target as f32 * 0.9 => egui::Color32::YELLOW,
+        _ => ui.visuals().text_color(),
+    };
+    ui.label("FPS");
+    match target {
+        Some(target) => ui.colored_label(color, format!("{:.1} / {}", value, target)),
+        None => ui.colored_label(color, format!("{:.1}", value)),
+    };
+    sparkline(ui, fps, target.map(|target| target as f32));
+    ui.end_row();
+}
+
+fn render_bytes_row(ui: &mut egui::Ui, frame_bytes: &History) {
+    ui.label("Frame size");
+    ui.label(format!("{:.0} KiB", frame_bytes.last().unwrap_or(0.0) / 1024.0));
+    sparkline(ui, frame_bytes, None);
+    ui.end_row();
+}
+
+fn render_metric_row(ui: &mut egui::Ui, name: &str, history: &History, unit: &str) -> egui::Response {
+    let response = ui.label(name);
+    ui.label(format!(
+        "{:.1} {} (max {:.1})",
+        history.average().unwrap_or(0.0),
+        unit,
+        history.max().unwrap_or(0.0)
+    ));
+    sparkline(ui, history, None);
+    ui.end_row();
+    response
+}
+
+/// Piccolo grafico dei valori più recenti, scalato sul massimo. `reference` disegna una linea
+/// orizzontale di riferimento, ad esempio il framerate target.
+fn sparkline(ui: &mut egui::Ui, history: &History, reference: Option<f32>) {
+    let (rect, _) = ui.allocate_exact_size(SPARKLINE_SIZE, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let samples = history.samples();
+    let max = samples
+        .iter()
+        .copied()
+        .chain(reference)
+        .fold(f32::EPSILON, f32::max);
+    let to_y = |value: f32| rect.bottom() - (value / max).clamp(0.0, 1.0) * rect.height();
+
+    if let Some(reference) = reference {
+        let y = to_y(reference);
+        painter.hline(rect.x_range(), y, egui::Stroke::new(1.0, egui::Color32::DARK_GRAY));
+    }
+
+    if samples.len() < 2 {
+        return;
+    }
+    let step = rect.width() / (samples.len() - 1) as f32;
+    let points = samples
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| egui::pos2(rect.left() + i as f32 * step, to_y(value)))
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, ui.visuals().selection.bg_fill)));
+}
@@ -0,0 +1,84 @@
+// src/app/gui/toasts.rs
+
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+// Le notifiche informative spariscono da sole, gli errori restano finché non vengono chiusi
+const INFO_DURATION: Duration = Duration::from_secs(4);
+const MAX_TOASTS: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ToastKind {
+    Info,
+    Error,
+}
+
+struct Toast {
+    kind: ToastKind,
+    message: String,
+    created: Instant,
+}
+
+/// Notifiche mostrate in basso a destra, ad esempio quando una trasmissione o una registrazione si interrompe
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Info, message.into());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(ToastKind::Error, message.into());
+    }
+
+    fn push(&mut self, kind: ToastKind, message: String) {
+        // Lo stesso errore ripetuto (ad esempio a ogni tentativo di avvio) compare una sola volta
+        if self.toasts.iter().any(|toast| toast.kind == kind && toast.message == message) {
+            return;
+        }
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.remove(0);
+        }
+        self.toasts.push(Toast { kind, message, created: Instant::now() });
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        self.toasts
+            .retain(|toast| toast.kind == ToastKind::Error || toast.created.elapsed() < INFO_DURATION);
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for (index, toast) in self.toasts.iter().enumerate() {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.set_max_width(320.0);
+                        ui.horizontal(|ui| {
+                            match toast.kind {
+                                ToastKind::Error => ui.colored_label(ui.visuals().error_fg_color, &toast.message),
+                                ToastKind::Info => ui.label(&toast.message),
+                            };
+                            if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                                dismissed = Some(index);
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(index) = dismissed {
+            self.toasts.remove(index);
+        }
+
+        // Le notifiche informative scadono anche se l'utente non muove il mouse
+        if self.toasts.iter().any(|toast| toast.kind == ToastKind::Info) {
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
+    }
+}
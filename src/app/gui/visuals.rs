@@ -1,9 +1,13 @@
 use eframe::egui;
-use super::app_main::{MyApp, Theme};
-use super::components::{render_caster_ui, render_receiver_ui};
-use super::receiver_ui::render_receiving_button;
-use crate::app::capture::CaptureArea;
-use crate::utils::multi_monitor::get_available_monitors;
+use super::app_main::{AppMode, MyApp};
+use crate::app::settings_file::Theme;
+use crate::app::commands::{self, Command};
+use super::components::{render_caster_ui, render_receiver_ui, render_snapshot_controls};
+use super::receiver_ui::render_received_annotations;
+use crate::core::capture::CaptureArea;
+use crate::utils::multi_monitor::{get_available_monitors, monitor_size};
+use crate::app::hotkey_action::HotkeyAction;
+use log::{debug, info, trace};
 
 
 /// Pannello di selezione del monitor con un pulsante "Indietro" migliorato e pulsanti accattivanti per la selezione del monitor.
@@ -48,7 +52,9 @@ pub fn monitor_selection_panel(ctx: &egui::Context, app: &mut MyApp) {
                     .rounding(10.0)  // Angoli arrotondati
                     .stroke(egui::Stroke::new(2.0, egui::Color32::WHITE)),  // Contorno bianco
                 ).clicked() {
+                    app.select_monitor(Some(index));
                     app.capture.set_capture_area(Some(CaptureArea::new(0, 0, display.width(), display.height())));
+                    app.capture.set_fullscreen(true);
                     app.ui_state.set_showing_monitor_selection(false);  // Torna al menu principale dopo la selezione
                 }
 
@@ -81,13 +87,10 @@ pub fn capture_area_panel(ctx: &egui::Context, app: &mut MyApp) {
 
     // Aggiorna il flag `is_fullscreen` in base all'area selezionata
     if let Some(capture_area) = app.capture.get_capture_area() {
-        let display = scrap::Display::primary().unwrap();
-        app.capture.set_fullscreen(
-            capture_area.x == 0
-                && capture_area.y == 0
-                && capture_area.width == display.width()
-                && capture_area.height == display.height(),
-        );
+        let is_fullscreen = monitor_size(app.monitor).is_some_and(|(width, height)| {
+            capture_area.x == 0 && capture_area.y == 0 && capture_area.width == width && capture_area.height == height
+        });
+        app.capture.set_fullscreen(is_fullscreen);
     } else {
         app.capture.set_fullscreen(true);
     }
@@ -112,7 +115,7 @@ fn handle_area_selection(ui: &mut egui::Ui, app: &mut MyApp) {
     if let Some(capture_area) = app.capture.get_capture_area_mut() {
         if response.drag_started() {
             capture_area.drag_state.start_pos = Some(response.interact_pointer_pos().unwrap());
-            println!("Drag started at {:?}", capture_area.drag_state.start_pos);
+            trace!("Drag started at {:?}", capture_area.drag_state.start_pos);
         }
 
         if response.dragged() {
@@ -129,20 +132,17 @@ fn handle_area_selection(ui: &mut egui::Ui, app: &mut MyApp) {
                 capture_area.width = (end.x - start.x).abs() as usize;
                 capture_area.height = (end.y - start.y).abs() as usize;
 
-                println!("Area selected: {:?}", capture_area);
+                debug!("Area selected: {:?}", capture_area);
             }
         }
     }
 
     // Una volta rilasciato il primo borrow mutabile, possiamo accedere di nuovo a `app.capture`
     if let Some(capture_area) = app.capture.get_capture_area() {
-        let display = scrap::Display::primary().unwrap();
-        app.capture.set_fullscreen(
-            capture_area.x == 0
-                && capture_area.y == 0
-                && capture_area.width == display.width()
-                && capture_area.height == display.height(),
-        );
+        let is_fullscreen = monitor_size(app.monitor).is_some_and(|(width, height)| {
+            capture_area.x == 0 && capture_area.y == 0 && capture_area.width == width && capture_area.height == height
+        });
+        app.capture.set_fullscreen(is_fullscreen);
     }
 }
 
@@ -174,11 +174,16 @@ fn render_fullscreen_button(ui: &mut egui::Ui, app: &mut MyApp) {
             )
             .fill(if is_fullscreen { egui::Color32::from_rgb(100, 100, 100) } else { egui::Color32::from_rgb(0, 153, 0) }),
         ).clicked() {
-            let display = scrap::Display::primary().unwrap();
-            let fullscreen_area = CaptureArea::new(0, 0, display.width(), display.height());
-            app.capture.set_capture_area(Some(fullscreen_area));
-            app.ui_state.set_selecting_area(false); // Esci dalla modalità di selezione
-            println!("Fullscreen selected");
+            match monitor_size(app.monitor) {
+                Some((width, height)) => {
+                    let fullscreen_area = CaptureArea::new(0, 0, width, height);
+                    app.capture.set_capture_area(Some(fullscreen_area));
+                    app.capture.set_fullscreen(true);
+                    app.ui_state.set_selecting_area(false); // Esci dalla modalità di selezione
+                    info!("Fullscreen selected");
+                }
+                None => app.toasts.error("Cannot access the selected monitor"),
+            }
         }
     });
 }
@@ -200,7 +205,7 @@ fn render_confirm_selection_button(ui: &mut egui::Ui, app: &mut MyApp) {
             .fill(if is_valid_selection { egui::Color32::from_rgb(51, 153, 255) } else { egui::Color32::from_rgb(100, 100, 100) }),
         ).clicked() {
             app.ui_state.set_selecting_area(false); // Esci dalla modalità di selezione
-            println!("Area confirmed: {:?}", app.capture.get_capture_area());
+            info!("Area confirmed: {:?}", app.capture.get_capture_area());
         }
     });
 }
@@ -244,7 +249,7 @@ fn handle_selection_confirmation_dialog(ctx: &egui::Context, app: &mut MyApp) {
                         }
                         app.ui_state.set_selecting_area(false);
                         app.ui_state.set_show_confirmation_dialog(false);
-                        println!("Selection cancelled");
+                        debug!("Selection cancelled");
                     }
                 });
             });
@@ -298,6 +303,9 @@ fn render_show_shortcuts_button(ui: &mut egui::Ui, app: &mut MyApp) {
         if ui.button("Show Shortcuts").clicked() {
             app.ui_state.set_showing_shortcuts_menu(true);
         }
+        if ui.button(Command::ToggleStatsPanel.label(app)).clicked() {
+            commands::execute(app, ui.ctx(), Command::ToggleStatsPanel);
+        }
     });
 }
 
@@ -306,51 +314,120 @@ fn render_shortcuts_window(ctx: &egui::Context, app: &mut MyApp) {
         egui::Window::new("Available Shortcuts")
             .collapsible(false)
             .show(ctx, |ui| {
-                render_shortcuts_menu(ui);
+                render_shortcuts_menu(ui, app);
 
                 ui.add_space(10.0);
                 
-                if ui.button("Close").clicked() {
-                    app.ui_state.set_showing_shortcuts_menu(false);
-                }
+                ui.horizontal(|ui| {
+                    if ui.button("Reset to defaults").clicked() {
+                        app.capturing_hotkey = None;
+                        for (action, key_combination) in HotkeyAction::default_bindings() {
+                            let _ = app.hotkeys.rebind(action, &key_combination);
+                        }
+                    }
+                    if ui.button("Close").clicked() {
+                        app.capturing_hotkey = None;
+                        app.ui_state.set_showing_shortcuts_menu(false);
+                    }
+                });
             });
     }
 }
 
-fn render_shortcuts_menu(ui: &mut egui::Ui) {
+fn render_shortcuts_menu(ui: &mut egui::Ui, app: &mut MyApp) {
+    // Mentre si scrive la combinazione i tasti premuti vanno al campo di testo
+    if let Some(action) = app.capturing_hotkey.filter(|_| !ui.ctx().wants_keyboard_input()) {
+        capture_hotkey(ui, app, action);
+    }
+
     ui.vertical(|ui| {
         ui.label(egui::RichText::new("Shortcuts").heading());
 
-        let shortcuts = get_shortcuts();
-        for shortcut in shortcuts {
+        for action in HotkeyAction::ALL {
             ui.horizontal(|ui| {
-                ui.label(shortcut.name);
+                ui.label(action.label());
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.label(egui::RichText::new(shortcut.key_combination).monospace());
+                    let capturing = app.capturing_hotkey == Some(action);
+                    let button_label = if capturing { "Press keys… (Esc to cancel)" } else { "Change" };
+                    if ui.selectable_label(capturing, button_label).clicked() {
+                        app.capturing_hotkey = if capturing { None } else { Some(action) };
+                        app.hotkey_text.clear();
+                    }
+                    let key_combination = app.hotkeys.get_bindings().get(&action).cloned().unwrap_or_default();
+                    ui.label(egui::RichText::new(key_combination).monospace());
                 });
             });
+            if app.capturing_hotkey == Some(action) {
+                type_hotkey(ui, app, action);
+            }
+            if let Some(error) = app.hotkeys.get_error(action) {
+                ui.label(egui::RichText::new(error).color(egui::Color32::RED).small());
+            }
         }
     });
 }
 
-struct Shortcut {
-    name: &'static str,
-    key_combination: &'static str,
-}
+/// Assegna all'azione la prima combinazione premuta: almeno un modificatore più una lettera,
+/// una cifra o un tasto funzione. Esc annulla.
+fn capture_hotkey(ui: &mut egui::Ui, app: &mut MyApp, action: HotkeyAction) {
+    let pressed = ui.input(|i| {
+        i.events.iter().find_map(|event| match event {
+            egui::Event::Key { key, pressed: true, modifiers, .. } => Some((*key, *modifiers)),
+            _ => None,
+        })
+    });
+    let Some((key, modifiers)) = pressed else {
+        return;
+    };
+    if key == egui::Key::Escape {
+        app.capturing_hotkey = None;
+        return;
+    }
 
-fn get_shortcuts() -> Vec<Shortcut> {
-    vec![
-        Shortcut { name: "Start/Pause Broadcast", key_combination: "Ctrl+Shift+B" },
-        Shortcut { name: "Start/Stop Recording", key_combination: "Ctrl+Shift+R" },
-        Shortcut { name: "Lock/Unlock Screen", key_combination: "Ctrl+Shift+L" },
-        Shortcut { name: "Toggle Annotation", key_combination: "Ctrl+Shift+A" },
-        Shortcut { name: "Quick Capture Selection", key_combination: "Ctrl+Shift+S" },
-        Shortcut { name: "End Session", key_combination: "Ctrl+Shift+Q" },
-        Shortcut { name: "Switch Monitor", key_combination: "Ctrl+Shift+M" },
-    ]
-}
+    let mut parts = Vec::new();
+    if modifiers.ctrl {
+        parts.push("Ctrl");
+    }
+    if modifiers.alt {
+        parts.push("Alt");
+    }
+    if modifiers.shift {
+        parts.push("Shift");
+    }
+    if modifiers.mac_cmd {
+        parts.push("Super");
+    }
+    // Un tasto senza modificatori catturato a livello di sistema renderebbe inutilizzabile la tastiera
+    if parts.is_empty() {
+        return;
+    }
+    parts.push(key.name());
 
+    // L'esito (anche un errore) resta visibile accanto all'azione
+    let _ = app.hotkeys.rebind(action, &parts.join("+"));
+    app.capturing_hotkey = None;
+}
 
+/// Alternativa alla pressione dei tasti: fuori da macOS la finestra non riceve il tasto Super,
+/// che si può però scrivere nella combinazione (ad esempio "Super+Shift+K").
+fn type_hotkey(ui: &mut egui::Ui, app: &mut MyApp, action: HotkeyAction) {
+    ui.horizontal(|ui| {
+        ui.label("or type it:");
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut app.hotkey_text)
+                .hint_text("Super+Shift+K")
+                .desired_width(160.0),
+        );
+        let typed = !app.hotkey_text.trim().is_empty();
+        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if ui.add_enabled(typed, egui::Button::new("Apply")).clicked() || (typed && submitted) {
+            // Una combinazione non valida viene segnalata accanto all'azione, come quelle premute
+            let _ = app.hotkeys.rebind(action, app.hotkey_text.trim());
+            app.hotkey_text.clear();
+            app.capturing_hotkey = None;
+        }
+    });
+}
 
 // Nuova funzione per visualizzare il pulsante di cambio tema
 fn render_theme_toggle_button(ui: &mut egui::Ui, app: &mut MyApp) {
@@ -398,31 +475,37 @@ fn render_mode_selection_buttons(ui: &mut egui::Ui, app: &mut MyApp) {
 
 /// Mostra il pulsante "Caster"
 fn render_caster_button(ui: &mut egui::Ui, app: &mut MyApp) {
-    if ui.add_sized(
-        [150.0, 50.0],
-        egui::Button::new(
-            egui::RichText::new("🎥 Caster")
-                .color(egui::Color32::WHITE)
-                .strong(),
+    let command = Command::SetMode(AppMode::Caster);
+    if ui.add_enabled_ui(command.is_available(app), |ui| {
+        ui.add_sized(
+            [150.0, 50.0],
+            egui::Button::new(
+                egui::RichText::new(command.label(app))
+                    .color(egui::Color32::WHITE)
+                    .strong(),
+            )
+            .fill(egui::Color32::from_rgb(0, 102, 204)), // Blu per Caster
         )
-        .fill(egui::Color32::from_rgb(0, 102, 204)), // Blu per Caster
-    ).clicked() {
-        app.set_caster(true);
+    }).inner.on_disabled_hover_text("Stop the active session to switch mode").clicked() {
+        commands::execute(app, ui.ctx(), command);
     }
 }
 
 /// Mostra il pulsante "Receiver"
 fn render_receiver_button(ui: &mut egui::Ui, app: &mut MyApp) {
-    if ui.add_sized(
-        [150.0, 50.0],
-        egui::Button::new(
-            egui::RichText::new("📡 Receiver")
-                .color(egui::Color32::WHITE)
-                .strong(),
+    let command = Command::SetMode(AppMode::Receiver);
+    if ui.add_enabled_ui(command.is_available(app), |ui| {
+        ui.add_sized(
+            [150.0, 50.0],
+            egui::Button::new(
+                egui::RichText::new(command.label(app))
+                    .color(egui::Color32::WHITE)
+                    .strong(),
+            )
+            .fill(egui::Color32::from_rgb(0, 204, 102)), // Verde per Receiver
         )
-        .fill(egui::Color32::from_rgb(0, 204, 102)), // Verde per Receiver
-    ).clicked() {
-        app.set_caster(false);
+    }).inner.on_disabled_hover_text("Stop the active session to switch mode").clicked() {
+        commands::execute(app, ui.ctx(), command);
     }
 }
 
@@ -451,22 +534,26 @@ pub fn render_receiving_screen(ctx: &egui::Context, app: &mut MyApp) {
         .resizable(true)
         .show(ctx, |ui| {
             egui::TopBottomPanel::top("top_panel").show_inside(ui, |ui| {
-                if ui.button("Stop Receiving").clicked() {
-                    app.stop_receiving();
+                for command in [Command::ToggleReceiving, Command::ToggleRecording] {
+                    if ui.add_enabled(command.is_available(app), egui::Button::new(command.label(app))).clicked() {
+                        commands::execute(app, ui.ctx(), command);
+                    }
                 }
 
-                if ui.button(if app.flags.is_recording() { "Stop Recording" } else { "Start Recording" }).clicked() {
-                    if app.flags.is_recording() {
-                        app.stop_recording_receiver();
-                    } else {
-                        app.start_recording_receiver();
-                    }
+                let mut show_annotations = app.ui_state.is_showing_received_annotations();
+                if ui.checkbox(&mut show_annotations, "Show Annotations").changed() {
+                    app.ui_state.set_showing_received_annotations(show_annotations);
                 }
+
+                render_snapshot_controls(ui, app);
             });
 
             ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
                 if let Some(ref texture) = app.texture {
-                    ui.image(texture);
+                    let image_rect = ui.image(texture).rect;
+                    if app.ui_state.is_showing_received_annotations() {
+                        render_received_annotations(ui, app, image_rect);
+                    }
                 } else {
                     ui.label("No image received yet.");
                 }
@@ -0,0 +1,273 @@
+// src/app/headless.rs
+
+//! Trasmissione, ricezione e registrazione senza finestra, usate dai sottocomandi da riga di comando.
+//! Usano gli stessi `Caster`, `Receiver` e `Recorder` della GUI; Ctrl+C li ferma.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::app::settings_file::{SettingsError, SettingsFile};
+use crate::core::capture::CaptureSource;
+use crate::core::capture_pipeline::CapturePipeline;
+use crate::core::caster::{Caster, CasterConfig};
+use crate::core::error::WorkerError;
+use crate::core::network::FrameEncoding;
+use crate::core::quality::{OutputScale, StreamQuality};
+use crate::core::receiver::Receiver;
+use crate::core::recorder::{Recorder, RecordingProfile};
+use crate::core::worker::CancellationToken;
+use crate::core::network::StreamMessage;
+use crate::utils::logging::LogLevel;
+use crate::utils::multi_monitor::monitor_id;
+use crate::utils::redaction::PrivacyMask;
+
+// Intervallo con cui il thread principale controlla se la ricezione è terminata
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub type HeadlessResult = Result<(), Box<dyn Error>>;
+
+pub struct CastOptions {
+    pub source: CaptureSource,
+    pub port: u16,
+    pub password: Option<String>,
+    pub quality: QualityOptions,
+    /// Formato dei frame; se assente si usa quello delle impostazioni salvate
+    pub encoding: Option<FrameEncoding>,
+    /// Attiva o disattiva l'adattamento della qualità alla rete
+    pub adaptive: Option<bool>,
+}
+
+/// Framerate e risoluzione scelti da riga di comando; quelli assenti vengono dalle impostazioni salvate
+#[derive(Default)]
+pub struct QualityOptions {
+    pub fps: Option<u32>,
+    pub scale: Option<OutputScale>,
+}
+
+impl QualityOptions {
+    fn apply_to(&self, quality: &mut StreamQuality) {
+        if let Some(fps) = self.fps {
+            quality.fps = fps;
+        }
+        if let Some(scale) = self.scale {
+            quality.scale = scale;
+        }
+    }
+}
+
+pub struct ReceiveOptions {
+    pub address: String,
+    pub port: u16,
+    pub password: Option<String>,
+    /// File di destinazione, oppure directory in cui salvare `receiver_recorded_video.mp4`
+    pub output: PathBuf,
+    /// Encoder di ffmpeg; se assente si usa quello delle impostazioni salvate
+    pub codec: Option<String>,
+    /// Framerate e risoluzione del video registrato
+    pub quality: QualityOptions,
+}
+
+pub struct RecordOptions {
+    pub source: CaptureSource,
+    /// File di destinazione, oppure directory in cui salvare `recorded_video.mp4`
+    pub output: PathBuf,
+    pub codec: Option<String>,
+    pub quality: QualityOptions,
+    /// Durata massima; senza durata la registrazione prosegue fino a Ctrl+C
+    pub duration: Option<Duration>,
+}
+
+/// Trasmette lo schermo ai receiver che si connettono sulla porta indicata, fino a Ctrl+C.
+/// Le maschere di privacy salvate per il monitor trasmesso vengono applicate come nella GUI;
+/// se il file delle impostazioni non si può leggere la trasmissione non parte.
+pub fn cast(options: CastOptions) -> HeadlessResult {
+    let interrupt = interrupt_on_ctrl_c()?;
+    let (errors_tx, errors) = mpsc::channel();
+    // Monitor e area non validi vengono segnalati prima di mettersi in ascolto
+    let settings = load_settings()?;
+    let privacy_masks = saved_privacy_masks(&settings, &options.source);
+    let pipeline = CapturePipeline::start(options.source, privacy_masks, Some(errors_tx.clone()))?;
+
+    let mut quality = settings.stream;
+    options.quality.apply_to(&mut quality.quality);
+    if let Some(encoding) = options.encoding {
+        quality.encoding = encoding;
+    }
+    if let Some(adaptive) = options.adaptive {
+        quality.adaptive.enabled = adaptive;
+    }
+    let config = CasterConfig {
+        password: options.password,
+        quality: Arc::new(Mutex::new(quality)),
+        errors: Some(errors_tx),
+        ..CasterConfig::new(options.port, pipeline.subscribe(quality.quality.fps()))
+    };
+    let caster = Caster::start(config)?;
+    println!(
+        "Casting on port {} at {} fps, {}, {} frames{}; press Ctrl+C to stop.",
+        options.port,
+        quality.quality.fps(),
+        quality.quality.scale,
+        quality.encoding,
+        if quality.adaptive.enabled { ", adapting to the network" } else { "" }
+    );
+
+    // Un errore di cattura interrompe la trasmissione senza attendere Ctrl+C
+    while caster.is_running() {
+        if interrupt.wait_timeout(POLL_INTERVAL) {
+            break;
+        }
+    }
+    caster.stop();
+    pipeline.stop();
+    worker_result(&errors)
+}
+
+/// Si connette a un caster e registra i frame ricevuti finché la trasmissione non termina o fino a Ctrl+C.
+pub fn receive(options: ReceiveOptions) -> HeadlessResult {
+    // Il video ricevuto non contiene lo schermo locale: senza impostazioni bastano quelle predefinite
+    let settings = load_settings().unwrap_or_else(|e| {
+        log::warn!("Failed to load settings, using defaults: {}", e);
+        SettingsFile::default()
+    });
+    let profile = recording_profile(&settings, options.codec, &options.quality);
+    let output = resolve_output(&options.output, "receiver_recorded_video.mp4");
+    let interrupt = interrupt_on_ctrl_c()?;
+    let (errors_tx, errors) = mpsc::channel();
+
+    let frames = Arc::new(Mutex::new(VecDeque::new()));
+    let frames_clone = Arc::clone(&frames);
+    let receiver = Receiver::connect(&options.address, options.port, options.password.as_deref(), Some(errors_tx.clone()), move |message| {
+        if let StreamMessage::Frame(frame) = message {
+            frames_clone.lock().unwrap().push_back(frame);
+        }
+    })?;
+
+    // ffmpeg ha bisogno delle dimensioni del video, note solo dal primo frame
+    let (width, height) = loop {
+        if let Some(frame) = frames.lock().unwrap().front() {
+            break (frame.width as usize, frame.height as usize);
+        }
+        if !receiver.is_running() {
+            receiver.join();
+            worker_result(&errors)?;
+            return Err("connection closed before the first frame".into());
+        }
+        if interrupt.wait_timeout(POLL_INTERVAL) {
+            receiver.stop();
+            return Ok(());
+        }
+    };
+
+    let recorder = Recorder::record_frames(&profile, &output, Arc::clone(&frames), width, height, Some(errors_tx))?;
+    println!("Receiving {}x{} into {}, press Ctrl+C to stop.", width, height, output.display());
+
+    // Quando il caster chiude, il file viene finalizzato dopo aver scritto i frame rimasti in coda
+    while (receiver.is_running() || !frames.lock().unwrap().is_empty()) && recorder.is_running() {
+        // I frame non ancora scritti da ffmpeg dicono al caster se la registrazione sta al passo
+        receiver.set_queue_depth(frames.lock().unwrap().len());
+        if interrupt.wait_timeout(POLL_INTERVAL) {
+            break;
+        }
+    }
+    recorder.stop();
+    receiver.stop();
+    worker_result(&errors)
+}
+
+/// Registra lo schermo in un file, per la durata indicata o fino a Ctrl+C.
+/// Come nella trasmissione, senza le maschere di privacy salvate la registrazione non parte.
+pub fn record(options: RecordOptions) -> HeadlessResult {
+    let settings = load_settings()?;
+    let profile = recording_profile(&settings, options.codec, &options.quality);
+    let output = resolve_output(&options.output, "recorded_video.mp4");
+    let interrupt = interrupt_on_ctrl_c()?;
+
+    let (errors_tx, errors) = mpsc::channel();
+
+    let privacy_masks = saved_privacy_masks(&settings, &options.source);
+    let pipeline = CapturePipeline::start(options.source, privacy_masks, Some(errors_tx.clone()))?;
+    let recorder = Recorder::record_screen(
+        &profile,
+        &output,
+        pipeline.subscribe(profile.quality.fps()),
+        Arc::new(Mutex::new(Vec::new())),
+        Some(errors_tx),
+    )?;
+    println!("Recording into {}, press Ctrl+C to stop.", output.display());
+
+    // Se ffmpeg o la cattura falliscono la registrazione termina prima della durata richiesta
+    let deadline = options.duration.map(|duration| Instant::now() + duration);
+    while recorder.is_running() && deadline.is_none_or(|deadline| Instant::now() < deadline) {
+        if interrupt.wait_timeout(POLL_INTERVAL) {
+            break;
+        }
+    }
+    recorder.stop();
+    pipeline.stop();
+    worker_result(&errors)
+}
+
+/// Token cancellato alla pressione di Ctrl+C
+fn interrupt_on_ctrl_c() -> Result<CancellationToken, ctrlc::Error> {
+    let interrupt = CancellationToken::new();
+    let token = interrupt.clone();
+    ctrlc::set_handler(move || {
+        println!("Stopping...");
+        token.cancel();
+    })?;
+    Ok(interrupt)
+}
+
+/// Primo errore con cui un thread di lavoro è terminato, da restituire al posto del successo
+fn worker_result(errors: &mpsc::Receiver<WorkerError>) -> HeadlessResult {
+    match errors.try_recv() {
+        Ok(error) => Err(error.into()),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Maschere di privacy salvate dalla GUI per il monitor catturato
+fn saved_privacy_masks(settings: &SettingsFile, source: &CaptureSource) -> Arc<Mutex<Vec<PrivacyMask>>> {
+    let masks = settings.privacy_masks.get(&monitor_id(source.display));
+    Arc::new(Mutex::new(masks.cloned().unwrap_or_default()))
+}
+
+/// Profilo di registrazione salvato dalla GUI, con encoder, framerate e risoluzione scelti da riga di comando
+fn recording_profile(settings: &SettingsFile, codec: Option<String>, quality: &QualityOptions) -> RecordingProfile {
+    let mut profile = settings.recording.clone();
+    if let Some(codec) = codec {
+        profile.codec = codec;
+    }
+    quality.apply_to(&mut profile.quality);
+    profile
+}
+
+/// Verbosità dei log salvata dalla GUI
+pub fn saved_log_level() -> LogLevel {
+    load_settings().map(|settings| settings.log_level).unwrap_or_default()
+}
+
+/// Impostazioni salvate dalla GUI, lette anche senza la feature `gui`. Un file illeggibile è un errore,
+/// perché potrebbe contenere maschere di privacy; senza directory di configurazione non ne sono
+/// state salvate e valgono le impostazioni predefinite.
+fn load_settings() -> Result<SettingsFile, SettingsError> {
+    match SettingsFile::default_path() {
+        Ok(path) => SettingsFile::load(&path),
+        Err(SettingsError::NoConfigDir) => Ok(SettingsFile::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Un percorso senza estensione o che è già una directory indica dove salvare `default_name`
+fn resolve_output(path: &Path, default_name: &str) -> PathBuf {
+    if path.is_dir() || path.extension().is_none() {
+        path.join(default_name)
+    } else {
+        path.to_path_buf()
+    }
+}
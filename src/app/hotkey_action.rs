@@ -0,0 +1,59 @@
+// src/app/hotkey_action.rs
+
+//! Azioni associabili a una hotkey globale, separate dalla loro registrazione presso il sistema
+//! così che il file delle impostazioni si possa leggere anche senza la GUI.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum HotkeyAction {
+    StartPauseBroadcast,
+    StartStopRecording,
+    LockUnlockScreen,
+    ToggleAnnotation,
+    QuickCaptureSelection,
+    EndSession,
+    SwitchMonitor,
+}
+
+impl HotkeyAction {
+    pub const ALL: [HotkeyAction; 7] = [
+        HotkeyAction::StartPauseBroadcast,
+        HotkeyAction::StartStopRecording,
+        HotkeyAction::LockUnlockScreen,
+        HotkeyAction::ToggleAnnotation,
+        HotkeyAction::QuickCaptureSelection,
+        HotkeyAction::EndSession,
+        HotkeyAction::SwitchMonitor,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HotkeyAction::StartPauseBroadcast => "Start/Pause Broadcast",
+            HotkeyAction::StartStopRecording => "Start/Stop Recording",
+            HotkeyAction::LockUnlockScreen => "Lock/Unlock Screen",
+            HotkeyAction::ToggleAnnotation => "Toggle Annotation",
+            HotkeyAction::QuickCaptureSelection => "Quick Capture Selection",
+            HotkeyAction::EndSession => "End Session",
+            HotkeyAction::SwitchMonitor => "Switch Monitor",
+        }
+    }
+
+    /// Combinazioni predefinite
+    pub fn default_bindings() -> BTreeMap<HotkeyAction, String> {
+        [
+            (HotkeyAction::StartPauseBroadcast, "Ctrl+Shift+B"),
+            (HotkeyAction::StartStopRecording, "Ctrl+Shift+R"),
+            (HotkeyAction::LockUnlockScreen, "Ctrl+Shift+L"),
+            (HotkeyAction::ToggleAnnotation, "Ctrl+Shift+A"),
+            (HotkeyAction::QuickCaptureSelection, "Ctrl+Shift+S"),
+            (HotkeyAction::EndSession, "Ctrl+Shift+Q"),
+            (HotkeyAction::SwitchMonitor, "Ctrl+Shift+M"),
+        ]
+        .into_iter()
+        .map(|(action, key_combination)| (action, key_combination.to_string()))
+        .collect()
+    }
+}
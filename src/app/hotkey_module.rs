@@ -1,74 +1,287 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
 use global_hotkey::{GlobalHotKeyManager, hotkey};
 use global_hotkey::hotkey::{Code, HotKey};
-use std::error::Error;
+use log::warn;
+
+use crate::app::hotkey_action::HotkeyAction;
 
 pub struct HotkeySettings {
     pub hotkey_map: HashMap<u32, HotkeyAction>,
     hotkey_manager: GlobalHotKeyManager,
+    // Combinazione associata a ciascuna azione, ad esempio "Ctrl+Shift+B"
+    bindings: BTreeMap<HotkeyAction, String>,
+    // Hotkey effettivamente registrate presso il sistema, per poterle deregistrare
+    registered: BTreeMap<HotkeyAction, HotKey>,
+    // Ultimo errore di registrazione di ciascuna azione, mostrato accanto alla combinazione
+    errors: BTreeMap<HotkeyAction, String>,
+}
+
+#[derive(Debug)]
+pub enum HotkeyError {
+    /// La combinazione non è nel formato "Mod+Mod+Tasto"
+    Parse(String),
+    /// La combinazione è già assegnata a un'altra azione
+    Conflict(HotkeyAction),
+    /// Il sistema ha rifiutato la registrazione (ad esempio perché la combinazione è già in uso)
+    Register(global_hotkey::Error),
 }
 
-#[derive(Clone)]
-pub enum HotkeyAction {
-    StartPauseBroadcast,
-    StartStopRecording,
-    LockUnlockScreen,
-    ToggleAnnotation,
-    QuickCaptureSelection,
-    EndSession,
-    SwitchMonitor,
+impl fmt::Display for HotkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HotkeyError::Parse(combination) => write!(f, "invalid key combination \"{}\"", combination),
+            HotkeyError::Conflict(action) => write!(f, "already used by \"{}\"", action.label()),
+            HotkeyError::Register(e) => write!(f, "registration failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HotkeyError {}
+
+impl From<global_hotkey::Error> for HotkeyError {
+    fn from(e: global_hotkey::Error) -> Self {
+        HotkeyError::Register(e)
+    }
 }
 
 impl HotkeySettings {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
+    /// Registra le combinazioni date. Una combinazione non valida o rifiutata dal sistema non
+    /// impedisce di registrare le altre: l'errore resta consultabile con `get_error`.
+    pub fn new(bindings: &BTreeMap<HotkeyAction, String>) -> Result<Self, HotkeyError> {
         let mut settings = HotkeySettings {
             hotkey_map: HashMap::new(),
             hotkey_manager: GlobalHotKeyManager::new()?,
+            bindings: BTreeMap::new(),
+            registered: BTreeMap::new(),
+            errors: BTreeMap::new(),
         };
 
         // Registra hotkeys con le azioni corrispondenti
-        settings.register_hotkey("Ctrl+Shift+B", HotkeyAction::StartPauseBroadcast)?;
-        settings.register_hotkey("Ctrl+Shift+R", HotkeyAction::StartStopRecording)?;
-        settings.register_hotkey("Ctrl+Shift+L", HotkeyAction::LockUnlockScreen)?;
-        settings.register_hotkey("Ctrl+Shift+A", HotkeyAction::ToggleAnnotation)?;
-        settings.register_hotkey("Ctrl+Shift+S", HotkeyAction::QuickCaptureSelection)?;
-        settings.register_hotkey("Ctrl+Shift+Q", HotkeyAction::EndSession)?;
-        settings.register_hotkey("Ctrl+Shift+M", HotkeyAction::SwitchMonitor)?;
+        for (action, key_combination) in bindings {
+            if let Err(e) = settings.rebind(*action, key_combination) {
+                warn!("Failed to register hotkey {} for {:?}: {}", key_combination, action, e);
+            }
+        }
 
         Ok(settings)
     }
 
-    fn register_hotkey(&mut self, key_combination: &str, action: HotkeyAction) -> Result<(), Box<dyn Error>> {
-        let parsed_hotkey = self.parse_key_combination(key_combination)?;
-        let hotkey = HotKey::new(parsed_hotkey.0, parsed_hotkey.1);
-        self.hotkey_manager.register(hotkey)?;
+    pub fn get_bindings(&self) -> &BTreeMap<HotkeyAction, String> {
+        &self.bindings
+    }
+
+    pub fn get_error(&self, action: HotkeyAction) -> Option<&str> {
+        self.errors.get(&action).map(String::as_str)
+    }
+
+    /// Assegna una nuova combinazione all'azione, sostituendo quella registrata in precedenza.
+    /// Se la nuova combinazione non è valida, è in conflitto o viene rifiutata dal sistema,
+    /// la combinazione precedente resta attiva.
+    pub fn rebind(&mut self, action: HotkeyAction, key_combination: &str) -> Result<(), HotkeyError> {
+        let result = self.try_rebind(action, key_combination);
+        match &result {
+            Ok(()) => {
+                self.errors.remove(&action);
+            }
+            Err(e) => {
+                self.errors.insert(action, e.to_string());
+                // Un'azione senza combinazione (ad esempio all'avvio) tiene quella rifiutata, così resta
+                // salvata e si può correggere; altrimenti resta quella precedente, ancora attiva
+                self.bindings.entry(action).or_insert_with(|| key_combination.to_string());
+            }
+        }
+        result
+    }
+
+    fn try_rebind(&mut self, action: HotkeyAction, key_combination: &str) -> Result<(), HotkeyError> {
+        let (modifiers, code) = parse_key_combination(key_combination)?;
+        let hotkey = HotKey::new(modifiers, code);
+
+        if let Some((other, _)) = self
+            .registered
+            .iter()
+            .find(|(other, registered)| **other != action && registered.id() == hotkey.id())
+        {
+            return Err(HotkeyError::Conflict(*other));
+        }
+
+        let previous = self.registered.get(&action).copied();
+        if previous.map(|p| p.id()) == Some(hotkey.id()) {
+            return Ok(());
+        }
+        if let Some(previous) = previous {
+            self.hotkey_manager.unregister(previous)?;
+            self.hotkey_map.remove(&previous.id());
+            self.registered.remove(&action);
+        }
+
+        if let Err(e) = self.hotkey_manager.register(hotkey) {
+            // Ripristina la combinazione precedente, se c'era
+            if let Some(previous) = previous {
+                if self.hotkey_manager.register(previous).is_ok() {
+                    self.hotkey_map.insert(previous.id(), action);
+                    self.registered.insert(action, previous);
+                }
+            }
+            return Err(e.into());
+        }
+
         self.hotkey_map.insert(hotkey.id(), action);
+        self.registered.insert(action, hotkey);
+        self.bindings.insert(action, format_key_combination(modifiers, code));
         Ok(())
     }
+}
 
-    fn parse_key_combination(&self, key_combination: &str) -> Result<(Option<hotkey::Modifiers>, Code), Box<dyn Error>> {
-        let mut modifiers = None;
-        let mut key = None;
-
-        for part in key_combination.split('+') {
-            match part.trim() {
-                "Ctrl" => modifiers = Some(modifiers.unwrap_or(hotkey::Modifiers::empty()) | hotkey::Modifiers::CONTROL),
-                "Shift" => modifiers = Some(modifiers.unwrap_or(hotkey::Modifiers::empty()) | hotkey::Modifiers::SHIFT),
-                "B" => key = Some(Code::KeyB),
-                "R" => key = Some(Code::KeyR),
-                "L" => key = Some(Code::KeyL),
-                "A" => key = Some(Code::KeyA),
-                "S" => key = Some(Code::KeyS),
-                "Q" => key = Some(Code::KeyQ),
-                "M" => key = Some(Code::KeyM),
-                _ => return Err("Invalid key combination".into()),
+/// Interpreta una combinazione come "Ctrl+Alt+F5": zero o più modificatori (Ctrl, Shift, Alt,
+/// Super) seguiti da un solo tasto tra lettere, cifre e tasti funzione F1-F24.
+/// Maiuscole, minuscole e spazi intorno ai `+` sono indifferenti.
+pub fn parse_key_combination(key_combination: &str) -> Result<(Option<hotkey::Modifiers>, Code), HotkeyError> {
+    let invalid = || HotkeyError::Parse(key_combination.to_string());
+    let mut modifiers = hotkey::Modifiers::empty();
+    let mut key = None;
+
+    for part in key_combination.split('+') {
+        let part = part.trim().to_ascii_uppercase();
+        let modifier = match part.as_str() {
+            "CTRL" | "CONTROL" => Some(hotkey::Modifiers::CONTROL),
+            "SHIFT" => Some(hotkey::Modifiers::SHIFT),
+            "ALT" | "OPTION" => Some(hotkey::Modifiers::ALT),
+            "SUPER" | "WIN" | "META" | "CMD" | "COMMAND" => Some(hotkey::Modifiers::SUPER),
+            _ => None,
+        };
+        if let Some(modifier) = modifier {
+            // I modificatori vanno prima del tasto
+            if key.is_some() {
+                return Err(invalid());
             }
+            modifiers |= modifier;
+            continue;
         }
+        if key.is_some() {
+            return Err(invalid());
+        }
+        key = Some(parse_key(&part).ok_or_else(invalid)?);
+    }
+
+    let key = key.ok_or_else(invalid)?;
+    Ok(((!modifiers.is_empty()).then_some(modifiers), key))
+}
+
+fn parse_key(key: &str) -> Option<Code> {
+    let mut chars = key.chars();
+    let name = match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_uppercase() => format!("Key{}", c),
+        (Some(c), None) if c.is_ascii_digit() => format!("Digit{}", c),
+        (Some('F'), Some(_)) => match key[1..].parse::<u8>() {
+            Ok(n) if (1..=24).contains(&n) => key.to_string(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Code::from_str(&name).ok()
+}
+
+/// Forma canonica di una combinazione, con i modificatori sempre nello stesso ordine
+pub fn format_key_combination(modifiers: Option<hotkey::Modifiers>, code: Code) -> String {
+    let modifiers = modifiers.unwrap_or(hotkey::Modifiers::empty());
+    let mut parts: Vec<String> = [
+        (hotkey::Modifiers::CONTROL, "Ctrl"),
+        (hotkey::Modifiers::ALT, "Alt"),
+        (hotkey::Modifiers::SHIFT, "Shift"),
+        (hotkey::Modifiers::SUPER, "Super"),
+    ]
+    .into_iter()
+    .filter(|(modifier, _)| modifiers.contains(*modifier))
+    .map(|(_, name)| name.to_string())
+    .collect();
+
+    let name = code.to_string();
+    let key = name
+        .strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name);
+    parts.push(key.to_string());
+    parts.join("+")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use global_hotkey::hotkey::Modifiers;
+
+    fn parse(key_combination: &str) -> Option<(Option<Modifiers>, Code)> {
+        parse_key_combination(key_combination).ok()
+    }
+
+    #[test]
+    fn modifier_aliases() {
+        assert_eq!(parse("Alt+X"), Some((Some(Modifiers::ALT), Code::KeyX)));
+        assert_eq!(parse("Option+X"), parse("Alt+X"));
+        for alias in ["Super", "Win", "Meta", "Cmd", "Command"] {
+            assert_eq!(parse(&format!("{}+X", alias)), Some((Some(Modifiers::SUPER), Code::KeyX)), "{}", alias);
+        }
+        assert_eq!(parse("control+x"), Some((Some(Modifiers::CONTROL), Code::KeyX)));
+        assert_eq!(
+            parse(" ctrl +  SHIFT+b "),
+            Some((Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyB))
+        );
+    }
+
+    #[test]
+    fn digits_and_bare_keys() {
+        assert_eq!(parse("Ctrl+7"), Some((Some(Modifiers::CONTROL), Code::Digit7)));
+        assert_eq!(parse("0"), Some((None, Code::Digit0)));
+        assert_eq!(parse("q"), Some((None, Code::KeyQ)));
+    }
+
+    #[test]
+    fn function_keys() {
+        assert_eq!(parse("F1"), Some((None, Code::F1)));
+        assert_eq!(parse("Shift+f12"), Some((Some(Modifiers::SHIFT), Code::F12)));
+        assert_eq!(parse("F24"), Some((None, Code::F24)));
+        assert!(parse("F0").is_none());
+        assert!(parse("F25").is_none());
+        assert!(parse("Fx").is_none());
+    }
+
+    #[test]
+    fn modifier_after_the_key_is_rejected() {
+        assert!(parse("B+Ctrl").is_none());
+        assert!(parse("Ctrl+B+Shift").is_none());
+    }
+
+    #[test]
+    fn malformed_combinations_are_rejected() {
+        assert!(parse("Ctrl+A+B").is_none());
+        assert!(parse("A+B").is_none());
+        assert!(parse("Ctrl+Shift").is_none());
+        assert!(parse("").is_none());
+        assert!(parse("Ctrl++A").is_none());
+        assert!(parse("Ctrl+Space").is_none());
+        assert!(matches!(parse_key_combination("Hyper+A"), Err(HotkeyError::Parse(s)) if s == "Hyper+A"));
+    }
+
+    #[test]
+    fn canonical_format_round_trips() {
+        let (modifiers, code) = parse("super+shift+alt+ctrl+f5").unwrap();
+        let canonical = format_key_combination(modifiers, code);
+        assert_eq!(canonical, "Ctrl+Alt+Shift+Super+F5");
+        assert_eq!(parse(&canonical), Some((modifiers, code)));
+
+        for combination in ["Ctrl+Shift+B", "Alt+9", "F13", "Q"] {
+            let (modifiers, code) = parse(combination).unwrap();
+            assert_eq!(format_key_combination(modifiers, code), combination);
+        }
+    }
 
-        if let Some(key) = key {
-            Ok((modifiers, key))
-        } else {
-            Err("Invalid key combination".into())
+    #[test]
+    fn default_bindings_are_canonical() {
+        for (action, combination) in HotkeyAction::default_bindings() {
+            let (modifiers, code) = parse(&combination).unwrap_or_else(|| panic!("{:?}", action));
+            assert_eq!(format_key_combination(modifiers, code), combination);
         }
     }
 }
@@ -1,5 +1,12 @@
+// Frontend dell'applicazione: la GUI egui (feature `gui`) e i comandi senza finestra
+#[cfg(feature = "gui")]
 pub mod gui;
-pub mod capture;
-pub mod network;
+#[cfg(feature = "gui")]
+pub mod commands;
+pub mod headless;
+pub mod hotkey_action;
+#[cfg(feature = "gui")]
 pub mod hotkey_module;
-pub mod state;
\ No newline at end of file
+#[cfg(feature = "gui")]
+pub mod state;
+pub mod settings_file;
@@ -0,0 +1,307 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app::hotkey_action::HotkeyAction;
+use crate::core::network::DEFAULT_PORT;
+use crate::core::quality::BroadcastQuality;
+use crate::core::recorder::RecordingProfile;
+use crate::utils::annotations::RedactStyle;
+use crate::utils::editor::{Editor, Mode, ToolStyle};
+use crate::utils::logging::LogLevel;
+use crate::utils::redaction::PrivacyMask;
+
+/// Versione del formato del file di impostazioni scritto da questa build.
+pub const SETTINGS_VERSION: u32 = 2;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Theme {
+    Light,
+    Dark,
+}
+
+/// Impostazioni salvate tra un avvio e l'altro. I campi mancanti nel file prendono il valore
+/// predefinito, così un file scritto da una build precedente resta leggibile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SettingsFile {
+    pub version: u32,
+    pub theme: Theme,
+    pub caster_address: String,
+    pub auto_detect_ip: bool,
+    pub port: u16,
+    /// Monitor selezionato, come indice in `Display::all()`; `None` per il monitor principale
+    pub monitor: Option<usize>,
+    /// Area di cattura come `[x, y, larghezza, altezza]`, `None` per lo schermo intero
+    pub capture_area: Option<[usize; 4]>,
+    /// Framerate, risoluzione, formato dei frame e limiti dell'adattamento alla rete della trasmissione
+    pub stream: BroadcastQuality,
+    pub recording: RecordingProfile,
+    pub annotations: AnnotationDefaults,
+    pub hotkeys: BTreeMap<HotkeyAction, String>,
+    /// Maschere di privacy di ciascun monitor, indicizzate con `multi_monitor::monitor_id`
+    pub privacy_masks: HashMap<String, Vec<PrivacyMask>>,
+    /// Verbosità dei log, se non è impostata la variabile d'ambiente `SCREENSTREAMX_LOG`
+    pub log_level: LogLevel,
+}
+
+impl Default for SettingsFile {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            theme: Theme::Dark,
+            caster_address: String::new(),
+            auto_detect_ip: true,
+            port: DEFAULT_PORT,
+            monitor: None,
+            capture_area: None,
+            stream: BroadcastQuality::default(),
+            recording: RecordingProfile::default(),
+            annotations: AnnotationDefaults::default(),
+            hotkeys: HotkeyAction::default_bindings(),
+            privacy_masks: HashMap::new(),
+            log_level: LogLevel::default(),
+        }
+    }
+}
+
+/// Stili degli strumenti di annotazione con cui si apre l'editor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnnotationDefaults {
+    pub tool_styles: HashMap<Mode, ToolStyle>,
+    pub snap_shapes: bool,
+    pub redact_style: RedactStyle,
+}
+
+impl Default for AnnotationDefaults {
+    fn default() -> Self {
+        Self::from_editor(&Editor::default())
+    }
+}
+
+impl AnnotationDefaults {
+    pub fn from_editor(editor: &Editor) -> Self {
+        Self {
+            tool_styles: editor.all_tool_styles(),
+            snap_shapes: editor.snap_shapes,
+            redact_style: editor.redact_style,
+        }
+    }
+
+    pub fn apply_to(&self, editor: &mut Editor) {
+        editor.tool_styles.extend(self.tool_styles.clone());
+        editor.snap_shapes = self.snap_shapes;
+        editor.redact_style = self.redact_style;
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u32),
+    /// Il sistema non indica una directory di configurazione per l'utente
+    NoConfigDir,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "I/O error: {}", e),
+            SettingsError::Format(e) => write!(f, "invalid settings file: {}", e),
+            SettingsError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported settings version {} (this build reads up to {})",
+                v, SETTINGS_VERSION
+            ),
+            SettingsError::NoConfigDir => write!(f, "no configuration directory available"),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SettingsError {
+    fn from(e: serde_json::Error) -> Self {
+        SettingsError::Format(e)
+    }
+}
+
+impl SettingsFile {
+    /// Percorso del file nella directory di configurazione della piattaforma
+    /// (ad esempio `~/.config/screen_stream_x/settings.json` su Linux).
+    pub fn default_path() -> Result<PathBuf, SettingsError> {
+        ProjectDirs::from("", "", "screen_stream_x")
+            .map(|dirs| dirs.config_dir().join(SETTINGS_FILE_NAME))
+            .ok_or(SettingsError::NoConfigDir)
+    }
+
+    /// Legge il file, portandolo alla versione corrente se è stato scritto da una build precedente.
+    /// Se il file non esiste restituisce le impostazioni predefinite.
+    pub fn load(path: &Path) -> Result<Self, SettingsError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let value = migrate(serde_json::from_str(&text)?)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Impostazioni con cui avviare l'applicazione e file in cui salvarle. Se il file non si può
+    /// leggere (ad esempio perché scritto da una build più recente) si parte dalle impostazioni
+    /// predefinite e il percorso restituito è `None`, così il file non viene sovrascritto.
+    pub fn load_or_default(path: PathBuf) -> (Self, Option<PathBuf>) {
+        match Self::load(&path) {
+            Ok(settings) => (settings, Some(path)),
+            Err(e) => {
+                warn!("Failed to load settings from {}: {}", path.display(), e);
+                (Self::default(), None)
+            }
+        }
+    }
+
+    /// Scrive il file passando da un file temporaneo, così un'interruzione non lo lascia a metà.
+    pub fn save(&self, path: &Path) -> Result<(), SettingsError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
+
+/// Applica in ordine le migrazioni dalla versione del file a `SETTINGS_VERSION`.
+fn migrate(mut value: Value) -> Result<Value, SettingsError> {
+    let mut version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version > SETTINGS_VERSION {
+        return Err(SettingsError::UnsupportedVersion(version));
+    }
+    while version < SETTINGS_VERSION {
+        match version {
+            // I file senza versione precedono il versionamento e hanno già il formato della v1
+            0 => {}
+            // La v1 identificava i monitor con la sola risoluzione ("1920x1080") e catturava sempre
+            // il principale: le maschere passano al monitor principale e il monitor selezionato si perde
+            1 => {
+                if let Some(object) = value.as_object_mut() {
+                    object.remove("monitor");
+                    if let Some(Value::Object(masks)) = object.get_mut("privacy_masks") {
+                        *masks = std::mem::take(masks)
+                            .into_iter()
+                            .map(|(monitor, masks)| (format!("primary:{}", monitor), masks))
+                            .collect();
+                    }
+                }
+            }
+            _ => unreachable!("no migration from settings version {}", version),
+        }
+        version += 1;
+    }
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), Value::from(version));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::quality::OutputScale;
+    use crate::utils::test_support::TempDir;
+
+
+    #[test]
+    fn missing_file_gives_defaults() {
+        let dir = TempDir::new("settings_missing");
+        assert_eq!(SettingsFile::load(&dir.join(SETTINGS_FILE_NAME)).unwrap(), SettingsFile::default());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = TempDir::new("settings_round_trip");
+        let mut settings = SettingsFile { port: 9000, caster_address: "10.0.0.2".to_string(), ..SettingsFile::default() };
+        settings.stream.quality.scale = OutputScale::MaxWidth(1280);
+        settings.hotkeys.insert(HotkeyAction::EndSession, "Ctrl+Alt+F4".to_string());
+        settings.save(&dir.join(SETTINGS_FILE_NAME)).unwrap();
+
+        assert_eq!(SettingsFile::load(&dir.join(SETTINGS_FILE_NAME)).unwrap(), settings);
+    }
+
+    #[test]
+    fn unversioned_file_is_migrated() {
+        let dir = TempDir::new("settings_v0");
+        fs::write(
+            dir.join(SETTINGS_FILE_NAME),
+            r#"{ "theme": "Light", "caster_address": "192.168.1.20", "auto_detect_ip": false, "port": 9090 }"#,
+        )
+        .unwrap();
+
+        let settings = SettingsFile::load(&dir.join(SETTINGS_FILE_NAME)).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.theme, Theme::Light);
+        assert_eq!(settings.caster_address, "192.168.1.20");
+        assert!(!settings.auto_detect_ip);
+        assert_eq!(settings.port, 9090);
+        // I campi aggiunti dopo la v0 prendono il valore predefinito
+        assert_eq!(settings.hotkeys, HotkeyAction::default_bindings());
+        assert_eq!(settings.recording, RecordingProfile::default());
+    }
+
+    #[test]
+    fn v1_masks_move_to_the_primary_monitor() {
+        let dir = TempDir::new("settings_v1");
+        fs::write(
+            dir.join(SETTINGS_FILE_NAME),
+            r#"{
+                "version": 1,
+                "monitor": "1920x1080",
+                "privacy_masks": {
+                    "1920x1080": [{ "rect": { "min": { "x": 10.0, "y": 20.0 }, "max": { "x": 110.0, "y": 70.0 } }, "style": "Solid" }]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let settings = SettingsFile::load(&dir.join(SETTINGS_FILE_NAME)).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.monitor, None);
+        assert_eq!(settings.privacy_masks.keys().collect::<Vec<_>>(), ["primary:1920x1080"]);
+        let mask = &settings.privacy_masks["primary:1920x1080"][0];
+        assert_eq!(mask.rect, egui::Rect::from_min_max(egui::pos2(10.0, 20.0), egui::pos2(110.0, 70.0)));
+    }
+
+    #[test]
+    fn future_version_falls_back_without_clobbering_the_file() {
+        let dir = TempDir::new("settings_future");
+        let text = format!(r#"{{ "version": {}, "port": 9999, "new_field": true }}"#, SETTINGS_VERSION + 1);
+        fs::write(dir.join(SETTINGS_FILE_NAME), &text).unwrap();
+
+        assert!(matches!(
+            SettingsFile::load(&dir.join(SETTINGS_FILE_NAME)),
+            Err(SettingsError::UnsupportedVersion(v)) if v == SETTINGS_VERSION + 1
+        ));
+        let (settings, save_path) = SettingsFile::load_or_default(dir.join(SETTINGS_FILE_NAME));
+        assert_eq!(settings, SettingsFile::default());
+        assert!(save_path.is_none());
+        assert_eq!(fs::read_to_string(dir.join(SETTINGS_FILE_NAME)).unwrap(), text);
+    }
+}
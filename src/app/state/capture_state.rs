@@ -1,8 +1,19 @@
-use crate::app::capture::CaptureArea;
+use std::sync::{Arc, Mutex};
+
+use crate::core::capture::CaptureArea;
+use crate::core::quality::BroadcastQuality;
+use crate::utils::annotations::RedactAnnotation;
+use crate::utils::redaction::PrivacyMask;
 
 pub struct CaptureState {
     capture_area: Option<CaptureArea>,
     is_fullscreen: bool,
+    // Regioni da oscurare, condivise con i thread di trasmissione e registrazione
+    redactions: Arc<Mutex<Vec<RedactAnnotation>>>,
+    // Maschere di privacy del monitor corrente, applicate da ogni `ScreenCapturer`
+    privacy_masks: Arc<Mutex<Vec<PrivacyMask>>>,
+    // Qualità della trasmissione, letta dal thread di trasmissione a ogni frame
+    stream_quality: Arc<Mutex<BroadcastQuality>>,
 }
 
 impl CaptureState {
@@ -10,6 +21,9 @@ impl CaptureState {
         Self {
             capture_area: Some(CaptureArea::default()),
             is_fullscreen: true,
+            redactions: Arc::new(Mutex::new(Vec::new())),
+            privacy_masks: Arc::new(Mutex::new(Vec::new())),
+            stream_quality: Arc::new(Mutex::new(BroadcastQuality::default())),
         }
     }
 
@@ -23,8 +37,9 @@ impl CaptureState {
 
     pub fn set_capture_area(&mut self, area: Option<CaptureArea>) {
         if let Some(area) = &area {
-            let display = scrap::Display::primary().unwrap();
-            self.is_fullscreen = area.x == 0 && area.y == 0 && area.width == display.width() && area.height == display.height();
+            self.is_fullscreen = scrap::Display::primary().is_ok_and(|display| {
+                area.x == 0 && area.y == 0 && area.width == display.width() && area.height == display.height()
+            });
         } else {
             self.is_fullscreen = true;
         }
@@ -38,4 +53,31 @@ impl CaptureState {
     pub fn set_fullscreen(&mut self, value: bool) {
         self.is_fullscreen = value;
     }
+
+    pub fn get_redactions(&self) -> Arc<Mutex<Vec<RedactAnnotation>>> {
+        Arc::clone(&self.redactions)
+    }
+
+    pub fn set_redactions(&self, redactions: Vec<RedactAnnotation>) {
+        *self.redactions.lock().unwrap() = redactions;
+    }
+
+    pub fn get_privacy_masks(&self) -> Arc<Mutex<Vec<PrivacyMask>>> {
+        Arc::clone(&self.privacy_masks)
+    }
+
+    pub fn get_stream_quality(&self) -> Arc<Mutex<BroadcastQuality>> {
+        Arc::clone(&self.stream_quality)
+    }
+
+    pub fn set_stream_quality(&self, quality: BroadcastQuality) {
+        *self.stream_quality.lock().unwrap() = quality;
+    }
+
+    pub fn set_privacy_masks(&self, masks: &[PrivacyMask]) {
+        let mut current = self.privacy_masks.lock().unwrap();
+        if current.as_slice() != masks {
+            *current = masks.to_vec();
+        }
+    }
 }
@@ -1,12 +1,12 @@
-use std::sync::mpsc;
 use local_ip_address::local_ip;
+use crate::core::network::DEFAULT_PORT;
 
 pub struct NetworkState {
     pub address: String,
     pub auto_detect_ip: bool, // New flag for toggling auto/manual IP selection
-    stop_tx: Option<mpsc::Sender<()>>,
-    broadcast_stop_tx: Option<mpsc::Sender<()>>,
-    record_stop_tx: Option<mpsc::Sender<()>>,
+    pub port: u16,
+    // Password richiesta dal caster, vuota se non serve
+    pub password: String,
 }
 
 impl NetworkState {
@@ -19,9 +19,8 @@ impl NetworkState {
         Self {
             address: ip_address,
             auto_detect_ip: true, // Default to auto-detection
-            stop_tx: None,
-            broadcast_stop_tx: None,
-            record_stop_tx: None,
+            port: DEFAULT_PORT,
+            password: String::new(),
         }
     }
 
@@ -33,27 +32,11 @@ impl NetworkState {
         self.address = value;
     }
 
-    pub fn get_broadcast_stop_tx(&self) -> Option<mpsc::Sender<()>> {
-        self.broadcast_stop_tx.clone()
+    pub fn get_port(&self) -> u16 {
+        self.port
     }
 
-    pub fn set_broadcast_stop_tx(&mut self, tx: Option<mpsc::Sender<()>>) {
-        self.broadcast_stop_tx = tx;
-    }
-
-    pub fn get_record_stop_tx(&self) -> Option<mpsc::Sender<()>> {
-        self.record_stop_tx.clone()
-    }
-
-    pub fn set_record_stop_tx(&mut self, tx: Option<mpsc::Sender<()>>) {
-        self.record_stop_tx = tx;
-    }
-
-    pub fn get_stop_tx(&self) -> Option<mpsc::Sender<()>> {
-        self.stop_tx.clone()
-    }
-
-    pub fn set_stop_tx(&mut self, tx: Option<mpsc::Sender<()>>) {
-        self.stop_tx = tx;
+    pub fn set_port(&mut self, value: u16) {
+        self.port = value;
     }
 }
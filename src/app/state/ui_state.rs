@@ -4,7 +4,8 @@ pub struct UIState {
     show_monitor_selection: bool,
     show_shortcuts_menu: bool,
     show_caster_preview_window: bool,
-
+    show_received_annotations: bool,
+    show_stats_panel: bool,
 }
 
 impl UIState {
@@ -15,6 +16,8 @@ impl UIState {
             show_monitor_selection: false,
             show_shortcuts_menu: false,
             show_caster_preview_window: false,
+            show_received_annotations: true,
+            show_stats_panel: false,
         }
     }
 
@@ -57,4 +60,20 @@ impl UIState {
     pub fn set_showing_caster_preview_window(&mut self, value: bool) {
         self.show_caster_preview_window = value;
     }
+
+    pub fn is_showing_received_annotations(&self) -> bool {
+        self.show_received_annotations
+    }
+
+    pub fn set_showing_received_annotations(&mut self, value: bool) {
+        self.show_received_annotations = value;
+    }
+
+    pub fn is_showing_stats_panel(&self) -> bool {
+        self.show_stats_panel
+    }
+
+    pub fn set_showing_stats_panel(&mut self, value: bool) {
+        self.show_stats_panel = value;
+    }
 }
@@ -1,13 +1,29 @@
-use crate::app::gui::app_main::Theme;
+use std::collections::HashMap;
+
+use crate::app::settings_file::Theme;
+use crate::core::quality::BroadcastQuality;
+use crate::core::recorder::RecordingProfile;
+use crate::utils::logging::{self, LogLevel};
+use crate::utils::redaction::PrivacyMask;
 
 pub struct UserSettings {
     theme: Theme,
+    // Maschere di privacy per ciascun monitor, indicizzate con `multi_monitor::monitor_id`
+    privacy_masks: HashMap<String, Vec<PrivacyMask>>,
+    recording_profile: RecordingProfile,
+    // Qualità della trasmissione (framerate e risoluzione della registrazione sono nel profilo)
+    stream_quality: BroadcastQuality,
+    log_level: LogLevel,
 }
 
 impl UserSettings {
     pub fn new() -> Self {
         Self {
             theme: Theme::Dark,
+            privacy_masks: HashMap::new(),
+            recording_profile: RecordingProfile::default(),
+            stream_quality: BroadcastQuality::default(),
+            log_level: LogLevel::default(),
         }
     }
 
@@ -18,4 +34,50 @@ impl UserSettings {
     pub fn set_theme(&mut self, new_theme: Theme) {
         self.theme = new_theme;
     }
+
+    pub fn get_privacy_masks(&self, monitor: &str) -> &[PrivacyMask] {
+        self.privacy_masks.get(monitor).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn get_privacy_masks_mut(&mut self, monitor: &str) -> &mut Vec<PrivacyMask> {
+        self.privacy_masks.entry(monitor.to_string()).or_default()
+    }
+
+    pub fn get_all_privacy_masks(&self) -> &HashMap<String, Vec<PrivacyMask>> {
+        &self.privacy_masks
+    }
+
+    pub fn set_all_privacy_masks(&mut self, masks: HashMap<String, Vec<PrivacyMask>>) {
+        self.privacy_masks = masks;
+    }
+
+    pub fn get_recording_profile(&self) -> &RecordingProfile {
+        &self.recording_profile
+    }
+
+    pub fn set_recording_profile(&mut self, profile: RecordingProfile) {
+        self.recording_profile = profile;
+    }
+
+    pub fn get_recording_profile_mut(&mut self) -> &mut RecordingProfile {
+        &mut self.recording_profile
+    }
+
+    pub fn get_stream_quality(&self) -> &BroadcastQuality {
+        &self.stream_quality
+    }
+
+    pub fn get_stream_quality_mut(&mut self) -> &mut BroadcastQuality {
+        &mut self.stream_quality
+    }
+
+    pub fn get_log_level(&self) -> LogLevel {
+        self.log_level
+    }
+
+    /// Cambia anche la verbosità del logger in esecuzione
+    pub fn set_log_level(&mut self, level: LogLevel) {
+        self.log_level = level;
+        logging::set_level(level);
+    }
 }
@@ -0,0 +1,183 @@
+// src/cli.rs
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use screen_stream_x::core::capture::{CaptureArea, CaptureSource};
+use screen_stream_x::app::headless::{self, CastOptions, HeadlessResult, QualityOptions, ReceiveOptions, RecordOptions};
+use screen_stream_x::core::network::{FrameEncoding, DEFAULT_PORT};
+use screen_stream_x::core::quality::{OutputScale, MAX_FRAMERATE, MIN_FRAMERATE};
+use screen_stream_x::utils::logging;
+
+/// Argomenti da riga di comando. Senza sottocomando si apre la GUI.
+#[derive(Parser, Debug)]
+#[command(version, about = "Screen casting, receiving and recording")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Broadcast the screen to receivers without opening a window
+    Cast(CastArgs),
+    /// Connect to a caster and record the received stream to a file
+    Receive(ReceiveArgs),
+    /// Record the screen to a file without opening a window
+    Record(RecordArgs),
+}
+
+/// Monitor e area da catturare, comuni a `cast` e `record`
+#[derive(Args, Debug)]
+pub struct SourceArgs {
+    /// Index of the monitor to capture (0 is the first one); defaults to the primary monitor
+    #[arg(long)]
+    pub source: Option<usize>,
+    /// Capture only this area of the monitor, as x,y,width,height in pixels
+    #[arg(long, value_parser = parse_area)]
+    pub area: Option<CaptureArea>,
+}
+
+/// Framerate e risoluzione in uscita, comuni a tutti i sottocomandi
+#[derive(Args, Debug)]
+pub struct QualityArgs {
+    /// Frames per second (1-60); defaults to the saved settings
+    #[arg(long, value_parser = clap::value_parser!(u32).range(MIN_FRAMERATE as i64..=MAX_FRAMERATE as i64))]
+    pub fps: Option<u32>,
+    /// Output size: a percentage of the captured size (75%) or a maximum width in pixels (1280)
+    #[arg(long)]
+    pub scale: Option<OutputScale>,
+}
+
+#[derive(Args, Debug)]
+pub struct CastArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+    /// TCP port receivers connect to
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    pub port: u16,
+    /// Format of the frames sent to receivers; defaults to the saved settings
+    #[arg(long, value_enum)]
+    pub codec: Option<StreamCodec>,
+    /// JPEG quality (1-100) used with --codec jpeg
+    #[arg(long, default_value_t = DEFAULT_JPEG_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub jpeg_quality: u8,
+    /// Lower the framerate, size and JPEG quality when receivers fall behind
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    pub adaptive: Option<bool>,
+    /// Require receivers to send this password when they connect
+    #[arg(long)]
+    pub password: Option<String>,
+    #[command(flatten)]
+    pub quality: QualityArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct ReceiveArgs {
+    /// Address of the caster
+    pub address: String,
+    /// TCP port of the caster
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    pub port: u16,
+    /// Password expected by the caster
+    #[arg(long)]
+    pub password: Option<String>,
+    /// Output file, or directory to save receiver_recorded_video.mp4 into
+    #[arg(long, short, default_value = "recordings")]
+    pub output: PathBuf,
+    /// ffmpeg video encoder; defaults to the one in the saved settings
+    #[arg(long)]
+    pub codec: Option<String>,
+    #[command(flatten)]
+    pub quality: QualityArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct RecordArgs {
+    #[command(flatten)]
+    pub source: SourceArgs,
+    /// Output file, or directory to save recorded_video.mp4 into
+    #[arg(long, short, default_value = "recordings")]
+    pub output: PathBuf,
+    /// ffmpeg video encoder; defaults to the one in the saved settings
+    #[arg(long)]
+    pub codec: Option<String>,
+    #[command(flatten)]
+    pub quality: QualityArgs,
+    /// Stop after this many seconds instead of waiting for Ctrl+C
+    #[arg(long)]
+    pub duration: Option<u64>,
+}
+
+// Qualità JPEG usata se `--codec jpeg` non è accompagnato da `--jpeg-quality`
+const DEFAULT_JPEG_QUALITY: u8 = 80;
+
+/// Formato dei frame trasmessi
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum StreamCodec {
+    /// Uncompressed RGBA frames
+    Raw,
+    /// JPEG-compressed frames, much smaller on the network
+    Jpeg,
+}
+
+impl From<QualityArgs> for QualityOptions {
+    fn from(args: QualityArgs) -> Self {
+        QualityOptions { fps: args.fps, scale: args.scale }
+    }
+}
+
+impl From<SourceArgs> for CaptureSource {
+    fn from(args: SourceArgs) -> Self {
+        CaptureSource { display: args.source, area: args.area }
+    }
+}
+
+/// Esegue il sottocomando senza aprire la finestra
+pub fn run(command: CliCommand) -> HeadlessResult {
+    logging::set_level(headless::saved_log_level());
+    match command {
+        CliCommand::Cast(args) => headless::cast(CastOptions {
+            source: args.source.into(),
+            port: args.port,
+            password: args.password,
+            quality: args.quality.into(),
+            encoding: args.codec.map(|codec| match codec {
+                StreamCodec::Raw => FrameEncoding::Raw,
+                StreamCodec::Jpeg => FrameEncoding::Jpeg(args.jpeg_quality),
+            }),
+            adaptive: args.adaptive,
+        }),
+        CliCommand::Receive(args) => headless::receive(ReceiveOptions {
+            address: args.address,
+            port: args.port,
+            password: args.password,
+            output: args.output,
+            codec: args.codec,
+            quality: args.quality.into(),
+        }),
+        CliCommand::Record(args) => headless::record(RecordOptions {
+            source: args.source.into(),
+            output: args.output,
+            codec: args.codec,
+            quality: args.quality.into(),
+            duration: args.duration.map(Duration::from_secs),
+        }),
+    }
+}
+
+/// Interpreta un'area nel formato "x,y,larghezza,altezza"
+fn parse_area(value: &str) -> Result<CaptureArea, String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("expected x,y,width,height: {}", e))?;
+    match parts[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(CaptureArea::new(x, y, width, height)),
+        [_, _, _, _] => Err("width and height must be greater than zero".to_string()),
+        _ => Err("expected four values: x,y,width,height".to_string()),
+    }
+}
@@ -0,0 +1,375 @@
+// src/core/adaptive.rs
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::network::{FrameEncoding, ReceiverFeedback};
+use crate::core::quality::{BroadcastQuality, MIN_FRAMERATE};
+use log::info;
+
+// Tempo minimo tra due riduzioni, per lasciare ai receiver il tempo di riportare l'effetto della precedente
+const DOWNGRADE_INTERVAL: Duration = Duration::from_secs(1);
+// Tempo senza congestione dopo cui la qualità risale di un passo
+const RECOVERY_DELAY: Duration = Duration::from_secs(5);
+// Un receiver con più frame in coda di così non sta al passo
+const MAX_QUEUE_DEPTH: u32 = 2;
+// Un receiver che riceve meno di questa frazione dei frame inviati è congestionato
+const MIN_DELIVERY_RATIO: f32 = 0.8;
+// Una scrittura sul socket che occupa più di questa frazione del tempo di un frame indica buffer TCP pieni
+const MAX_SEND_RATIO: f32 = 0.5;
+const SCALE_STEP: u32 = 10;
+const JPEG_QUALITY_STEP: u8 = 10;
+
+/// Limiti entro cui la trasmissione può ridurre la qualità quando la rete è congestionata
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveBounds {
+    pub enabled: bool,
+    pub min_fps: u32,
+    /// Percentuale minima della risoluzione impostata
+    pub min_scale_percent: u32,
+    /// Qualità JPEG minima, usata solo se i frame sono inviati in JPEG
+    pub min_jpeg_quality: u8,
+}
+
+impl Default for AdaptiveBounds {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_fps: 5,
+            min_scale_percent: 50,
+            min_jpeg_quality: 30,
+        }
+    }
+}
+
+/// Qualità effettivamente usata dalla trasmissione
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AppliedQuality {
+    pub fps: u32,
+    /// Percentuale applicata sopra la risoluzione impostata (100 = nessuna riduzione)
+    pub scale_percent: u32,
+    pub encoding: FrameEncoding,
+}
+
+impl AppliedQuality {
+    /// La qualità massima consentita dalle impostazioni
+    pub fn from_settings(settings: &BroadcastQuality) -> Self {
+        Self {
+            fps: settings.quality.fps(),
+            scale_percent: 100,
+            encoding: match settings.encoding {
+                FrameEncoding::Jpeg(quality) => FrameEncoding::Jpeg(quality.clamp(1, 100)),
+                encoding => encoding,
+            },
+        }
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps.max(MIN_FRAMERATE) as f64)
+    }
+}
+
+impl fmt::Display for AppliedQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} fps, {}% size, {}", self.fps, self.scale_percent, self.encoding)
+    }
+}
+
+/// Misure con cui il controller decide se la rete è congestionata
+pub struct NetworkConditions<'a> {
+    /// Ultime statistiche inviate da ciascun receiver
+    pub feedback: &'a [ReceiverFeedback],
+    /// Frame al secondo effettivamente inviati
+    pub sent_fps: f32,
+    /// Scrittura più lenta dell'ultimo frame tra tutti i receiver
+    pub max_send_time: Duration,
+}
+
+/// Sceglie la qualità della trasmissione: la riduce di un passo alla volta finché la rete è
+/// congestionata (prima la qualità JPEG, poi la risoluzione, infine il framerate) e la fa
+/// risalire nell'ordine inverso quando la congestione passa.
+pub struct AdaptiveController {
+    current: Option<AppliedQuality>,
+    last_change: Instant,
+    last_congestion: Instant,
+}
+
+impl Default for AdaptiveController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdaptiveController {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self { current: None, last_change: now, last_congestion: now }
+    }
+
+    /// Qualità da usare per il prossimo frame. Le impostazioni possono cambiare durante la trasmissione:
+    /// la qualità scelta resta sempre entro i loro limiti.
+    pub fn update(&mut self, settings: &BroadcastQuality, conditions: &NetworkConditions) -> AppliedQuality {
+        let max = AppliedQuality::from_settings(settings);
+        let bounds = &settings.adaptive;
+        if !bounds.enabled {
+            self.current = Some(max);
+            return max;
+        }
+
+        let mut quality = clamp(self.current.unwrap_or(max), max, bounds);
+        if is_congested(&quality, conditions) {
+            self.last_congestion = Instant::now();
+            if self.last_change.elapsed() >= DOWNGRADE_INTERVAL {
+                if let Some(lower) = downgrade(quality, bounds) {
+                    info!("Network congested, lowering broadcast quality to {}", lower);
+                    quality = lower;
+                    self.last_change = Instant::now();
+                }
+            }
+        } else if self.last_congestion.elapsed() >= RECOVERY_DELAY && self.last_change.elapsed() >= RECOVERY_DELAY {
+            if let Some(higher) = upgrade(quality, max) {
+                info!("Network recovered, raising broadcast quality to {}", higher);
+                quality = higher;
+                self.last_change = Instant::now();
+            }
+        }
+
+        self.current = Some(quality);
+        quality
+    }
+}
+
+fn is_congested(quality: &AppliedQuality, conditions: &NetworkConditions) -> bool {
+    let slow_send = conditions.max_send_time.as_secs_f32() > quality.frame_duration().as_secs_f32() * MAX_SEND_RATIO;
+    // `sent_fps` è una media che dopo una riduzione del framerate resta per un po' sopra il nuovo valore:
+    // i receiver vanno confrontati con il framerate applicato, o con quello inviato se la cattura non ci arriva
+    let expected_fps = conditions.sent_fps.min(quality.fps as f32);
+    let lagging_receiver = conditions.feedback.iter().any(|feedback| {
+        feedback.queue_depth > MAX_QUEUE_DEPTH || feedback.received_fps < expected_fps * MIN_DELIVERY_RATIO
+    });
+    slow_send || lagging_receiver
+}
+
+/// Riporta `quality` entro i limiti, nel caso le impostazioni siano cambiate
+fn clamp(quality: AppliedQuality, max: AppliedQuality, bounds: &AdaptiveBounds) -> AppliedQuality {
+    let encoding = match (max.encoding, quality.encoding) {
+        (FrameEncoding::Jpeg(max_quality), FrameEncoding::Jpeg(current)) => {
+            FrameEncoding::Jpeg(current.clamp(bounds.min_jpeg_quality.clamp(1, max_quality), max_quality))
+        }
+        (encoding, _) => encoding,
+    };
+    AppliedQuality {
+        fps: quality.fps.clamp(bounds.min_fps.clamp(MIN_FRAMERATE, max.fps), max.fps),
+        scale_percent: quality.scale_percent.clamp(bounds.min_scale_percent.clamp(1, 100), 100),
+        encoding,
+    }
+}
+
+fn downgrade(quality: AppliedQuality, bounds: &AdaptiveBounds) -> Option<AppliedQuality> {
+    if let FrameEncoding::Jpeg(jpeg_quality) = quality.encoding {
+        if jpeg_quality > bounds.min_jpeg_quality {
+            let lower = jpeg_quality.saturating_sub(JPEG_QUALITY_STEP).max(bounds.min_jpeg_quality).max(1);
+            return Some(AppliedQuality { encoding: FrameEncoding::Jpeg(lower), ..quality });
+        }
+    }
+    if quality.scale_percent > bounds.min_scale_percent {
+        let lower = quality.scale_percent.saturating_sub(SCALE_STEP).max(bounds.min_scale_percent).max(1);
+        return Some(AppliedQuality { scale_percent: lower, ..quality });
+    }
+    let min_fps = bounds.min_fps.max(MIN_FRAMERATE);
+    if quality.fps > min_fps {
+        let lower = (quality.fps * 4 / 5).min(quality.fps - 1).max(min_fps);
+        return Some(AppliedQuality { fps: lower, ..quality });
+    }
+    None
+}
+
+fn upgrade(quality: AppliedQuality, max: AppliedQuality) -> Option<AppliedQuality> {
+    if quality.fps < max.fps {
+        let higher = (quality.fps * 5 / 4).max(quality.fps + 1).min(max.fps);
+        return Some(AppliedQuality { fps: higher, ..quality });
+    }
+    if quality.scale_percent < 100 {
+        return Some(AppliedQuality { scale_percent: (quality.scale_percent + SCALE_STEP).min(100), ..quality });
+    }
+    match (quality.encoding, max.encoding) {
+        (FrameEncoding::Jpeg(current), FrameEncoding::Jpeg(max_quality)) if current < max_quality => {
+            let higher = current.saturating_add(JPEG_QUALITY_STEP).min(max_quality);
+            Some(AppliedQuality { encoding: FrameEncoding::Jpeg(higher), ..quality })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::quality::StreamQuality;
+
+    fn settings(fps: u32, encoding: FrameEncoding) -> BroadcastQuality {
+        BroadcastQuality {
+            quality: StreamQuality { fps, ..StreamQuality::default() },
+            encoding,
+            adaptive: AdaptiveBounds::default(),
+        }
+    }
+
+    fn feedback(received_fps: f32, queue_depth: u32) -> ReceiverFeedback {
+        ReceiverFeedback { received_fps, bytes_per_second: 0.0, queue_depth }
+    }
+
+    /// Aggiorna il controller come se l'ultima modifica e l'ultima congestione fossero lontane nel tempo
+    fn update_later(
+        controller: &mut AdaptiveController,
+        settings: &BroadcastQuality,
+        conditions: &NetworkConditions,
+    ) -> AppliedQuality {
+        let past = Instant::now() - RECOVERY_DELAY * 2;
+        controller.last_change = past;
+        controller.last_congestion = past;
+        controller.update(settings, conditions)
+    }
+
+    fn congested(sent_fps: f32) -> NetworkConditions<'static> {
+        const BACKLOG: &[ReceiverFeedback] =
+            &[ReceiverFeedback { received_fps: 0.0, bytes_per_second: 0.0, queue_depth: 10 }];
+        NetworkConditions { feedback: BACKLOG, sent_fps, max_send_time: Duration::ZERO }
+    }
+
+    fn idle(sent_fps: f32) -> NetworkConditions<'static> {
+        NetworkConditions { feedback: &[], sent_fps, max_send_time: Duration::ZERO }
+    }
+
+    #[test]
+    fn downgrades_jpeg_then_scale_then_fps() {
+        let settings = settings(20, FrameEncoding::Jpeg(80));
+        let mut controller = AdaptiveController::new();
+        let mut steps = vec![controller.update(&settings, &idle(20.0))];
+        for _ in 0..30 {
+            steps.push(update_later(&mut controller, &settings, &congested(20.0)));
+        }
+
+        let first_scale = steps.iter().position(|q| q.scale_percent < 100).unwrap();
+        let first_fps = steps.iter().position(|q| q.fps < 20).unwrap();
+        // La qualità JPEG arriva al minimo prima di toccare la risoluzione, e la risoluzione prima del framerate
+        assert_eq!(steps[first_scale].encoding, FrameEncoding::Jpeg(30));
+        assert!(steps[..first_scale].iter().all(|q| q.fps == 20));
+        assert_eq!(steps[first_fps].scale_percent, 50);
+        assert!(first_scale < first_fps);
+
+        let floor = AppliedQuality { fps: 5, scale_percent: 50, encoding: FrameEncoding::Jpeg(30) };
+        assert_eq!(*steps.last().unwrap(), floor);
+        // Ogni passo cambia una sola cosa
+        for pair in steps.windows(2) {
+            let (before, after) = (pair[0], pair[1]);
+            let changed = [
+                before.fps != after.fps,
+                before.scale_percent != after.scale_percent,
+                before.encoding != after.encoding,
+            ];
+            assert!(changed.iter().filter(|c| **c).count() <= 1, "{} -> {}", before, after);
+        }
+    }
+
+    #[test]
+    fn recovers_fps_then_scale_then_jpeg() {
+        let settings = settings(20, FrameEncoding::Jpeg(80));
+        let mut controller = AdaptiveController::new();
+        for _ in 0..30 {
+            update_later(&mut controller, &settings, &congested(20.0));
+        }
+        let mut steps = Vec::new();
+        for _ in 0..30 {
+            steps.push(update_later(&mut controller, &settings, &idle(20.0)));
+        }
+
+        let fps_restored = steps.iter().position(|q| q.fps == 20).unwrap();
+        let first_scale = steps.iter().position(|q| q.scale_percent > 50).unwrap();
+        let first_jpeg = steps.iter().position(|q| q.encoding != FrameEncoding::Jpeg(30)).unwrap();
+        assert!(fps_restored < first_scale);
+        assert_eq!(steps[first_jpeg].scale_percent, 100);
+        assert_eq!(*steps.last().unwrap(), AppliedQuality::from_settings(&settings));
+    }
+
+    #[test]
+    fn quality_waits_before_changing_again() {
+        let settings = settings(20, FrameEncoding::Jpeg(80));
+        let mut controller = AdaptiveController::new();
+        let lowered = update_later(&mut controller, &settings, &congested(20.0));
+        assert_eq!(lowered.encoding, FrameEncoding::Jpeg(70));
+        // Subito dopo una riduzione non ne arriva un'altra, né la qualità risale
+        assert_eq!(controller.update(&settings, &congested(20.0)), lowered);
+        assert_eq!(controller.update(&settings, &idle(20.0)), lowered);
+    }
+
+    #[test]
+    fn quality_is_clamped_to_changed_settings() {
+        let mut settings = settings(30, FrameEncoding::Jpeg(90));
+        let mut controller = AdaptiveController::new();
+        for _ in 0..30 {
+            update_later(&mut controller, &settings, &congested(30.0));
+        }
+
+        // Limiti minimi più alti di quelli già raggiunti
+        settings.adaptive = AdaptiveBounds { enabled: true, min_fps: 15, min_scale_percent: 80, min_jpeg_quality: 60 };
+        let quality = controller.update(&settings, &congested(30.0));
+        assert_eq!(quality, AppliedQuality { fps: 15, scale_percent: 80, encoding: FrameEncoding::Jpeg(60) });
+
+        // Massimi più bassi della qualità attuale, anche sotto i minimi
+        settings.quality.fps = 10;
+        settings.encoding = FrameEncoding::Jpeg(50);
+        let quality = controller.update(&settings, &idle(10.0));
+        assert_eq!(quality, AppliedQuality { fps: 10, scale_percent: 80, encoding: FrameEncoding::Jpeg(50) });
+
+        // Con un formato non compresso la qualità JPEG non ha più senso
+        settings.encoding = FrameEncoding::Raw;
+        assert_eq!(controller.update(&settings, &idle(10.0)).encoding, FrameEncoding::Raw);
+    }
+
+    #[test]
+    fn disabled_adaptation_keeps_the_maximum() {
+        let mut settings = settings(20, FrameEncoding::Jpeg(80));
+        settings.adaptive.enabled = false;
+        let mut controller = AdaptiveController::new();
+        for _ in 0..5 {
+            let quality = update_later(&mut controller, &settings, &congested(20.0));
+            assert_eq!(quality, AppliedQuality::from_settings(&settings));
+        }
+    }
+
+    #[test]
+    fn lagging_sent_average_after_fps_downgrade_is_not_congestion() {
+        let quality = AppliedQuality { fps: 16, scale_percent: 50, encoding: FrameEncoding::Raw };
+        // La media dei frame inviati ricorda ancora i 20 fps precedenti, il receiver riceve già a 16
+        let receivers = [feedback(15.0, 0)];
+        let conditions = NetworkConditions { feedback: &receivers, sent_fps: 20.0, max_send_time: Duration::ZERO };
+        assert!(!is_congested(&quality, &conditions));
+
+        let receivers = [feedback(10.0, 0)];
+        let conditions = NetworkConditions { feedback: &receivers, sent_fps: 20.0, max_send_time: Duration::ZERO };
+        assert!(is_congested(&quality, &conditions));
+    }
+
+    #[test]
+    fn slow_capture_is_not_congestion() {
+        let quality = AppliedQuality { fps: 30, scale_percent: 100, encoding: FrameEncoding::Raw };
+        // La cattura produce solo 12 fps e il receiver li riceve tutti
+        let receivers = [feedback(12.0, 0)];
+        let conditions = NetworkConditions { feedback: &receivers, sent_fps: 12.0, max_send_time: Duration::ZERO };
+        assert!(!is_congested(&quality, &conditions));
+    }
+
+    #[test]
+    fn slow_send_or_queued_frames_are_congestion() {
+        let quality = AppliedQuality { fps: 20, scale_percent: 100, encoding: FrameEncoding::Raw };
+        let slow = NetworkConditions { feedback: &[], sent_fps: 20.0, max_send_time: Duration::from_millis(40) };
+        assert!(is_congested(&quality, &slow));
+
+        let receivers = [feedback(20.0, MAX_QUEUE_DEPTH + 1)];
+        let queued = NetworkConditions { feedback: &receivers, sent_fps: 20.0, max_send_time: Duration::ZERO };
+        assert!(is_congested(&quality, &queued));
+    }
+}
@@ -0,0 +1,312 @@
+// src/core/broadcast_session.rs
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::error::{Error, Result, Worker};
+use crate::core::network::{deserialize_feedback, read_payload, ReceiverFeedback, MAX_CONTROL_PAYLOAD};
+use crate::core::worker::{CancellationToken, WorkerHandle};
+use log::{debug, info, warn};
+
+// Tempo concesso a un receiver per inviare la password dopo la connessione
+const PASSWORD_TIMEOUT: Duration = Duration::from_secs(5);
+// Il listener non è bloccante, così il thread si accorge in fretta della chiusura della sessione
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Ultime statistiche inviate da ciascun receiver, con l'istante in cui sono arrivate
+pub(crate) type FeedbackMap = HashMap<SocketAddr, (ReceiverFeedback, Instant)>;
+
+/// Listener, receiver connessi e thread che li accetta per una singola trasmissione.
+/// Ogni receiver ha un thread che legge le statistiche che invia (`ReceiverFeedback`).
+/// `shutdown` (chiamato anche dal `Drop`) chiude tutte le connessioni, attende i thread
+/// e libera la porta, così più sessioni possono esistere una dopo l'altra o in parallelo.
+pub struct BroadcastSession {
+    local_addr: SocketAddr,
+    receivers: Arc<Mutex<Vec<TcpStream>>>,
+    feedback: Arc<Mutex<FeedbackMap>>,
+    accept_thread: Option<WorkerHandle>,
+}
+
+impl BroadcastSession {
+    /// Si mette in ascolto su `port` (0 per una porta libera qualsiasi, vedi `local_addr`).
+    /// Con una `password` i receiver devono inviarla subito dopo la connessione, altrimenti vengono scartati.
+    pub fn bind(port: u16, password: Option<String>) -> Result<Self> {
+        let listen = || -> io::Result<_> {
+            let listener = TcpListener::bind(("0.0.0.0", port))?;
+            listener.set_nonblocking(true)?;
+            let local_addr = listener.local_addr()?;
+            Ok((listener, local_addr))
+        };
+        let (listener, local_addr) = listen().map_err(|source| Error::Bind { port, source })?;
+
+        let receivers = Arc::new(Mutex::new(Vec::new()));
+        let feedback = Arc::new(Mutex::new(HashMap::new()));
+        let accept_thread = {
+            let receivers = Arc::clone(&receivers);
+            let feedback = Arc::clone(&feedback);
+            WorkerHandle::spawn(
+                Worker::Listener,
+                None,
+                || Ok(()),
+                move |(), cancel| accept_loop(listener, password, receivers, feedback, cancel),
+            )?
+        };
+
+        Ok(Self {
+            local_addr,
+            receivers,
+            feedback,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.receivers.lock().unwrap().len()
+    }
+
+    /// Receiver connessi e autenticati, a cui il thread di trasmissione scrive i messaggi
+    pub(crate) fn receivers(&self) -> Arc<Mutex<Vec<TcpStream>>> {
+        Arc::clone(&self.receivers)
+    }
+
+    pub(crate) fn feedback(&self) -> Arc<Mutex<FeedbackMap>> {
+        Arc::clone(&self.feedback)
+    }
+
+    /// Smette di accettare connessioni, avvisa i receiver della fine della trasmissione e chiude
+    /// le connessioni. Chiamarlo più volte non ha effetto.
+    pub fn shutdown(&mut self) {
+        let Some(accept_thread) = self.accept_thread.take() else {
+            return;
+        };
+        accept_thread.stop();
+        disconnect_receivers(&mut self.receivers.lock().unwrap());
+    }
+}
+
+impl Drop for BroadcastSession {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Accetta i receiver finché la sessione è attiva. Il listener viene chiuso all'uscita.
+fn accept_loop(
+    listener: TcpListener,
+    password: Option<String>,
+    receivers: Arc<Mutex<Vec<TcpStream>>>,
+    feedback: Arc<Mutex<FeedbackMap>>,
+    cancel: CancellationToken,
+) -> Result<()> {
+    // Connessioni aperte, con il thread che le autentica e poi ne legge le statistiche
+    let mut readers: Vec<(TcpStream, thread::JoinHandle<()>)> = Vec::new();
+
+    let mut result = Ok(());
+    while !cancel.is_cancelled() {
+        readers.retain(|(_, handle)| !handle.is_finished());
+
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                cancel.wait_timeout(ACCEPT_POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => {
+                result = Err(Error::Network(e));
+                break;
+            }
+        };
+        if let Err(e) = stream.set_nonblocking(false) {
+            warn!("Failed to configure receiver connection: {:?}", e);
+            continue;
+        }
+
+        // L'autenticazione e la lettura avvengono in un thread a parte per non bloccare le altre connessioni
+        let Ok(reader_stream) = stream.try_clone() else {
+            continue;
+        };
+        let password = password.clone();
+        let receivers = Arc::clone(&receivers);
+        let feedback = Arc::clone(&feedback);
+        let handle = thread::spawn(move || serve_receiver(stream, password.as_deref(), &receivers, &feedback));
+        readers.push((reader_stream, handle));
+    }
+
+    // Sblocca le letture in corso (comprese le autenticazioni, senza aspettare il timeout della password);
+    // la scrittura resta aperta per il segnale di chiusura inviato da `disconnect_receivers`
+    for (stream, handle) in readers {
+        let _ = stream.shutdown(Shutdown::Read);
+        let _ = handle.join();
+    }
+    debug!("Listener thread exiting.");
+    result
+}
+
+/// Verifica l'eventuale password, aggiunge il receiver a quelli a cui inviare i frame e ne legge
+/// le statistiche finché la connessione resta aperta
+fn serve_receiver(
+    mut stream: TcpStream,
+    password: Option<&str>,
+    receivers: &Mutex<Vec<TcpStream>>,
+    feedback: &Mutex<FeedbackMap>,
+) {
+    let Ok(addr) = stream.peer_addr() else {
+        let _ = stream.shutdown(Shutdown::Both);
+        return;
+    };
+    if let Some(password) = password {
+        let received = stream
+            .set_read_timeout(Some(PASSWORD_TIMEOUT))
+            .and_then(|_| read_payload(&mut stream, MAX_CONTROL_PAYLOAD));
+        match received {
+            Ok(received) if received == password.as_bytes() => {
+                let _ = stream.set_read_timeout(None);
+            }
+            _ => {
+                warn!("Receiver {} rejected: wrong or missing password", addr);
+                let _ = stream.shutdown(Shutdown::Both);
+                return;
+            }
+        }
+    }
+
+    let Ok(writer) = stream.try_clone() else {
+        let _ = stream.shutdown(Shutdown::Both);
+        return;
+    };
+    info!("Receiver {} connected", addr);
+    receivers.lock().unwrap().push(writer);
+
+    while let Ok(payload) = read_payload(&mut stream, MAX_CONTROL_PAYLOAD) {
+        match deserialize_feedback(&payload) {
+            Ok(received) => {
+                feedback.lock().unwrap().insert(addr, (received, Instant::now()));
+            }
+            Err(e) => warn!("Invalid feedback from receiver {}: {:?}", addr, e),
+        }
+    }
+    feedback.lock().unwrap().remove(&addr);
+    debug!("Receiver {} disconnected", addr);
+}
+
+/// Avvisa i receiver connessi della fine della trasmissione e chiude le connessioni
+fn disconnect_receivers(receivers: &mut Vec<TcpStream>) {
+    for stream in receivers.iter_mut() {
+        if let Err(e) = stream.write_all(&[0]) { // Segnale speciale per indicare la chiusura
+            warn!("Failed to send stop signal to receiver: {:?}", e);
+        } else {
+            debug!("Stop signal sent to receiver and connection closed.");
+        }
+
+        if let Err(e) = stream.shutdown(Shutdown::Both) {
+            warn!("Failed to shutdown receiver connection: {:?}", e);
+        }
+    }
+    receivers.clear();
+    info!("All receivers disconnected.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::network::{serialize_feedback, write_payload};
+    use std::io::Read;
+
+    const WAIT_LIMIT: Duration = Duration::from_secs(5);
+
+    fn wait_until(mut condition: impl FnMut() -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < WAIT_LIMIT {
+            if condition() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    fn connect(session: &BroadcastSession) -> TcpStream {
+        let stream = TcpStream::connect(("127.0.0.1", session.local_addr().port())).unwrap();
+        stream.set_read_timeout(Some(WAIT_LIMIT)).unwrap();
+        stream
+    }
+
+    /// Legge fino alla chiusura della connessione da parte della sessione
+    fn read_to_close(stream: &mut TcpStream) -> Vec<u8> {
+        let mut received = Vec::new();
+        stream.read_to_end(&mut received).unwrap();
+        received
+    }
+
+    #[test]
+    fn shutdown_closes_receivers_joins_threads_and_frees_the_port() {
+        let mut session = BroadcastSession::bind(0, None).unwrap();
+        let port = session.local_addr().port();
+        let mut client = connect(&session);
+        assert!(wait_until(|| session.receiver_count() == 1));
+
+        let feedback = ReceiverFeedback { received_fps: 20.0, bytes_per_second: 1000.0, queue_depth: 2 };
+        write_payload(&mut client, &serialize_feedback(&feedback).unwrap()).unwrap();
+        let feedback_map = session.feedback();
+        assert!(wait_until(|| feedback_map.lock().unwrap().values().any(|(f, _)| *f == feedback)));
+
+        session.shutdown();
+        assert!(session.accept_thread.is_none());
+        assert_eq!(session.receiver_count(), 0);
+        // Il thread che leggeva le statistiche del receiver è terminato e ha tolto la sua voce
+        assert!(feedback_map.lock().unwrap().is_empty());
+        assert_eq!(read_to_close(&mut client), vec![0]);
+
+        let rebound = BroadcastSession::bind(port, None).unwrap();
+        assert_eq!(rebound.local_addr().port(), port);
+    }
+
+    #[test]
+    fn drop_frees_the_port() {
+        let session = BroadcastSession::bind(0, None).unwrap();
+        let port = session.local_addr().port();
+        let mut client = connect(&session);
+        assert!(wait_until(|| session.receiver_count() == 1));
+
+        drop(session);
+        assert_eq!(read_to_close(&mut client), vec![0]);
+        BroadcastSession::bind(port, None).unwrap();
+    }
+
+    #[test]
+    fn wrong_password_is_rejected() {
+        let session = BroadcastSession::bind(0, Some("secret".to_string())).unwrap();
+        let mut client = connect(&session);
+        write_payload(&mut client, b"guess").unwrap();
+
+        assert!(read_to_close(&mut client).is_empty());
+        assert_eq!(session.receiver_count(), 0);
+    }
+
+    #[test]
+    fn right_password_is_accepted() {
+        let session = BroadcastSession::bind(0, Some("secret".to_string())).unwrap();
+        let mut client = connect(&session);
+        write_payload(&mut client, b"secret").unwrap();
+
+        assert!(wait_until(|| session.receiver_count() == 1));
+    }
+
+    #[test]
+    fn oversized_password_is_rejected_without_reading_it() {
+        let session = BroadcastSession::bind(0, Some("secret".to_string())).unwrap();
+        let mut client = connect(&session);
+        client.write_all(&u32::MAX.to_be_bytes()).unwrap();
+
+        assert!(read_to_close(&mut client).is_empty());
+        assert_eq!(session.receiver_count(), 0);
+    }
+}
@@ -0,0 +1,312 @@
+// src/core/capture.rs
+use scrap::{Capturer, Display};
+use std::io::ErrorKind::WouldBlock;
+use std::thread;
+use std::time::Duration;
+use image::imageops::{self, FilterType};
+use image::{ImageBuffer, Rgba};
+use serde::{Serialize, Deserialize};
+use crate::core::error::{Error, Result};
+use crate::core::quality::OutputScale;
+use crate::core::stats::unix_millis;
+use crate::utils::annotations::RedactAnnotation;
+use std::sync::{Arc, Mutex};
+use crate::utils::redaction::{mask_rgba, redact_rgba, PrivacyMask};
+use log::warn;
+
+// Errori di cattura consecutivi tollerati prima di considerare persa la cattura
+const MAX_CAPTURE_FAILURES: u32 = 20;
+
+// Definizione di ScreenCapture per la serializzazione
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ScreenCapture {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>, // I dati dell'immagine memorizzati come un vettore di byte
+    // Istante della cattura (ms dallo UNIX epoch), con cui il receiver misura la latenza
+    pub captured_at: u64,
+}
+
+impl ScreenCapture {
+    pub fn from_image_buffer(buffer: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Self {
+        let (width, height) = buffer.dimensions();
+        let data = buffer.clone().into_raw();
+        ScreenCapture { width, height, data, captured_at: unix_millis() }
+    }
+
+    /// Verifica che i pixel corrispondano alle dimensioni, ad esempio per un frame arrivato dalla rete
+    pub fn validate(&self) -> Result<()> {
+        let expected = (self.width as usize).checked_mul(self.height as usize).and_then(|pixels| pixels.checked_mul(4));
+        if expected == Some(self.data.len()) {
+            Ok(())
+        } else {
+            Err(Error::InvalidFrame { width: self.width, height: self.height, len: self.data.len() })
+        }
+    }
+
+    /// Nasconde (pixelando o sfocando) le regioni da oscurare, prima che il frame lasci il caster
+    pub fn redact(&mut self, regions: &[RedactAnnotation]) {
+        if !regions.is_empty() {
+            redact_rgba(&mut self.data, self.width as usize, self.height as usize, regions);
+        }
+    }
+
+    /// Ridimensiona il frame secondo `scale` con un filtro bicubico (Catmull-Rom),
+    /// che mantiene leggibile il testo anche a risoluzioni ridotte.
+    /// Fallisce con `Error::InvalidFrame` se i pixel non corrispondono alle dimensioni del frame.
+    pub fn scaled(self, scale: OutputScale) -> Result<Self> {
+        let (width, height) = scale.output_size(self.width as usize, self.height as usize);
+        self.resized(width, height)
+    }
+
+    /// Come `scaled`, ma verso dimensioni esplicite
+    pub fn resized(self, width: usize, height: usize) -> Result<Self> {
+        self.validate()?;
+        if (width as u32, height as u32) == (self.width, self.height) {
+            return Ok(self);
+        }
+        let captured_at = self.captured_at;
+        let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(self.width, self.height, self.data)
+            .expect("frame size checked by validate");
+        let resized = imageops::resize(&buffer, width as u32, height as u32, FilterType::CatmullRom);
+        Ok(ScreenCapture { captured_at, ..ScreenCapture::from_image_buffer(&resized) })
+    }
+
+    // pub fn into_image_buffer(self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    //     ImageBuffer::from_raw(self.width, self.height, self.data).expect("Errore nella conversione in ImageBuffer")
+    // }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DragState {
+    pub start_pos: Option<egui::Pos2>,
+    pub end_pos: Option<egui::Pos2>,
+}
+
+impl DragState {
+    pub fn new() -> Self {
+        DragState {
+            start_pos: None,
+            end_pos: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.start_pos = None;
+        self.end_pos = None;
+    }
+}
+
+
+/// Struttura che rappresenta l'area di cattura selezionata.
+#[derive(Clone, Debug, Default)]
+pub struct CaptureArea {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub drag_state: DragState,  // Stato di drag incorporato
+}
+
+impl CaptureArea {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        CaptureArea { 
+            x, 
+            y, 
+            width, 
+            height,
+            drag_state: DragState::new(),
+        }
+    }
+
+    /// Verifica se l'area di cattura è valida (non zero).
+    pub fn is_valid(&self) -> bool {
+        self.width > 0 && self.height > 0
+    }
+
+    // /// Converte l'area di cattura in un rettangolo.
+    // pub fn as_rect(&self) -> (usize, usize, usize, usize) {
+    //     (self.x, self.y, self.width, self.height)
+    // }
+    
+    //  /// Elimina l'area di cattura, rendendola non valida.
+    //  pub fn clear(&mut self) {
+    //     self.x = 0;
+    //     self.y = 0;
+    //     self.width = 0;
+    //     self.height = 0;
+    //     self.drag_state.reset();
+    //     println!("Capture area cleared.");
+    // }
+}
+
+
+/// Cosa catturare: un monitor (il principale se `display` è None, altrimenti l'indice in
+/// `Display::all()`) e, opzionalmente, un'area al suo interno.
+#[derive(Clone, Debug, Default)]
+pub struct CaptureSource {
+    pub display: Option<usize>,
+    pub area: Option<CaptureArea>,
+}
+
+impl CaptureSource {
+    pub fn primary(area: Option<CaptureArea>) -> Self {
+        Self { display: None, area }
+    }
+
+    /// True se `other` cattura lo stesso monitor e la stessa area
+    pub fn same_region(&self, other: &CaptureSource) -> bool {
+        let region = |area: &CaptureArea| (area.x, area.y, area.width, area.height);
+        self.display == other.display && self.area.as_ref().map(region) == other.area.as_ref().map(region)
+    }
+
+    /// Dimensioni dei frame catturati: quelle dell'area, o del monitor se l'area non è impostata.
+    /// Verifica anche che il monitor esista e che l'area stia al suo interno.
+    pub fn frame_size(&self) -> Result<(usize, usize)> {
+        let display = self.open_display()?;
+        self.check_area(display.width(), display.height())?;
+        Ok(self.area.as_ref().map_or((display.width(), display.height()), |area| (area.width, area.height)))
+    }
+
+    fn open_display(&self) -> Result<Display> {
+        match self.display {
+            Some(index) => Display::all()
+                .map_err(Error::Display)?
+                .into_iter()
+                .nth(index)
+                .ok_or(Error::DisplayNotFound(index)),
+            None => Display::primary().map_err(Error::Display),
+        }
+    }
+
+    fn check_area(&self, width: usize, height: usize) -> Result<()> {
+        match &self.area {
+            Some(area) if !area.is_valid() || area.x + area.width > width || area.y + area.height > height => {
+                Err(Error::InvalidCaptureArea {
+                    area: [area.x, area.y, area.width, area.height],
+                    display: [width, height],
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Struttura responsabile della cattura dello schermo.
+pub struct ScreenCapturer {
+    capturer: Capturer,
+    width: usize,
+    height: usize,
+    capture_area: Option<CaptureArea>,
+    privacy_masks: Arc<Mutex<Vec<PrivacyMask>>>,
+    // Errori di cattura consecutivi
+    failures: u32,
+}
+
+impl ScreenCapturer {
+    /// Crea un nuovo `ScreenCapturer` per catturare l'intero schermo o un'area specifica.
+    /// Le `privacy_masks` (in coordinate del monitor) vengono oscurate in ogni frame catturato.
+    pub fn new(capture_area: Option<CaptureArea>, privacy_masks: Arc<Mutex<Vec<PrivacyMask>>>) -> Result<Self> {
+        Self::from_source(CaptureSource::primary(capture_area), privacy_masks)
+    }
+
+    /// Come `new`, ma cattura dal monitor indicato in `source`.
+    pub fn from_source(source: CaptureSource, privacy_masks: Arc<Mutex<Vec<PrivacyMask>>>) -> Result<Self> {
+        let display = source.open_display()?;
+        let capturer = Capturer::new(display).map_err(Error::Capture)?;
+        let (width, height) = (capturer.width(), capturer.height());
+
+        // Verifica che l'area di cattura sia valida rispetto alla dimensione dello schermo.
+        source.check_area(width, height)?;
+
+        Ok(Self {
+            capturer,
+            width,
+            height,
+            capture_area: source.area,
+            privacy_masks,
+            failures: 0,
+        })
+    }
+
+    /// Cattura un singolo frame dello schermo o dell'area selezionata.
+    /// Un errore isolato salta il frame (`None`); dopo troppi errori consecutivi la cattura è persa.
+    pub fn capture_frame(&mut self) -> Result<Option<ScreenCapture>> {
+        loop {
+            match self.capturer.frame() {
+                Ok(frame) => {
+                    self.failures = 0;
+                    let frame = frame.to_vec();
+                    let mut buffer = ImageBuffer::new(self.width as u32, self.height as u32);
+    
+                    // Converti i dati raw in un'immagine RGBA
+                    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+                        let idx = (y as usize * self.width + x as usize) * 4;
+                        *pixel = Rgba([frame[idx + 2], frame[idx + 1], frame[idx], 255]);
+                    }
+    
+                    // Se è stata definita un'area di cattura, taglia l'immagine a quell'area.
+                    let final_buffer = match &self.capture_area {
+                        Some(area) => self.crop_frame(&buffer, area),
+                        None => buffer,
+                    };
+    
+                    // Converte l'ImageBuffer in ScreenCapture e applica le maschere di privacy
+                    let mut capture = ScreenCapture::from_image_buffer(&final_buffer);
+                    let origin = self
+                        .capture_area
+                        .as_ref()
+                        .map_or(egui::Pos2::ZERO, |area| egui::pos2(area.x as f32, area.y as f32));
+                    let masks = self.privacy_masks.lock().unwrap();
+                    if !masks.is_empty() {
+                        mask_rgba(&mut capture.data, capture.width as usize, capture.height as usize, origin, &masks);
+                    }
+                    return Ok(Some(capture));
+                }
+                // Se non è pronto, aspetta un po' e riprova.
+                Err(ref e) if e.kind() == WouldBlock => {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+                Err(e) => {
+                    self.failures += 1;
+                    if self.failures >= MAX_CAPTURE_FAILURES {
+                        return Err(Error::Capture(e));
+                    }
+                    warn!("Failed to capture frame: {}", e);
+                    return Ok(None);
+                }
+            }
+        }
+    }    
+
+    #[allow(dead_code)]
+    fn create_image_buffer(&self, frame: &[u8]) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let mut buffer = ImageBuffer::new(self.width as u32, self.height as u32);
+        for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+            let idx = (y as usize * self.width + x as usize) * 4;
+            *pixel = Rgba([frame[idx + 2], frame[idx + 1], frame[idx], 255]);
+        }
+        buffer
+    }
+
+    /// Taglia l'immagine catturata all'area specificata.
+    fn crop_frame(&self, buffer: &ImageBuffer<Rgba<u8>, Vec<u8>>, area: &CaptureArea) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let mut cropped = ImageBuffer::new(area.width as u32, area.height as u32);
+        for y in 0..area.height {
+            for x in 0..area.width {
+                let pixel = buffer.get_pixel((area.x + x) as u32, (area.y + y) as u32);
+                cropped.put_pixel(x as u32, y as u32, *pixel);
+            }
+        }
+        cropped
+    }
+
+    // /// Elimina l'area di cattura, ripristinando la cattura all'intero schermo.
+    // pub fn clear_capture_area(&mut self) {
+    //     self.capture_area = None;
+    //     println!("Capture area cleared. Capturing entire screen.");
+    // }
+
+    
+}
@@ -0,0 +1,310 @@
+// src/core/capture_pipeline.rs
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::capture::{CaptureSource, ScreenCapture, ScreenCapturer};
+use crate::core::error::{ErrorSender, Result, Worker};
+use crate::core::sync_frame_rate;
+use crate::core::worker::{CancellationToken, WorkerHandle, WorkerStatus};
+use crate::utils::redaction::PrivacyMask;
+use log::debug;
+
+// Frame in attesa per ciascun consumatore: se non li legge in tempo, i successivi vengono scartati
+const SUBSCRIBER_QUEUE: usize = 2;
+// Senza consumatori la cattura si ferma e il thread controlla di tanto in tanto se ne sono arrivati
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+// Ogni quanto un consumatore in attesa di un frame controlla se è stato fermato
+const RECV_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Frame catturato una sola volta e condiviso tra tutti i consumatori
+#[derive(Clone)]
+pub struct SharedFrame {
+    pub frame: Arc<ScreenCapture>,
+    /// Tempo impiegato dalla cattura, riportato nelle statistiche della trasmissione
+    pub capture_time: Duration,
+}
+
+struct Subscriber {
+    tx: mpsc::SyncSender<SharedFrame>,
+    // Condiviso con il `FrameSubscriber`: quando resta l'unico riferimento, il consumatore è stato scartato
+    fps: Arc<AtomicU32>,
+    // Frame maturati e non ancora consegnati: ogni frame catturato ne aggiunge `fps / fps della cattura`
+    credit: f32,
+}
+
+impl Subscriber {
+    fn is_dropped(&self) -> bool {
+        Arc::strong_count(&self.fps) == 1
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    subscribers: Mutex<Vec<Subscriber>>,
+    latest: Mutex<Option<Arc<ScreenCapture>>>,
+}
+
+impl Shared {
+    fn subscribe(&self, fps: u32, frame_size: (usize, usize)) -> FrameSubscriber {
+        let (tx, rx) = mpsc::sync_channel(SUBSCRIBER_QUEUE);
+        let fps = Arc::new(AtomicU32::new(fps));
+        self.subscribers.lock().unwrap().push(Subscriber { tx, fps: Arc::clone(&fps), credit: 1.0 });
+        FrameSubscriber { rx, fps, frame_size }
+    }
+
+    /// Rimuove i consumatori scartati e restituisce il framerate del più veloce tra quelli rimasti.
+    /// Chiamato a ogni ciclo, così anche un consumatore che non riceve frame (ad esempio a 0 fps)
+    /// non resta nella lista e non tiene attiva la cattura.
+    fn prune_and_max_fps(&self) -> u32 {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| !subscriber.is_dropped());
+        subscribers
+            .iter()
+            .map(|subscriber| subscriber.fps.load(Ordering::Relaxed))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Consegna a ciascun consumatore la sua quota del frame catturato a `max_fps`
+    fn deliver(&self, shared_frame: &SharedFrame, max_fps: u32) {
+        *self.latest.lock().unwrap() = Some(Arc::clone(&shared_frame.frame));
+
+        self.subscribers.lock().unwrap().retain_mut(|subscriber| {
+            let fps = subscriber.fps.load(Ordering::Relaxed);
+            subscriber.credit = (subscriber.credit + fps as f32 / max_fps as f32).min(1.0);
+            // Il margine assorbe gli arrotondamenti della somma (ad esempio sei volte 1/6)
+            if subscriber.credit < 0.999 {
+                return true;
+            }
+            subscriber.credit -= 1.0;
+            // Un consumatore in ritardo perde il frame, ma resta collegato
+            !matches!(subscriber.tx.try_send(shared_frame.clone()), Err(TrySendError::Disconnected(_)))
+        });
+    }
+}
+
+/// Unico thread di cattura dello schermo, i cui frame vengono distribuiti a preview, trasmissione,
+/// registrazione e snapshot. La cattura procede al framerate del consumatore più veloce e ciascun
+/// consumatore riceve solo i frame necessari al proprio framerate; senza consumatori resta in pausa.
+/// Quando la pipeline si ferma (con `stop`, per un errore o con il drop) i consumatori ricevono `None`.
+pub struct CapturePipeline {
+    source: CaptureSource,
+    frame_size: (usize, usize),
+    shared: Arc<Shared>,
+    worker: WorkerHandle,
+}
+
+impl CapturePipeline {
+    /// Avvia la cattura di `source`, oscurando le `privacy_masks` in ogni frame.
+    /// Fallisce se il monitor non esiste, l'area non è valida o il sistema rifiuta la cattura.
+    pub fn start(
+        source: CaptureSource,
+        privacy_masks: Arc<Mutex<Vec<PrivacyMask>>>,
+        errors: Option<ErrorSender>,
+    ) -> Result<Self> {
+        let frame_size = source.frame_size()?;
+        let shared = Arc::new(Shared::default());
+        let thread_shared = Arc::clone(&shared);
+        let capture_source = source.clone();
+        let worker = WorkerHandle::spawn(
+            Worker::Capture,
+            errors,
+            // Il capturer non può passare da un thread all'altro, quindi nasce nel thread di cattura
+            move || ScreenCapturer::from_source(capture_source, privacy_masks),
+            move |screen_capturer, cancel| run_capture(screen_capturer, cancel, &thread_shared),
+        )?;
+
+        Ok(Self { source, frame_size, shared, worker })
+    }
+
+    pub fn source(&self) -> &CaptureSource {
+        &self.source
+    }
+
+    /// Dimensioni dei frame catturati
+    pub fn frame_size(&self) -> (usize, usize) {
+        self.frame_size
+    }
+
+    /// Nuovo consumatore che riceve i frame a `fps` frame al secondo
+    pub fn subscribe(&self, fps: u32) -> FrameSubscriber {
+        self.shared.subscribe(fps, self.frame_size)
+    }
+
+    /// Consumatori ancora attivi
+    pub fn subscriber_count(&self) -> usize {
+        self.shared.subscribers.lock().unwrap().iter().filter(|subscriber| !subscriber.is_dropped()).count()
+    }
+
+    /// Ultimo frame catturato, ad esempio per uno snapshot
+    pub fn latest_frame(&self) -> Option<Arc<ScreenCapture>> {
+        self.shared.latest.lock().unwrap().clone()
+    }
+
+    pub fn status(&self) -> WorkerStatus {
+        self.worker.status()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Ferma la cattura e ne attende la fine
+    pub fn stop(self) {
+        self.worker.stop();
+    }
+}
+
+/// Consumatore dei frame di una `CapturePipeline`. Il drop lo rimuove dalla pipeline.
+pub struct FrameSubscriber {
+    rx: mpsc::Receiver<SharedFrame>,
+    fps: Arc<AtomicU32>,
+    frame_size: (usize, usize),
+}
+
+impl FrameSubscriber {
+    /// Cambia il framerate con cui vengono consegnati i frame, anche durante la cattura
+    pub fn set_fps(&self, fps: u32) {
+        self.fps.store(fps, Ordering::Relaxed);
+    }
+
+    /// Dimensioni dei frame catturati
+    pub fn frame_size(&self) -> (usize, usize) {
+        self.frame_size
+    }
+
+    /// Attende il prossimo frame. `None` se `cancel` viene cancellato o se la pipeline si è fermata.
+    pub fn next_frame(&self, cancel: &CancellationToken) -> Option<SharedFrame> {
+        while !cancel.is_cancelled() {
+            match self.rx.recv_timeout(RECV_POLL_INTERVAL) {
+                Ok(frame) => return Some(frame),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+        None
+    }
+
+    /// Ultimo frame arrivato, scartando quelli precedenti, senza attendere; per chi legge i frame
+    /// dal thread dell'interfaccia. `Err` se la pipeline si è fermata.
+    pub fn try_latest(&self) -> std::result::Result<Option<SharedFrame>, TryRecvError> {
+        let mut latest = None;
+        loop {
+            match self.rx.try_recv() {
+                Ok(frame) => latest = Some(frame),
+                Err(TryRecvError::Empty) => return Ok(latest),
+                // I frame già in coda vengono consegnati prima di segnalare la fine
+                Err(TryRecvError::Disconnected) if latest.is_some() => return Ok(latest),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Ciclo del thread di cattura: cattura al framerate del consumatore più veloce e consegna a ciascuno
+/// una quota dei frame proporzionale al proprio framerate. I consumatori scartati vengono rimossi a ogni ciclo.
+fn run_capture(mut screen_capturer: ScreenCapturer, cancel: CancellationToken, shared: &Shared) -> Result<()> {
+    debug!("Capture thread started");
+
+    while !cancel.is_cancelled() {
+        let max_fps = shared.prune_and_max_fps();
+        if max_fps == 0 {
+            cancel.wait_timeout(IDLE_POLL_INTERVAL);
+            continue;
+        }
+
+        let frame_start = Instant::now();
+        if let Some(frame) = screen_capturer.capture_frame()? {
+            let shared_frame = SharedFrame { frame: Arc::new(frame), capture_time: frame_start.elapsed() };
+            shared.deliver(&shared_frame, max_fps);
+        }
+
+        sync_frame_rate(frame_start, Duration::from_secs_f32(1.0 / max_fps as f32), &cancel);
+    }
+
+    debug!("Capture thread exiting");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> SharedFrame {
+        let frame = ScreenCapture { width: 1, height: 1, data: vec![0; 4], captured_at: 0 };
+        SharedFrame { frame: Arc::new(frame), capture_time: Duration::ZERO }
+    }
+
+    fn received(subscriber: &FrameSubscriber) -> usize {
+        std::iter::from_fn(|| subscriber.rx.try_recv().ok()).count()
+    }
+
+    #[test]
+    fn dropped_subscriber_is_pruned_without_a_send() {
+        let shared = Shared::default();
+        let live = shared.subscribe(10, (1, 1));
+        let paused = shared.subscribe(0, (1, 1));
+        let fast = shared.subscribe(60, (1, 1));
+        assert_eq!(shared.prune_and_max_fps(), 60);
+
+        // Nessuno dei due riceverebbe più frame, quindi un invio fallito non li toglierebbe mai
+        drop(paused);
+        drop(fast);
+        assert_eq!(shared.prune_and_max_fps(), 10);
+        assert_eq!(shared.subscribers.lock().unwrap().len(), 1);
+
+        drop(live);
+        assert_eq!(shared.prune_and_max_fps(), 0);
+        assert!(shared.subscribers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn frames_are_split_by_framerate() {
+        let shared = Shared::default();
+        let full = shared.subscribe(30, (1, 1));
+        let third = shared.subscribe(10, (1, 1));
+        let paused = shared.subscribe(0, (1, 1));
+
+        let (mut full_count, mut third_count) = (0, 0);
+        for _ in 0..30 {
+            shared.deliver(&frame(), 30);
+            full_count += received(&full);
+            third_count += received(&third);
+        }
+        assert_eq!(full_count, 30);
+        assert_eq!(third_count, 10);
+        // Ogni nuovo consumatore riceve subito il primo frame, poi solo la sua quota
+        assert_eq!(received(&paused), 1);
+        assert!(shared.latest.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn slow_subscriber_loses_frames_but_stays_subscribed() {
+        let shared = Shared::default();
+        let slow = shared.subscribe(30, (1, 1));
+        for _ in 0..5 {
+            shared.deliver(&frame(), 30);
+        }
+        assert_eq!(received(&slow), SUBSCRIBER_QUEUE);
+        assert_eq!(shared.subscribers.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn try_latest_keeps_only_the_newest_frame() {
+        let shared = Shared::default();
+        let subscriber = shared.subscribe(30, (1, 1));
+        assert!(subscriber.try_latest().unwrap().is_none());
+
+        shared.deliver(&frame(), 30);
+        shared.deliver(&frame(), 30);
+        assert!(subscriber.try_latest().unwrap().is_some());
+        assert!(subscriber.try_latest().unwrap().is_none());
+
+        // Senza più mittenti la pipeline è considerata ferma
+        shared.subscribers.lock().unwrap().clear();
+        assert!(matches!(subscriber.try_latest(), Err(TryRecvError::Disconnected)));
+    }
+}
@@ -0,0 +1,296 @@
+// src/core/caster.rs
+
+use std::collections::HashSet;
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::adaptive::{AdaptiveController, NetworkConditions};
+use crate::core::broadcast_session::{BroadcastSession, FeedbackMap};
+use crate::core::capture::ScreenCapture;
+use crate::core::capture_pipeline::FrameSubscriber;
+use crate::core::error::{ErrorSender, Result, Worker};
+use crate::core::network::{serialize_frame, serialize_message, write_payload, StreamMessage};
+use crate::core::quality::{BroadcastQuality, OutputScale};
+use crate::core::stats::{millis, CasterStats};
+use crate::core::worker::{CancellationToken, WorkerHandle, WorkerStatus};
+use crate::utils::annotations::{Annotation, AnnotationEvent, RedactAnnotation};
+use log::{debug, error, warn};
+
+// Le statistiche di un receiver più vecchie di così non vengono considerate
+const FEEDBACK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Parametri di una trasmissione
+pub struct CasterConfig {
+    pub port: u16,
+    /// Se presente, i receiver devono inviarla subito dopo la connessione
+    pub password: Option<String>,
+    /// Frame della pipeline di cattura, già privi delle regioni coperte dalle maschere di privacy.
+    /// Il framerate con cui arrivano segue quello scelto dall'adattamento alla rete.
+    pub frames: FrameSubscriber,
+    /// Stato iniziale delle annotazioni, inviato per intero a ogni receiver appena connesso
+    pub annotations: Vec<Annotation>,
+    /// Regioni oscurate in ogni frame prima della serializzazione
+    pub redactions: Arc<Mutex<Vec<RedactAnnotation>>>,
+    /// Qualità massima e limiti dell'adattamento alla rete, letti a ogni frame:
+    /// le modifiche valgono anche a trasmissione avviata
+    pub quality: Arc<Mutex<BroadcastQuality>>,
+    /// Se presente, riceve l'errore che interrompe la trasmissione
+    pub errors: Option<ErrorSender>,
+}
+
+impl CasterConfig {
+    /// Trasmissione senza password, annotazioni o redazioni, con la qualità predefinita
+    pub fn new(port: u16, frames: FrameSubscriber) -> Self {
+        Self {
+            port,
+            password: None,
+            frames,
+            annotations: Vec::new(),
+            redactions: Arc::new(Mutex::new(Vec::new())),
+            quality: Arc::new(Mutex::new(BroadcastQuality::default())),
+            errors: None,
+        }
+    }
+}
+
+/// Trasmissione in corso: un thread cattura lo schermo e invia ogni frame ai receiver della sessione.
+/// Alla fine della trasmissione (`stop`, `join` o drop) la sessione viene chiusa e la porta liberata.
+pub struct Caster {
+    // Dichiarato prima della sessione: nel drop il thread viene atteso prima che la sessione si chiuda
+    worker: WorkerHandle,
+    session: BroadcastSession,
+    annotation_tx: mpsc::Sender<AnnotationEvent>,
+    stats: Arc<Mutex<CasterStats>>,
+}
+
+impl Caster {
+    /// Si mette in ascolto sulla porta e avvia il thread di trasmissione.
+    /// Fallisce se la porta è occupata. Se la pipeline di cattura si ferma, si ferma anche la trasmissione.
+    pub fn start(mut config: CasterConfig) -> Result<Self> {
+        let session = BroadcastSession::bind(config.port, config.password.clone())?;
+        let receivers = session.receivers();
+        let feedback = session.feedback();
+
+        let (annotation_tx, annotation_rx) = mpsc::channel();
+        let stats = Arc::new(Mutex::new(CasterStats::default()));
+        let thread_stats = Arc::clone(&stats);
+        let worker = WorkerHandle::spawn(
+            Worker::Broadcast,
+            config.errors.take(),
+            || Ok(()),
+            move |(), cancel| run_broadcast(cancel, annotation_rx, receivers, feedback, thread_stats, config),
+        )?;
+
+        Ok(Self { worker, session, annotation_tx, stats })
+    }
+
+    /// Indirizzo su cui i receiver si connettono
+    pub fn local_addr(&self) -> SocketAddr {
+        self.session.local_addr()
+    }
+
+    pub fn receiver_count(&self) -> usize {
+        self.session.receiver_count()
+    }
+
+    /// Metriche della trasmissione, aggiornate a ogni frame, compresa la qualità scelta in base alla rete
+    pub fn stats(&self) -> Arc<Mutex<CasterStats>> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Inoltra ai receiver una modifica alle annotazioni insieme al prossimo frame
+    pub fn send_annotation(&self, event: AnnotationEvent) -> bool {
+        self.annotation_tx.send(event).is_ok()
+    }
+
+    /// Token con cui fermare la trasmissione da un altro thread, ad esempio da un gestore di Ctrl+C
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.worker.cancellation_token()
+    }
+
+    pub fn status(&self) -> WorkerStatus {
+        self.worker.status()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Ferma la trasmissione e chiude la sessione
+    pub fn stop(self) {
+        self.worker.cancel();
+        self.join();
+    }
+
+    /// Attende che la trasmissione venga fermata (ad esempio tramite il `CancellationToken`),
+    /// poi chiude la sessione
+    pub fn join(self) {
+        let Self { worker, mut session, .. } = self;
+        worker.join();
+        session.shutdown();
+    }
+}
+
+/// Ciclo del thread di trasmissione.
+/// Insieme ai frame vengono inoltrati gli eventi di annotazione ricevuti su `annotation_rx`;
+/// le regioni in `redactions` vengono oscurate in ogni frame prima della serializzazione.
+/// Framerate, risoluzione e qualità JPEG si adattano alle statistiche inviate dai receiver (`feedback`).
+fn run_broadcast(
+    cancel: CancellationToken,
+    annotation_rx: mpsc::Receiver<AnnotationEvent>,
+    receivers: Arc<Mutex<Vec<TcpStream>>>,
+    feedback: Arc<Mutex<FeedbackMap>>,
+    stats: Arc<Mutex<CasterStats>>,
+    config: CasterConfig,
+) -> Result<()> {
+    debug!("Broadcast thread started");
+
+    let CasterConfig { frames, mut annotations, redactions, quality, .. } = config;
+    // Receiver che hanno già ricevuto lo stato completo delle annotazioni
+    let mut synced_receivers: HashSet<SocketAddr> = HashSet::new();
+    let mut controller = AdaptiveController::new();
+    // Scrittura più lenta del frame precedente, segno di buffer TCP pieni
+    let mut max_send_time = Duration::ZERO;
+
+    loop {
+        // La pipeline consegna i frame al framerate scelto dall'adattamento nel ciclo precedente
+        let Some(shared_frame) = frames.next_frame(&cancel) else {
+            if cancel.is_cancelled() {
+                debug!("Received stop signal, stopping broadcast...");
+            } else {
+                warn!("Screen capture stopped, stopping broadcast...");
+            }
+            break;
+        };
+        let frame_start = Instant::now();
+        let settings = *quality.lock().unwrap();
+        let recent_feedback: Vec<_> = feedback
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|(_, received_at)| received_at.elapsed() < FEEDBACK_TIMEOUT)
+            .map(|(feedback, _)| *feedback)
+            .collect();
+        let sent_fps = stats.lock().unwrap().framerate.fps.average().unwrap_or(0.0);
+        let applied = controller.update(&settings, &NetworkConditions {
+            feedback: &recent_feedback,
+            sent_fps,
+            max_send_time,
+        });
+        frames.set_fps(applied.fps);
+
+        // Raccogli gli eventi di annotazione arrivati dall'ultimo frame
+        let mut event_payloads = Vec::new();
+        for event in annotation_rx.try_iter() {
+            event.clone().apply(&mut annotations);
+            match serialize_message(StreamMessage::Annotation(event)) {
+                Ok(payload) => event_payloads.push(payload),
+                Err(e) => error!("Failed to serialize annotation event: {:?}", e),
+            }
+        }
+
+        // Oscura, ridimensiona e serializza il frame: è condiviso con gli altri consumatori, quindi si lavora su una copia
+        let capture_time = shared_frame.capture_time;
+        let mut frame = ScreenCapture::clone(&shared_frame.frame);
+        frame.redact(&redactions.lock().unwrap());
+        // La riduzione scelta dal controller si somma alla risoluzione impostata
+        let (width, height) = settings.quality.scale.output_size(frame.width as usize, frame.height as usize);
+        let (width, height) = OutputScale::Percent(applied.scale_percent).output_size(width, height);
+        let serialized_frame = match frame.resized(width, height).and_then(|frame| serialize_frame(frame, applied.encoding)) {
+            Ok(payload) => {
+                let mut stats = stats.lock().unwrap();
+                stats.capture_ms.push(millis(capture_time));
+                stats.serialize_ms.push(millis(frame_start.elapsed()));
+                stats.frame_bytes.push(payload.len() as f32);
+                Some(payload)
+            }
+            Err(e) => {
+                error!("Failed to serialize frame: {:?}", e);
+                None
+            }
+        };
+        // Tempi di invio del frame a ciascun receiver
+        let mut send_times = Vec::new();
+
+        // Invia al receiver le annotazioni e il frame; false se la connessione si è interrotta
+        let mut send_to_receiver = |mut stream: &TcpStream| {
+            let peer = stream.peer_addr().ok();
+
+            match peer {
+                Some(addr) if !synced_receivers.contains(&addr) => {
+                    // Un receiver appena connesso riceve tutte le annotazioni correnti
+                    let sync = StreamMessage::Annotation(AnnotationEvent::Sync(annotations.clone()));
+                    let sent = serialize_message(sync)
+                        .is_ok_and(|payload| write_payload(&mut stream, &payload).is_ok());
+                    if !sent {
+                        warn!("Failed to send annotations to receiver.");
+                        return false;
+                    }
+                    synced_receivers.insert(addr);
+                }
+                _ => {
+                    for payload in &event_payloads {
+                        if write_payload(&mut stream, payload).is_err() {
+                            warn!("Failed to send annotation event.");
+                            return false;
+                        }
+                    }
+                }
+            }
+
+            if let Some(payload) = &serialized_frame {
+                let send_start = Instant::now();
+                if write_payload(&mut stream, payload).is_err() {
+                    warn!("Failed to send frame data.");
+                    return false;
+                }
+                if let Some(addr) = peer {
+                    send_times.push((addr, send_start.elapsed()));
+                }
+            }
+
+            //println!("Frame sent to receiver."); per debug
+            true // Mantieni la connessione attiva
+        };
+
+        let mut receivers = receivers.lock().unwrap();
+        let mut connected_peers = Vec::with_capacity(receivers.len());
+        receivers.retain(|stream| {
+            let connected = send_to_receiver(stream);
+            if connected {
+                connected_peers.extend(stream.peer_addr().ok());
+            } else {
+                // Chiude anche la copia del socket usata per leggere le statistiche del receiver
+                let _ = stream.shutdown(Shutdown::Both);
+            }
+            connected
+        });
+        drop(receivers);
+        // Un receiver che si riconnette dallo stesso indirizzo e porta deve ricevere di nuovo lo stato completo
+        synced_receivers.retain(|addr| connected_peers.contains(addr));
+        max_send_time = send_times.iter().map(|(_, send_time)| *send_time).max().unwrap_or_default();
+
+        // Il frame è in ritardo se cattura e invio hanno richiesto più del tempo a disposizione
+        let on_time = capture_time + frame_start.elapsed() <= applied.frame_duration();
+
+        let mut stats = stats.lock().unwrap();
+        stats.target_fps = applied.fps;
+        stats.applied_quality = Some(applied);
+        if serialized_frame.is_some() {
+            // I receiver disconnessi spariscono dalle statistiche
+            stats.send_ms.retain(|addr, _| send_times.iter().any(|(peer, _)| peer == addr));
+            for (addr, send_time) in send_times {
+                stats.send_ms.entry(addr).or_default().push(millis(send_time));
+            }
+            stats.frames += 1;
+            stats.framerate.tick();
+        }
+        if !on_time {
+            stats.overloaded_frames += 1;
+        }
+    }
+
+    debug!("Broadcast thread exiting");
+    Ok(())
+}
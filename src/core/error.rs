@@ -0,0 +1,151 @@
+// src/core/error.rs
+
+use std::any::Any;
+use std::fmt;
+use std::io;
+use std::sync::mpsc;
+
+/// Errori di cattura, rete e registrazione
+#[derive(Debug)]
+pub enum Error {
+    /// Non è possibile elencare i monitor o trovare quello principale
+    Display(io::Error),
+    /// Nessun monitor con questo indice in `Display::all()`
+    DisplayNotFound(usize),
+    /// L'area di cattura esce dal monitor
+    InvalidCaptureArea { area: [usize; 4], display: [usize; 2] },
+    /// Il sistema rifiuta la cattura dello schermo, ad esempio per mancanza di permessi
+    Capture(io::Error),
+    Bind { port: u16, source: io::Error },
+    Connect { address: String, source: io::Error },
+    /// La connessione si è interrotta durante la trasmissione o la ricezione
+    Network(io::Error),
+    /// Un messaggio non rispetta il protocollo
+    Protocol(bincode::Error),
+    /// I pixel del frame (ricevuti o decodificati) non corrispondono alle dimensioni dichiarate
+    InvalidFrame { width: u32, height: u32, len: usize },
+    Image(image::ImageError),
+    /// ffmpeg non può essere avviato (di solito perché non è installato) o ha smesso di leggere i frame
+    Ffmpeg(io::Error),
+    Io(io::Error),
+    /// Un thread di lavoro è terminato con un panic
+    Panic(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Display(e) => write!(f, "cannot access the displays: {}", e),
+            Error::DisplayNotFound(index) => write!(f, "monitor {} not found", index),
+            Error::InvalidCaptureArea { area: [x, y, width, height], display: [display_width, display_height] } => write!(
+                f,
+                "capture area {}x{} at {},{} does not fit the {}x{} display",
+                width, height, x, y, display_width, display_height
+            ),
+            Error::Capture(e) => write!(f, "cannot capture the screen: {}", e),
+            Error::Bind { port, source } => write!(f, "cannot listen on port {}: {}", port, source),
+            Error::Connect { address, source } => write!(f, "cannot connect to {}: {}", address, source),
+            Error::Network(e) => write!(f, "connection lost: {}", e),
+            Error::Protocol(e) => write!(f, "invalid stream data: {}", e),
+            Error::InvalidFrame { width, height, len } => write!(
+                f,
+                "invalid frame: {} bytes of pixel data for a {}x{} frame",
+                len, width, height
+            ),
+            Error::Image(e) => write!(f, "image error: {}", e),
+            Error::Ffmpeg(e) => write!(f, "ffmpeg error (is it installed?): {}", e),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Panic(message) => write!(f, "unexpected failure: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Display(e) | Error::Capture(e) | Error::Network(e) | Error::Ffmpeg(e) | Error::Io(e) => Some(e),
+            Error::Bind { source, .. } | Error::Connect { source, .. } => Some(source),
+            Error::Protocol(e) => Some(e),
+            Error::Image(e) => Some(e),
+            Error::DisplayNotFound(_) | Error::InvalidCaptureArea { .. } | Error::InvalidFrame { .. } | Error::Panic(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Error::Protocol(e)
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Error::Image(e)
+    }
+}
+
+impl Error {
+    /// Il messaggio di un panic catturato con `catch_unwind` o restituito da `JoinHandle::join`
+    pub fn from_panic(payload: Box<dyn Any + Send>) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "thread panicked".to_string());
+        Error::Panic(message)
+    }
+}
+
+/// Thread di lavoro che può segnalare un errore
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Worker {
+    Broadcast,
+    Recording,
+    Receiving,
+    /// Thread che cattura lo schermo per preview, trasmissione e registrazione
+    Capture,
+    /// Thread che accetta le connessioni dei receiver durante una trasmissione
+    Listener,
+}
+
+impl fmt::Display for Worker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Worker::Broadcast => write!(f, "Broadcast"),
+            Worker::Recording => write!(f, "Recording"),
+            Worker::Receiving => write!(f, "Receiving"),
+            Worker::Capture => write!(f, "Capture"),
+            Worker::Listener => write!(f, "Listener"),
+        }
+    }
+}
+
+/// Errore con cui un thread di lavoro è terminato
+#[derive(Debug)]
+pub struct WorkerError {
+    pub worker: Worker,
+    pub error: Error,
+}
+
+impl fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} stopped: {}", self.worker, self.error)
+    }
+}
+
+impl std::error::Error for WorkerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Canale su cui i thread di lavoro segnalano all'applicazione di essere terminati per un errore
+pub type ErrorSender = mpsc::Sender<WorkerError>;
@@ -0,0 +1,34 @@
+// src/core/mod.rs
+
+//! Cattura, protocollo di rete e registrazione, senza dipendenze dall'interfaccia grafica.
+//! `Caster`, `Receiver` e `Recorder` avviano ciascuno il proprio thread (vedi `worker`) e
+//! restituiscono un handle con cui fermarlo o attenderne la fine.
+
+pub mod adaptive;
+pub mod broadcast_session;
+pub mod capture;
+pub mod capture_pipeline;
+pub mod caster;
+pub mod error;
+pub mod network;
+pub mod quality;
+pub mod receiver;
+pub mod recorder;
+pub mod stats;
+pub mod worker;
+
+use std::time::{Duration, Instant};
+
+use crate::core::worker::CancellationToken;
+
+/// Sincronizza il framerate per evitare sovraccarichi; l'attesa si interrompe se `cancel` viene cancellato.
+/// Restituisce false se il frame iniziato a `start_time` ha richiesto più di `frame_duration`.
+pub(crate) fn sync_frame_rate(start_time: Instant, frame_duration: Duration, cancel: &CancellationToken) -> bool {
+    let elapsed = start_time.elapsed();
+    if elapsed < frame_duration {
+        cancel.wait_timeout(frame_duration - elapsed);
+        true
+    } else {
+        false
+    }
+}
@@ -0,0 +1,260 @@
+// src/core/network.rs
+
+use crate::core::capture::ScreenCapture;
+use crate::core::error::{Error, Result};
+use crate::utils::annotations::AnnotationEvent;
+use std::fmt;
+use std::io::{self, Read, Write};
+use bincode;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ExtendedColorType, ImageFormat};
+use serde::{Serialize, Deserialize};
+
+/// Porta TCP su cui il caster accetta i receiver
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Dimensione massima di un messaggio del caster: un frame RGBA non compresso in 8K, più l'intestazione
+pub const MAX_PAYLOAD: usize = 7680 * 4320 * 4 + 1024;
+/// Dimensione massima della password e delle statistiche inviate dal receiver
+pub const MAX_CONTROL_PAYLOAD: usize = 4 * 1024;
+
+/// Messaggio trasmesso dal caster ai receiver, con il frame già decodificato.
+/// Ogni messaggio viaggia preceduto dalla sua lunghezza (u32 big endian).
+#[derive(Debug)]
+pub enum StreamMessage {
+    Frame(ScreenCapture),
+    Annotation(AnnotationEvent),
+}
+
+/// Formato dei frame sul socket
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameEncoding {
+    /// RGBA non compresso
+    #[default]
+    Raw,
+    /// JPEG con la qualità indicata (1-100): molti meno byte, al costo di un po' di CPU e di dettaglio
+    Jpeg(u8),
+}
+
+impl fmt::Display for FrameEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameEncoding::Raw => write!(f, "raw"),
+            FrameEncoding::Jpeg(quality) => write!(f, "JPEG {}", quality),
+        }
+    }
+}
+
+/// Statistiche che il receiver invia periodicamente al caster, sulla stessa connessione dei frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReceiverFeedback {
+    /// Frame ricevuti al secondo
+    pub received_fps: f32,
+    pub bytes_per_second: f32,
+    /// Frame ricevuti ma non ancora mostrati o registrati dall'applicazione
+    pub queue_depth: u32,
+}
+
+/// Messaggio come viaggia sul socket: i frame possono essere compressi
+#[derive(Serialize, Deserialize)]
+enum WireMessage {
+    Frame(WireFrame),
+    Annotation(AnnotationEvent),
+}
+
+#[derive(Serialize, Deserialize)]
+enum WireFrame {
+    Raw(ScreenCapture),
+    Jpeg { width: u32, height: u32, captured_at: u64, data: Vec<u8> },
+}
+
+/// Serializza un messaggio una sola volta, così da poterlo inviare a più receiver.
+/// I frame vengono inviati non compressi; per comprimerli si usa `serialize_frame`.
+pub fn serialize_message(message: StreamMessage) -> Result<Vec<u8>> {
+    match message {
+        StreamMessage::Frame(frame) => serialize_frame(frame, FrameEncoding::Raw),
+        StreamMessage::Annotation(event) => to_bytes(&WireMessage::Annotation(event)),
+    }
+}
+
+/// Serializza un frame nel formato indicato
+pub fn serialize_frame(frame: ScreenCapture, encoding: FrameEncoding) -> Result<Vec<u8>> {
+    let frame = match encoding {
+        FrameEncoding::Raw => WireFrame::Raw(frame),
+        FrameEncoding::Jpeg(quality) => {
+            // JPEG non ha canale alfa: i frame catturati sono comunque opachi
+            let rgb: Vec<u8> = frame.data.chunks_exact(4).flat_map(|pixel| [pixel[0], pixel[1], pixel[2]]).collect();
+            let mut data = Vec::new();
+            JpegEncoder::new_with_quality(&mut data, quality.clamp(1, 100))
+                .encode(&rgb, frame.width, frame.height, ExtendedColorType::Rgb8)?;
+            WireFrame::Jpeg { width: frame.width, height: frame.height, captured_at: frame.captured_at, data }
+        }
+    };
+    to_bytes(&WireMessage::Frame(frame))
+}
+
+/// Legge un messaggio serializzato con `serialize_message` o `serialize_frame`, decomprimendo il frame
+pub fn deserialize_message(payload: &[u8]) -> Result<StreamMessage> {
+    let message = bincode::deserialize::<WireMessage>(payload)?;
+    Ok(match message {
+        WireMessage::Frame(WireFrame::Raw(frame)) => {
+            frame.validate()?;
+            StreamMessage::Frame(frame)
+        }
+        WireMessage::Frame(WireFrame::Jpeg { width, height, captured_at, data }) => {
+            let image = image::load_from_memory_with_format(&data, ImageFormat::Jpeg)?.to_rgba8();
+            if image.dimensions() != (width, height) {
+                return Err(Error::InvalidFrame { width, height, len: image.len() });
+            }
+            StreamMessage::Frame(ScreenCapture { captured_at, ..ScreenCapture::from_image_buffer(&image) })
+        }
+        WireMessage::Annotation(event) => StreamMessage::Annotation(event),
+    })
+}
+
+pub fn serialize_feedback(feedback: &ReceiverFeedback) -> Result<Vec<u8>> {
+    to_bytes(feedback)
+}
+
+pub fn deserialize_feedback(payload: &[u8]) -> Result<ReceiverFeedback> {
+    Ok(bincode::deserialize(payload)?)
+}
+
+fn to_bytes(value: &impl Serialize) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(value)?)
+}
+
+/// Scrive un messaggio già serializzato, preceduto dalla sua lunghezza.
+pub fn write_payload(stream: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    let length = payload.len() as u32;
+    stream.write_all(&length.to_be_bytes())?;
+    stream.write_all(payload)
+}
+
+/// Legge un messaggio scritto con `write_payload`.
+/// Una lunghezza oltre `max_len` viene rifiutata con `InvalidData` prima di allocare il buffer.
+pub fn read_payload(stream: &mut impl Read, max_len: usize) -> io::Result<Vec<u8>> {
+    let mut length_buffer = [0u8; 4];
+    stream.read_exact(&mut length_buffer)?;
+    let length = u32::from_be_bytes(length_buffer) as usize;
+    if length > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes exceeds the {} bytes limit", length, max_len),
+        ));
+    }
+    let mut buffer = vec![0; length];
+    stream.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::annotations::Annotation;
+    use egui::{Color32, Pos2};
+    use std::io::Cursor;
+
+    fn gradient_frame(width: u32, height: u32) -> ScreenCapture {
+        let data = (0..width * height)
+            .flat_map(|i| [(i % 256) as u8, (i / 256 % 256) as u8, 128, 255])
+            .collect();
+        ScreenCapture { width, height, data, captured_at: 1234 }
+    }
+
+    #[test]
+    fn messages_survive_the_wire() {
+        let frame = gradient_frame(16, 8);
+        let annotation = Annotation::segment(Pos2::new(3.0, 4.0), Color32::RED, 2.0);
+
+        let mut wire = Vec::new();
+        write_payload(&mut wire, &serialize_message(StreamMessage::Frame(frame.clone())).unwrap()).unwrap();
+        write_payload(&mut wire, &serialize_frame(frame.clone(), FrameEncoding::Jpeg(90)).unwrap()).unwrap();
+        let event = AnnotationEvent::Added { index: 0, annotation };
+        write_payload(&mut wire, &serialize_message(StreamMessage::Annotation(event)).unwrap()).unwrap();
+
+        let mut reader = Cursor::new(wire);
+        let mut next = || deserialize_message(&read_payload(&mut reader, MAX_PAYLOAD).unwrap()).unwrap();
+        match next() {
+            StreamMessage::Frame(raw) => {
+                assert_eq!((raw.width, raw.height, raw.captured_at), (16, 8, 1234));
+                assert_eq!(raw.data, frame.data);
+            }
+            other => panic!("expected a raw frame, got {:?}", other),
+        }
+        match next() {
+            StreamMessage::Frame(jpeg) => {
+                assert_eq!((jpeg.width, jpeg.height, jpeg.captured_at), (16, 8, 1234));
+                assert_eq!(jpeg.data.len(), frame.data.len());
+            }
+            other => panic!("expected a JPEG frame, got {:?}", other),
+        }
+        match next() {
+            StreamMessage::Annotation(AnnotationEvent::Added { index: 0, annotation: Annotation::Segment(segment) }) => {
+                assert_eq!(segment.starting_pos, Pos2::new(3.0, 4.0));
+            }
+            other => panic!("expected an added segment, got {:?}", other),
+        }
+        // Dopo l'ultimo messaggio la connessione è chiusa
+        let end = read_payload(&mut reader, MAX_PAYLOAD).unwrap_err();
+        assert_eq!(end.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn feedback_survives_the_wire() {
+        let feedback = ReceiverFeedback { received_fps: 12.5, bytes_per_second: 4096.0, queue_depth: 3 };
+        let mut wire = Vec::new();
+        write_payload(&mut wire, &serialize_feedback(&feedback).unwrap()).unwrap();
+
+        let payload = read_payload(&mut Cursor::new(wire), MAX_CONTROL_PAYLOAD).unwrap();
+        assert_eq!(deserialize_feedback(&payload).unwrap(), feedback);
+    }
+
+    #[test]
+    fn oversized_payload_is_rejected() {
+        let mut wire = Vec::new();
+        write_payload(&mut wire, &[7; 64]).unwrap();
+
+        let error = read_payload(&mut Cursor::new(&wire), 63).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(read_payload(&mut Cursor::new(&wire), 64).unwrap(), vec![7; 64]);
+    }
+
+    #[test]
+    fn jpeg_with_wrong_size_is_an_invalid_frame() {
+        let Ok(WireMessage::Frame(WireFrame::Jpeg { data, captured_at, .. })) =
+            bincode::deserialize(&serialize_frame(gradient_frame(16, 8), FrameEncoding::Jpeg(80)).unwrap())
+        else {
+            panic!("expected a JPEG frame");
+        };
+        let lying = to_bytes(&WireMessage::Frame(WireFrame::Jpeg { width: 32, height: 8, captured_at, data })).unwrap();
+
+        match deserialize_message(&lying) {
+            Err(Error::InvalidFrame { width, height, len }) => {
+                assert_eq!((width, height), (32, 8));
+                assert_eq!(len, 16 * 8 * 4);
+            }
+            other => panic!("expected an invalid frame error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn truncated_raw_frame_is_an_invalid_frame() {
+        let mut frame = gradient_frame(16, 8);
+        frame.data.truncate(16 * 8 * 4 - 4);
+        let truncated = serialize_message(StreamMessage::Frame(frame)).unwrap();
+
+        match deserialize_message(&truncated) {
+            Err(Error::InvalidFrame { width, height, len }) => {
+                assert_eq!((width, height), (16, 8));
+                assert_eq!(len, 16 * 8 * 4 - 4);
+            }
+            other => panic!("expected an invalid frame error, got {:?}", other),
+        }
+
+        // Anche dimensioni il cui prodotto supera la memoria indirizzabile vengono rifiutate
+        let huge = ScreenCapture { width: u32::MAX, height: u32::MAX, data: vec![0; 4], captured_at: 0 };
+        let huge = serialize_message(StreamMessage::Frame(huge)).unwrap();
+        assert!(matches!(deserialize_message(&huge), Err(Error::InvalidFrame { .. })));
+    }
+}
@@ -0,0 +1,182 @@
+// src/core/quality.rs
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::adaptive::AdaptiveBounds;
+use crate::core::network::FrameEncoding;
+
+pub const DEFAULT_FRAMERATE: u32 = 20;
+pub const MIN_FRAMERATE: u32 = 1;
+pub const MAX_FRAMERATE: u32 = 60;
+
+/// Risoluzione dei frame in uscita rispetto a quella catturata
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputScale {
+    /// Percentuale delle dimensioni catturate (100 = risoluzione nativa)
+    Percent(u32),
+    /// Larghezza massima in pixel; i frame più stretti restano invariati
+    MaxWidth(u32),
+}
+
+impl Default for OutputScale {
+    fn default() -> Self {
+        OutputScale::Percent(100)
+    }
+}
+
+impl OutputScale {
+    /// Percentuali proposte nell'interfaccia
+    pub const PRESETS: [OutputScale; 3] = [OutputScale::Percent(100), OutputScale::Percent(75), OutputScale::Percent(50)];
+
+    /// Dimensioni di un frame `width`x`height` dopo il ridimensionamento, mantenendo le proporzioni.
+    /// Le dimensioni ridotte sono arrotondate a numeri pari, richiesti dal formato yuv420p delle registrazioni.
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let factor = match *self {
+            OutputScale::Percent(percent) => percent.clamp(1, 100) as f64 / 100.0,
+            OutputScale::MaxWidth(max_width) if width > max_width as usize && max_width > 0 => {
+                max_width as f64 / width as f64
+            }
+            OutputScale::MaxWidth(_) => 1.0,
+        };
+        if factor >= 1.0 {
+            return (width, height);
+        }
+        let scale = |size: usize| (((size as f64 * factor) as usize) & !1).max(2);
+        (scale(width), scale(height))
+    }
+}
+
+impl fmt::Display for OutputScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputScale::Percent(percent) => write!(f, "{}%", percent),
+            OutputScale::MaxWidth(max_width) => write!(f, "max {} px wide", max_width),
+        }
+    }
+}
+
+/// Accetta una percentuale ("75%") oppure una larghezza massima in pixel ("1280")
+impl FromStr for OutputScale {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        match value.strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<u32>() {
+                Ok(percent @ 1..=100) => Ok(OutputScale::Percent(percent)),
+                _ => Err(format!("invalid percentage \"{}\": expected 1% to 100%", value)),
+            },
+            None => match value.parse::<u32>() {
+                Ok(max_width) if max_width > 0 => Ok(OutputScale::MaxWidth(max_width)),
+                _ => Err(format!("invalid scale \"{}\": expected a percentage (75%) or a maximum width (1280)", value)),
+            },
+        }
+    }
+}
+
+/// Framerate e risoluzione di una trasmissione o di una registrazione
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamQuality {
+    /// Frame al secondo, tra `MIN_FRAMERATE` e `MAX_FRAMERATE`
+    pub fps: u32,
+    pub scale: OutputScale,
+}
+
+impl Default for StreamQuality {
+    fn default() -> Self {
+        Self { fps: DEFAULT_FRAMERATE, scale: OutputScale::default() }
+    }
+}
+
+impl StreamQuality {
+    /// Framerate limitato all'intervallo supportato, anche se il file delle impostazioni contiene altro
+    pub fn fps(&self) -> u32 {
+        self.fps.clamp(MIN_FRAMERATE, MAX_FRAMERATE)
+    }
+
+    /// Tempo a disposizione per ogni frame
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps() as f64)
+    }
+}
+
+/// Qualità di una trasmissione: oltre a framerate e risoluzione, il formato dei frame e i limiti
+/// entro cui la qualità si adatta alla rete. `quality` ed `encoding` sono il massimo consentito.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BroadcastQuality {
+    #[serde(flatten)]
+    pub quality: StreamQuality,
+    pub encoding: FrameEncoding,
+    pub adaptive: AdaptiveBounds,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_percentages() {
+        assert_eq!("75%".parse(), Ok(OutputScale::Percent(75)));
+        assert_eq!(" 100 % ".parse(), Ok(OutputScale::Percent(100)));
+        assert_eq!("1%".parse(), Ok(OutputScale::Percent(1)));
+        assert!("0%".parse::<OutputScale>().is_err());
+        assert!("101%".parse::<OutputScale>().is_err());
+        assert!("-5%".parse::<OutputScale>().is_err());
+        assert!("half%".parse::<OutputScale>().is_err());
+    }
+
+    #[test]
+    fn parses_max_widths() {
+        assert_eq!("1280".parse(), Ok(OutputScale::MaxWidth(1280)));
+        assert_eq!(" 640 ".parse(), Ok(OutputScale::MaxWidth(640)));
+        assert!("0".parse::<OutputScale>().is_err());
+        assert!("1280px".parse::<OutputScale>().is_err());
+        assert!("".parse::<OutputScale>().is_err());
+    }
+
+    #[test]
+    fn display_matches_the_parsed_forms() {
+        assert_eq!(OutputScale::Percent(75).to_string(), "75%");
+        assert_eq!(OutputScale::MaxWidth(1280).to_string(), "max 1280 px wide");
+    }
+
+    #[test]
+    fn percent_keeps_the_aspect_ratio() {
+        assert_eq!(OutputScale::Percent(100).output_size(1920, 1080), (1920, 1080));
+        assert_eq!(OutputScale::Percent(50).output_size(1920, 1080), (960, 540));
+        assert_eq!(OutputScale::Percent(75).output_size(2560, 1440), (1920, 1080));
+    }
+
+    #[test]
+    fn max_width_only_shrinks_wider_frames() {
+        assert_eq!(OutputScale::MaxWidth(1280).output_size(1920, 1080), (1280, 720));
+        assert_eq!(OutputScale::MaxWidth(1280).output_size(1024, 768), (1024, 768));
+        assert_eq!(OutputScale::MaxWidth(1280).output_size(1280, 1024), (1280, 1024));
+    }
+
+    #[test]
+    fn reduced_sizes_are_even() {
+        // 1366x768 al 75% sarebbe 1024.5x576, 1001x501 al 50% sarebbe 500.5x250.5
+        assert_eq!(OutputScale::Percent(75).output_size(1366, 768), (1024, 576));
+        assert_eq!(OutputScale::Percent(50).output_size(1001, 501), (500, 250));
+        assert_eq!(OutputScale::MaxWidth(999).output_size(1920, 1080), (998, 560));
+        for percent in 1..100 {
+            let (width, height) = OutputScale::Percent(percent).output_size(1023, 767);
+            assert!(width % 2 == 0 && height % 2 == 0, "{}% -> {}x{}", percent, width, height);
+            assert!(width >= 2 && height >= 2);
+        }
+    }
+
+    #[test]
+    fn framerate_is_clamped() {
+        assert_eq!(StreamQuality { fps: 0, ..StreamQuality::default() }.fps(), MIN_FRAMERATE);
+        assert_eq!(StreamQuality { fps: 240, ..StreamQuality::default() }.fps(), MAX_FRAMERATE);
+        assert_eq!(StreamQuality { fps: 25, ..StreamQuality::default() }.frame_duration(), Duration::from_millis(40));
+    }
+}
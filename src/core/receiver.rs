@@ -0,0 +1,165 @@
+// src/core/receiver.rs
+
+use std::io;
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::error::{Error, ErrorSender, Result, Worker};
+use crate::core::network::{deserialize_message, read_payload, serialize_feedback, write_payload, ReceiverFeedback, StreamMessage, MAX_PAYLOAD};
+use crate::core::stats::{millis, unix_millis, ReceiverStats};
+use crate::core::worker::{WorkerHandle, WorkerStatus};
+use log::{info, warn};
+
+// Oltre questo tempo un caster che non risponde viene considerato irraggiungibile
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// Ogni quanto il receiver invia al caster le proprie statistiche
+const FEEDBACK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Connessione a un caster: un thread legge i messaggi e li passa alla callback.
+/// Anche il drop chiude la connessione e attende la fine del thread.
+pub struct Receiver {
+    worker: WorkerHandle,
+    stats: Arc<Mutex<ReceiverStats>>,
+    // Frame in attesa nell'applicazione, riportati al caster con le statistiche
+    queue_depth: Arc<Mutex<usize>>,
+}
+
+impl Receiver {
+    /// Si connette al caster e avvia il thread di lettura.
+    /// Se il caster è protetto da password, `password` viene inviata subito dopo la connessione.
+    /// Se presente, `errors` riceve l'errore che interrompe la ricezione; la chiusura della
+    /// trasmissione da parte del caster non è un errore.
+    pub fn connect(
+        address: &str,
+        port: u16,
+        password: Option<&str>,
+        errors: Option<ErrorSender>,
+        mut on_message_received: impl FnMut(StreamMessage) + Send + 'static,
+    ) -> Result<Self> {
+        let mut stream = connect_with_timeout(address, port)
+            .map_err(|source| Error::Connect { address: format!("{}:{}", address, port), source })?;
+        if let Some(password) = password {
+            write_payload(&mut stream, password.as_bytes()).map_err(Error::Network)?;
+        }
+        info!("Receiver client started");
+
+        let mut reader = stream.try_clone()?;
+        let mut writer = stream.try_clone()?;
+        let stats = Arc::new(Mutex::new(ReceiverStats::default()));
+        let thread_stats = Arc::clone(&stats);
+        let queue_depth = Arc::new(Mutex::new(0));
+        let thread_queue_depth = Arc::clone(&queue_depth);
+
+        let worker = WorkerHandle::spawn(Worker::Receiving, errors, || Ok(()), move |(), cancel| {
+            // La cancellazione chiude il socket, così la lettura in corso si sblocca
+            cancel.on_cancel(move || {
+                let _ = stream.shutdown(Shutdown::Both);
+            });
+            // Frame e byte ricevuti dall'ultimo invio delle statistiche al caster
+            let mut window_start = Instant::now();
+            let (mut window_frames, mut window_bytes) = (0u32, 0usize);
+
+            let mut result = Ok(());
+            while !cancel.is_cancelled() {
+                match read_payload(&mut reader, MAX_PAYLOAD) {
+                    Ok(buffer) => {
+                        let decode_start = Instant::now();
+                        match deserialize_message(&buffer) {
+                            Ok(message) => {
+                                if let StreamMessage::Frame(frame) = &message {
+                                    let mut stats = thread_stats.lock().unwrap();
+                                    stats.decode_ms.push(millis(decode_start.elapsed()));
+                                    stats.frame_bytes.push(buffer.len() as f32);
+                                    stats.latency_ms.push(unix_millis().saturating_sub(frame.captured_at) as f32);
+                                    stats.frames += 1;
+                                    stats.framerate.tick();
+                                    window_frames += 1;
+                                    window_bytes += buffer.len();
+                                }
+                                on_message_received(message)
+                            }
+                            Err(e) => warn!("Failed to deserialize message: {}", e),
+                        }
+                    }
+                    // Il caster chiude la trasmissione con un byte di stop seguito dalla chiusura del socket
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                        info!("Caster closed the stream");
+                        break;
+                    }
+                    Err(e) => {
+                        // Con `stop` la lettura viene interrotta di proposito
+                        if !cancel.is_cancelled() {
+                            result = Err(Error::Network(e));
+                        }
+                        break;
+                    }
+                }
+
+                let elapsed = window_start.elapsed();
+                if elapsed >= FEEDBACK_INTERVAL {
+                    let seconds = elapsed.as_secs_f32();
+                    let feedback = ReceiverFeedback {
+                        received_fps: window_frames as f32 / seconds,
+                        bytes_per_second: window_bytes as f32 / seconds,
+                        queue_depth: *thread_queue_depth.lock().unwrap() as u32,
+                    };
+                    // Un caster che non legge le statistiche non impedisce la ricezione dei frame
+                    let sent = serialize_feedback(&feedback)
+                        .and_then(|payload| write_payload(&mut writer, &payload).map_err(Error::Network));
+                    if let Err(e) = sent {
+                        warn!("Failed to send feedback to caster: {}", e);
+                    }
+                    window_start = Instant::now();
+                    (window_frames, window_bytes) = (0, 0);
+                }
+            }
+
+            info!("Receiver client exiting");
+            result
+        })?;
+
+        Ok(Self { worker, stats, queue_depth })
+    }
+
+    /// False quando il caster ha chiuso la trasmissione o la connessione si è interrotta
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    pub fn status(&self) -> WorkerStatus {
+        self.worker.status()
+    }
+
+    /// Metriche della ricezione, aggiornate a ogni frame
+    pub fn stats(&self) -> Arc<Mutex<ReceiverStats>> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Frame ricevuti che l'applicazione non ha ancora mostrato o registrato. Il valore viene
+    /// inviato al caster, che riduce la qualità se il receiver non sta al passo.
+    pub fn set_queue_depth(&self, depth: usize) {
+        *self.queue_depth.lock().unwrap() = depth;
+    }
+
+    /// Chiude la connessione e attende la fine del thread di lettura
+    pub fn stop(self) {
+        self.worker.stop();
+    }
+
+    /// Attende che il caster chiuda la trasmissione
+    pub fn join(self) {
+        self.worker.join();
+    }
+}
+
+fn connect_with_timeout(address: &str, port: u16) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, format!("cannot resolve {}", address));
+    for addr in (address, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
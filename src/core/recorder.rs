@@ -0,0 +1,253 @@
+// src/core/recorder.rs
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::capture::ScreenCapture;
+use crate::core::capture_pipeline::FrameSubscriber;
+use crate::core::error::{Error, ErrorSender, Result, Worker};
+use crate::core::quality::StreamQuality;
+use crate::core::sync_frame_rate;
+use crate::core::worker::{CancellationToken, WorkerHandle, WorkerStatus};
+use crate::utils::annotations::RedactAnnotation;
+use log::{debug, info, warn};
+
+/// Parametri di ffmpeg e cartella di destinazione delle registrazioni
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingProfile {
+    pub directory: String,
+    // Encoder video passato a ffmpeg con `-c:v`
+    pub codec: String,
+    // Preset di libx264: più è veloce, meno CPU usa e più grande è il file
+    pub preset: String,
+    // Constant Rate Factor: 0 è senza perdita, valori più alti comprimono di più
+    pub crf: u8,
+    // Framerate e risoluzione del video, indipendenti da quelli della trasmissione
+    pub quality: StreamQuality,
+}
+
+impl Default for RecordingProfile {
+    fn default() -> Self {
+        Self {
+            directory: "recordings".to_string(),
+            codec: "libx264".to_string(),
+            preset: "ultrafast".to_string(),
+            crf: 0,
+            quality: StreamQuality::default(),
+        }
+    }
+}
+
+impl RecordingProfile {
+    pub fn output_path(&self, file_name: &str) -> PathBuf {
+        PathBuf::from(&self.directory).join(file_name)
+    }
+}
+
+/// Registrazione in corso: un thread passa i frame a ffmpeg, che li codifica nel file di output.
+/// Anche il drop ferma la registrazione e attende che ffmpeg chiuda il file.
+pub struct Recorder {
+    worker: WorkerHandle,
+}
+
+impl Recorder {
+    /// Registra lo schermo con i frame della pipeline di cattura, al framerate del profilo.
+    /// Le regioni in `redactions` vengono oscurate in ogni frame come nella trasmissione.
+    /// Se presente, `errors` riceve l'errore che interrompe la registrazione; se la pipeline
+    /// si ferma, la registrazione termina e il file viene chiuso.
+    pub fn record_screen(
+        profile: &RecordingProfile,
+        output: &Path,
+        frames: FrameSubscriber,
+        redactions: Arc<Mutex<Vec<RedactAnnotation>>>,
+        errors: Option<ErrorSender>,
+    ) -> Result<Self> {
+        let (capture_width, capture_height) = frames.frame_size();
+        let quality = profile.quality;
+        frames.set_fps(quality.fps());
+        let (width, height) = quality.scale.output_size(capture_width, capture_height);
+        let profile = profile.clone();
+        let output = output.to_path_buf();
+
+        let worker = WorkerHandle::spawn(
+            Worker::Recording,
+            errors,
+            move || spawn_ffmpeg(&profile, &output, width, height),
+            move |(child, mut out), cancel: CancellationToken| {
+                debug!("Record thread started");
+                let mut record = || {
+                    // La pipeline consegna i frame al framerate della registrazione
+                    while let Some(shared_frame) = frames.next_frame(&cancel) {
+                        let mut frame = ScreenCapture::clone(&shared_frame.frame);
+                        frame.redact(&redactions.lock().unwrap());
+                        write_frame(&mut out, &frame.scaled(quality.scale)?, width, height)?;
+                    }
+                    if cancel.is_cancelled() {
+                        debug!("Received stop signal, stopping recording...");
+                    } else {
+                        warn!("Screen capture stopped, stopping recording...");
+                    }
+                    Ok(())
+                };
+                let result = record();
+
+                // Il file viene chiuso anche se la registrazione si è interrotta per un errore.
+                // Se ffmpeg è terminato con un errore, quello è il motivo dell'interruzione.
+                let finished = finish_ffmpeg(child, out);
+                debug!("Record thread exiting");
+                finished.and(result)
+            },
+        )?;
+
+        Ok(Self { worker })
+    }
+
+    /// Registra i frame accodati in `frames` da un altro thread, ad esempio quelli ricevuti
+    /// da un caster. I frame di dimensioni diverse da `width`x`height` vengono ridimensionati.
+    pub fn record_frames(
+        profile: &RecordingProfile,
+        output: &Path,
+        frames: Arc<Mutex<VecDeque<ScreenCapture>>>,
+        width: usize,
+        height: usize,
+        errors: Option<ErrorSender>,
+    ) -> Result<Self> {
+        let quality = profile.quality;
+        let (output_width, output_height) = quality.scale.output_size(width, height);
+        let profile = profile.clone();
+        let output = output.to_path_buf();
+
+        let worker = WorkerHandle::spawn(
+            Worker::Recording,
+            errors,
+            move || spawn_ffmpeg(&profile, &output, output_width, output_height),
+            move |(child, mut out), cancel: CancellationToken| {
+                debug!("Record thread for receiver started");
+                let mut record = || loop {
+                    let frame_start = Instant::now();
+                    if cancel.is_cancelled() {
+                        debug!("Received stop signal, stopping recording...");
+                        return Ok(());
+                    }
+
+                    let frame = frames.lock().unwrap().pop_front();
+                    match frame {
+                        // Il caster può cambiare risoluzione durante la trasmissione: ogni frame viene
+                        // riportato alle dimensioni del video (`width`x`height` ridotte dalla scala del profilo)
+                        Some(frame) => {
+                            // Un frame non valido viene scartato senza interrompere la registrazione
+                            match frame.resized(output_width, output_height) {
+                                Ok(frame) => write_frame(&mut out, &frame, output_width, output_height)?,
+                                Err(e) => warn!("Skipping frame: {}", e),
+                            }
+                        }
+                        None => {
+                            // Attendi un breve periodo se non ci sono frame disponibili
+                            cancel.wait_timeout(Duration::from_millis(10));
+                        }
+                    }
+
+                    sync_frame_rate(frame_start, quality.frame_duration(), &cancel);
+                };
+                let result = record();
+
+                let finished = finish_ffmpeg(child, out);
+                debug!("Record thread for receiver exiting");
+                finished.and(result)
+            },
+        )?;
+
+        Ok(Self { worker })
+    }
+
+    /// Token con cui fermare la registrazione senza attendere che ffmpeg chiuda il file
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.worker.cancellation_token()
+    }
+
+    pub fn status(&self) -> WorkerStatus {
+        self.worker.status()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Ferma la registrazione e attende che ffmpeg chiuda il file
+    pub fn stop(self) {
+        self.worker.stop();
+    }
+
+    /// Attende che la registrazione venga fermata e che ffmpeg chiuda il file
+    pub fn join(self) {
+        self.worker.join();
+    }
+}
+
+/// Avvia ffmpeg in attesa di frame RGBA grezzi sullo stdin, creando la cartella di `output` se manca
+fn spawn_ffmpeg(profile: &RecordingProfile, output: &Path, width: usize, height: usize) -> Result<(Child, ChildStdin)> {
+    if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-loglevel", "quiet",
+            "-f", "rawvideo",
+            "-pixel_format", "rgb0", // Usare 'rgb0' per i colori corretti
+            "-video_size", &format!("{}x{}", width, height),
+            "-framerate", &profile.quality.fps().to_string(), // Specifica il framerate corretto
+            "-i", "-", // Legge dallo stdin per il video
+            "-c:v", &profile.codec, // Codec video
+            "-preset", &profile.preset,  // Preset per ridurre la compressione e mantenere velocità reale
+            "-crf", &profile.crf.to_string(), // Constant Rate Factor: 0 per qualità massima (nessuna compressione)
+            "-pix_fmt", "yuv420p", // Formato pixel compatibile
+            "-r", &profile.quality.fps().to_string(), // Forza il framerate in uscita
+            "-y",
+        ])
+        .arg(output) // Nome del file di output con percorso
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(Error::Ffmpeg)?;
+
+    let out = child.stdin.take().ok_or_else(|| Error::Ffmpeg(io::Error::other("stdin not available")))?;
+    Ok((child, out))
+}
+
+/// Un errore di scrittura indica che ffmpeg ha smesso di leggere, ad esempio perché è terminato
+fn write_frame(out: &mut impl Write, frame: &ScreenCapture, width: usize, height: usize) -> Result<()> {
+    if frame.width as usize != width || frame.height as usize != height {
+        warn!("Skipping {}x{} frame in a {}x{} recording", frame.width, frame.height, width, height);
+        return Ok(());
+    }
+    let stride = frame.data.len() / height;
+    let rowlen = 4 * width;
+    for row in frame.data.chunks(stride) {
+        out.write_all(&row[..rowlen]).map_err(Error::Ffmpeg)?;
+    }
+    Ok(())
+}
+
+/// Chiude lo stdin di ffmpeg e attende che finalizzi il file. Un'uscita con errore (ad esempio
+/// per un encoder non disponibile) viene restituita come `Error::Ffmpeg`.
+fn finish_ffmpeg(mut child: Child, out: ChildStdin) -> Result<()> {
+    info!("Flushing and closing ffmpeg...");
+    drop(out); // Chiude lo stdin di ffmpeg per consentirgli di terminare correttamente
+
+    let status = child.wait().map_err(Error::Ffmpeg)?;
+    info!("ffmpeg exited with status: {:?}", status);
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Ffmpeg(io::Error::other(format!("ffmpeg exited with {}", status))))
+    }
+}
+
@@ -0,0 +1,104 @@
+// src/core/stats.rs
+
+use std::collections::{BTreeMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::core::adaptive::AppliedQuality;
+
+// Campioni conservati per ogni metrica: circa 6 secondi al framerate target
+pub const HISTORY_LEN: usize = 120;
+
+/// Ultimi `HISTORY_LEN` valori di una metrica, dal più vecchio al più recente
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    samples: VecDeque<f32>,
+}
+
+impl History {
+    pub fn push(&mut self, value: f32) {
+        if self.samples.len() == HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    pub fn samples(&self) -> &VecDeque<f32> {
+        &self.samples
+    }
+
+    pub fn last(&self) -> Option<f32> {
+        self.samples.back().copied()
+    }
+
+    pub fn average(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<f32>() / self.samples.len() as f32)
+    }
+
+    pub fn max(&self) -> Option<f32> {
+        self.samples.iter().copied().reduce(f32::max)
+    }
+}
+
+/// Framerate ottenuto, calcolato dall'intervallo tra due frame consecutivi
+#[derive(Clone, Debug, Default)]
+pub struct FpsCounter {
+    last_frame: Option<Instant>,
+    pub fps: History,
+}
+
+impl FpsCounter {
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame {
+            let elapsed = now.duration_since(last).as_secs_f32();
+            if elapsed > 0.0 {
+                self.fps.push(1.0 / elapsed);
+            }
+        }
+        self.last_frame = Some(now);
+    }
+}
+
+/// Metriche del caster, aggiornate dal thread di trasmissione a ogni frame
+#[derive(Clone, Debug, Default)]
+pub struct CasterStats {
+    pub capture_ms: History,
+    pub serialize_ms: History,
+    pub frame_bytes: History,
+    pub framerate: FpsCounter,
+    // Framerate richiesto, eventualmente ridotto per adattarsi alla rete
+    pub target_fps: u32,
+    // Qualità scelta per l'ultimo frame
+    pub applied_quality: Option<AppliedQuality>,
+    // Tempo di scrittura del frame sul socket di ciascun receiver connesso
+    pub send_ms: BTreeMap<SocketAddr, History>,
+    // Frame che hanno richiesto più del tempo concesso dal framerate
+    pub overloaded_frames: u64,
+    pub frames: u64,
+}
+
+/// Metriche del receiver, aggiornate dal thread di lettura a ogni frame
+#[derive(Clone, Debug, Default)]
+pub struct ReceiverStats {
+    pub decode_ms: History,
+    pub frame_bytes: History,
+    // Tempo trascorso dalla cattura sul caster: è attendibile solo se gli orologi delle due macchine sono sincronizzati
+    pub latency_ms: History,
+    pub framerate: FpsCounter,
+    pub frames: u64,
+}
+
+/// Millisecondi trascorsi dallo UNIX epoch, usati come timestamp dei frame
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+pub fn millis(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
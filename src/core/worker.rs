@@ -0,0 +1,336 @@
+// src/core/worker.rs
+
+//! Thread di lavoro (trasmissione, ricezione, registrazione, preview e listener della sessione).
+//! Ogni thread viene avviato da `WorkerHandle::spawn`, si ferma quando il suo `CancellationToken`
+//! viene cancellato ed è sempre atteso alla fine: con `stop`, `join` o il drop dell'handle.
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::error;
+
+use crate::core::error::{Error, ErrorSender, Result, Worker, WorkerError};
+
+type CancelCallback = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: bool,
+    // Eseguite alla cancellazione, ad esempio per sbloccare una lettura dal socket
+    callbacks: Vec<CancelCallback>,
+}
+
+/// Richiesta di fermare un thread di lavoro. Si può clonare e passare ad altri thread,
+/// ad esempio a un gestore di Ctrl+C: tutte le copie condividono lo stesso stato.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<(Mutex<TokenState>, Condvar)>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Non attende la fine del thread; se il token è già stato cancellato non fa nulla.
+    pub fn cancel(&self) {
+        let (state, condvar) = &*self.0;
+        let callbacks = {
+            let mut state = state.lock().unwrap();
+            if state.cancelled {
+                return;
+            }
+            state.cancelled = true;
+            std::mem::take(&mut state.callbacks)
+        };
+        condvar.notify_all();
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0 .0.lock().unwrap().cancelled
+    }
+
+    /// Esegue `callback` alla cancellazione, o subito se il token è già stato cancellato.
+    /// Serve a interrompere le operazioni bloccanti che non controllano il token.
+    pub fn on_cancel(&self, callback: impl FnOnce() + Send + 'static) {
+        let mut state = self.0 .0.lock().unwrap();
+        if state.cancelled {
+            drop(state);
+            callback();
+        } else {
+            state.callbacks.push(Box::new(callback));
+        }
+    }
+
+    /// Attende `timeout`, o meno se il token viene cancellato prima.
+    /// Restituisce true se il token è stato cancellato.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (state, condvar) = &*self.0;
+        let state = state.lock().unwrap();
+        let (state, _) = condvar.wait_timeout_while(state, timeout, |state| !state.cancelled).unwrap();
+        state.cancelled
+    }
+}
+
+/// Stato di un thread di lavoro, mostrato nella diagnostica
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkerStatus {
+    Running,
+    /// Il token è stato cancellato ma il thread non è ancora terminato
+    Stopping,
+    Finished,
+    /// Terminato con un errore o un panic, già inviato sul canale degli errori
+    Failed,
+}
+
+impl fmt::Display for WorkerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkerStatus::Running => write!(f, "running"),
+            WorkerStatus::Stopping => write!(f, "stopping"),
+            WorkerStatus::Finished => write!(f, "finished"),
+            WorkerStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Thread di lavoro avviato. Il drop cancella il token e attende la fine del thread,
+/// quindi un thread non resta mai in esecuzione dopo che il suo handle è stato scartato.
+pub struct WorkerHandle {
+    worker: Worker,
+    token: CancellationToken,
+    failed: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl WorkerHandle {
+    /// Avvia un thread di lavoro in due fasi. `setup` gira nel nuovo thread (il capturer, ad esempio,
+    /// non può cambiare thread) e il suo errore viene restituito subito al chiamante; poi `body` gira
+    /// finché non termina da solo o il token non viene cancellato. Un errore o un panic di `body`
+    /// viene scritto nel log e inviato su `errors`, così chi ha avviato il thread può aggiornare il proprio stato.
+    pub(crate) fn spawn<S>(
+        worker: Worker,
+        errors: Option<ErrorSender>,
+        setup: impl FnOnce() -> Result<S> + Send + 'static,
+        body: impl FnOnce(S, CancellationToken) -> Result<()> + Send + 'static,
+    ) -> Result<Self> {
+        let token = CancellationToken::new();
+        let failed = Arc::new(AtomicBool::new(false));
+        let (ready_tx, ready_rx) = mpsc::sync_channel(1);
+        let thread = {
+            let token = token.clone();
+            let failed = Arc::clone(&failed);
+            thread::Builder::new().name(worker.to_string()).spawn(move || {
+                let state = match catch_panic(setup) {
+                    Ok(state) => {
+                        let _ = ready_tx.send(Ok(()));
+                        state
+                    }
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                if let Err(error) = catch_panic(|| body(state, token)) {
+                    error!("{} stopped: {}", worker, error);
+                    failed.store(true, Ordering::SeqCst);
+                    if let Some(errors) = errors {
+                        let _ = errors.send(WorkerError { worker, error });
+                    }
+                }
+            })?
+        };
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self { worker, token, failed, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(Error::Panic(format!("{} thread exited during setup", worker))),
+        }
+    }
+
+    pub fn worker(&self) -> Worker {
+        self.worker
+    }
+
+    /// Token con cui fermare il thread da un altro thread senza attenderne la fine
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    pub fn status(&self) -> WorkerStatus {
+        match &self.thread {
+            Some(thread) if !thread.is_finished() => {
+                if self.token.is_cancelled() {
+                    WorkerStatus::Stopping
+                } else {
+                    WorkerStatus::Running
+                }
+            }
+            _ if self.failed.load(Ordering::SeqCst) => WorkerStatus::Failed,
+            _ => WorkerStatus::Finished,
+        }
+    }
+
+    /// True finché il thread non è terminato, anche se è già stato chiesto di fermarlo
+    pub fn is_running(&self) -> bool {
+        matches!(self.status(), WorkerStatus::Running | WorkerStatus::Stopping)
+    }
+
+    /// Chiede al thread di fermarsi senza attenderne la fine
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Ferma il thread e ne attende la fine
+    pub fn stop(self) {
+        self.cancel();
+        self.join();
+    }
+
+    /// Attende che il thread termini da solo o perché il token è stato cancellato altrove
+    pub fn join(mut self) {
+        self.join_thread();
+    }
+
+    fn join_thread(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("{} thread panicked", self.worker);
+            }
+        }
+    }
+}
+
+impl Drop for WorkerHandle {
+    fn drop(&mut self) {
+        self.cancel();
+        self.join_thread();
+    }
+}
+
+fn catch_panic<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| Err(Error::from_panic(payload)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const WAIT_LIMIT: Duration = Duration::from_secs(5);
+
+    fn wait_until(handle: &WorkerHandle, status: WorkerStatus) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < WAIT_LIMIT {
+            if handle.status() == status {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        false
+    }
+
+    /// Thread che gira finché il token non viene cancellato
+    fn spawn_until_cancelled(errors: Option<ErrorSender>) -> WorkerHandle {
+        WorkerHandle::spawn(Worker::Capture, errors, || Ok(()), |(), cancel| {
+            while !cancel.wait_timeout(Duration::from_millis(5)) {}
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn cancelled_worker_finishes() {
+        let (errors_tx, errors_rx) = mpsc::channel();
+        let handle = spawn_until_cancelled(Some(errors_tx));
+        assert_eq!(handle.status(), WorkerStatus::Running);
+
+        handle.cancel();
+        assert!(wait_until(&handle, WorkerStatus::Finished));
+        assert!(!handle.is_running());
+        handle.join();
+        assert!(errors_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn panic_is_reported_as_failure() {
+        let (errors_tx, errors_rx) = mpsc::channel();
+        let handle = WorkerHandle::spawn(Worker::Recording, Some(errors_tx), || Ok(()), |(), _| -> Result<()> {
+            panic!("encoder exploded")
+        })
+        .unwrap();
+
+        assert!(wait_until(&handle, WorkerStatus::Failed));
+        let reported = errors_rx.recv_timeout(WAIT_LIMIT).unwrap();
+        assert_eq!(reported.worker, Worker::Recording);
+        assert!(matches!(reported.error, Error::Panic(ref message) if message == "encoder exploded"));
+    }
+
+    #[test]
+    fn body_error_is_sent_on_the_channel() {
+        let (errors_tx, errors_rx) = mpsc::channel();
+        let handle = WorkerHandle::spawn(Worker::Receiving, Some(errors_tx), || Ok(()), |(), _| {
+            Err(Error::Network(std::io::Error::other("reset")))
+        })
+        .unwrap();
+
+        assert!(wait_until(&handle, WorkerStatus::Failed));
+        let reported = errors_rx.recv_timeout(WAIT_LIMIT).unwrap();
+        assert!(matches!(reported.error, Error::Network(_)));
+    }
+
+    #[test]
+    fn setup_error_is_returned_by_spawn() {
+        let spawned = WorkerHandle::spawn(
+            Worker::Broadcast,
+            None,
+            || -> Result<()> { Err(Error::DisplayNotFound(3)) },
+            |(), _| Ok(()),
+        );
+        assert!(matches!(spawned, Err(Error::DisplayNotFound(3))));
+    }
+
+    #[test]
+    fn drop_cancels_and_joins() {
+        let exited = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let exited = Arc::clone(&exited);
+            WorkerHandle::spawn(Worker::Listener, None, || Ok(()), move |(), cancel| {
+                while !cancel.wait_timeout(Duration::from_millis(5)) {}
+                // Lascia al drop il tempo di tornare se non attendesse il thread
+                thread::sleep(Duration::from_millis(50));
+                exited.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+            .unwrap()
+        };
+        let token = handle.cancellation_token();
+
+        drop(handle);
+        assert!(token.is_cancelled());
+        assert!(exited.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn on_cancel_runs_immediately_once_cancelled() {
+        let token = CancellationToken::new();
+        let calls = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&calls);
+        token.on_cancel(move || *counter.lock().unwrap() += 1);
+        token.cancel();
+        token.cancel();
+        let counter = Arc::clone(&calls);
+        token.on_cancel(move || *counter.lock().unwrap() += 1);
+
+        assert_eq!(*calls.lock().unwrap(), 2);
+        assert!(token.wait_timeout(Duration::from_secs(1)));
+    }
+}
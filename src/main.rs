@@ -1,19 +1,48 @@
-mod app;
-mod utils;
+mod cli;
 
-use app::gui::app_main::initialize;
+use clap::Parser;
+use cli::Cli;
+use screen_stream_x::utils::logging::{self, LogLevel};
 
 /// Punto di ingresso principale dell'applicazione ScreenCaster.
-/// Questa funzione inizializza l'applicazione, configura i gestori di hotkey
+/// Con un sottocomando (`cast`, `receive`, `record`) lavora senza finestra; altrimenti
+/// inizializza l'applicazione, configura i gestori di hotkey
 /// e il trascrittore, quindi avvia il ciclo degli eventi.
 fn main() {
-    println!("Main function started.");
+    let cli = Cli::parse();
+    // La verbosità salvata nelle impostazioni viene applicata appena sono state lette
+    logging::init(LogLevel::default());
+    if let Some(command) = cli.command {
+        if let Err(e) = cli::run(command) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    run_gui();
+}
+
+/// Senza sottocomando si apre la finestra
+#[cfg(feature = "gui")]
+fn run_gui() {
+    log::info!("Starting the GUI");
 
     // Inizializza l'applicazione e gestisci eventuali errori di inizializzazione.
-    if let Err(e) = initialize() {
-        eprintln!("Errore durante l'inizializzazione dell'interfaccia: {}", e);
+    if let Err(e) = screen_stream_x::app::gui::app_main::initialize() {
+        log::error!("Failed to initialize the GUI: {}", e);
         return;
     }
-    println!("Applicazione inizializzata con successo.");
+    log::info!("GUI closed");
+
+}
+
+/// Compilato senza la feature `gui`: restano solo i sottocomandi
+#[cfg(not(feature = "gui"))]
+fn run_gui() {
+    use clap::CommandFactory;
 
+    eprintln!("This build has no GUI; use one of the subcommands.\n");
+    let _ = Cli::command().print_help();
+    std::process::exit(2);
 }
@@ -1,8 +1,15 @@
-use eframe::emath::{Pos2, RectTransform, Rot2, Vec2};
+use egui::emath::{Pos2, RectTransform, Rot2, Vec2};
 use egui::epaint::{CircleShape, RectShape, TextShape};
 use egui::{Color32, FontId, Painter, Rect, Shape, Stroke};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use crate::utils::ephemeral::EphemeralEvent;
+use crate::utils::stroke;
+
+/// Points of the smoothed curve between two points of a simplified pencil stroke.
+const SMOOTH_SAMPLES: usize = 6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Annotation {
     Segment(SegmentAnnotation),
     Circle(CircleAnnotation),
@@ -13,6 +20,10 @@ pub enum Annotation {
     Eraser(EraserAnnotation),
     Crop(CropAnnotation),
     Highlighter(HighlighterAnnotation),
+    Edit(EditAnnotation),
+    Step(StepAnnotation),
+    Callout(CalloutAnnotation),
+    Redact(RedactAnnotation),
 }
 
 impl Annotation {
@@ -50,6 +61,22 @@ impl Annotation {
     pub fn crop(pos: Pos2) -> Self {
         Self::Crop(CropAnnotation::new(pos, pos))
     }
+
+    pub fn edit(previous: Annotation, index: usize) -> Self {
+        Self::Edit(EditAnnotation::new(index, Box::new(previous)))
+    }
+
+    pub fn step(center: Pos2, color: Color32, radius: f32) -> Self {
+        Self::Step(StepAnnotation::new(center, color, radius))
+    }
+
+    pub fn callout(anchor: Pos2, color: Color32, fill_color: Color32, width: f32, font_size: f32) -> Self {
+        Self::Callout(CalloutAnnotation::new(anchor, color, fill_color, width, font_size))
+    }
+
+    pub fn redact(pos: Pos2, style: RedactStyle) -> Self {
+        Self::Redact(RedactAnnotation::new(pos, style))
+    }
     pub fn render(
         &self,
         scaling: f32,
@@ -67,9 +94,14 @@ impl Annotation {
             Annotation::Text(t) => t.render(scaling, rect_transform, painter, editing),
             Annotation::Eraser(_) => Shape::Noop,
             Annotation::Crop(c) => c.render(scaling, rect_transform),
+            Annotation::Edit(_) => Shape::Noop,
+            Annotation::Step(s) => s.render(scaling, rect_transform, painter),
+            Annotation::Callout(c) => c.render(scaling, rect_transform, painter, editing),
+            Annotation::Redact(r) => r.render(rect_transform, editing),
         }
     }
 
+    /// `click` is in screen coordinates, hit-tested with a few points of tolerance.
     pub fn check_click(
         &self,
         click: Pos2,
@@ -77,14 +109,283 @@ impl Annotation {
         rect_transform: RectTransform,
         painter: &Painter,
     ) -> bool {
-        self.render(scaling, rect_transform, painter, false)
-            .visual_bounding_rect()
-            .contains(click)
+        let to_original = rect_transform.inverse();
+        self.hit_test(
+            to_original.transform_pos(click),
+            CLICK_TOLERANCE / scaling,
+            painter,
+        )
+    }
+
+    /// Precise hit test against the drawn geometry, `pos` and `tolerance` in original coordinates.
+    /// History entries (eraser, edit) and crops are never hit.
+    pub fn hit_test(&self, pos: Pos2, tolerance: f32, painter: &Painter) -> bool {
+        match self {
+            Annotation::Segment(s) => {
+                distance_to_segment(pos, s.starting_pos, s.ending_pos) <= s.width / 2.0 + tolerance
+            }
+            Annotation::Circle(c) => {
+                let distance = pos.distance(c.center);
+                let reach = c.width / 2.0 + tolerance;
+                if c.fill_color != Color32::TRANSPARENT {
+                    distance <= c.radius + reach
+                } else {
+                    (distance - c.radius).abs() <= reach
+                }
+            }
+            Annotation::Rect(r) => {
+                let rect = Rect::from_two_pos(r.p1, r.p2);
+                let reach = r.width / 2.0 + tolerance;
+                if r.fill_color != Color32::TRANSPARENT {
+                    rect.expand(reach).contains(pos)
+                } else {
+                    rect.expand(reach).contains(pos) && !rect.shrink(reach).contains(pos)
+                }
+            }
+            Annotation::Arrow(a) => {
+                let reach = a.width / 2.0 + tolerance;
+                let [tip1, tip2] = a.tip_ends();
+                distance_to_segment(pos, a.starting_pos, a.ending_pos) <= reach
+                    || distance_to_segment(pos, a.ending_pos, tip1) <= reach
+                    || distance_to_segment(pos, a.ending_pos, tip2) <= reach
+            }
+            Annotation::Pencil(p) => {
+                distance_to_polyline(pos, &p.points) <= p.width / 2.0 + tolerance
+            }
+            Annotation::Highlighter(h) => {
+                distance_to_polyline(pos, &h.points) <= h.width / 2.0 + tolerance
+            }
+            Annotation::Text(_) => self.bounds(painter).expand(tolerance).contains(pos),
+            Annotation::Step(s) => pos.distance(s.center) <= s.radius + tolerance,
+            Annotation::Callout(c) => {
+                let frame = c.frame(painter);
+                frame.expand(tolerance).contains(pos)
+                    || distance_to_segment(pos, frame.center(), c.anchor)
+                        <= c.tail_half_width(frame) + tolerance
+            }
+            Annotation::Redact(r) => r.get_rect().expand(tolerance).contains(pos),
+            Annotation::Eraser(_) | Annotation::Crop(_) | Annotation::Edit(_) => false,
+        }
+    }
+
+    /// Bounding rectangle of the geometry (without stroke width), in original coordinates.
+    pub fn bounds(&self, painter: &Painter) -> Rect {
+        match self {
+            Annotation::Segment(s) => Rect::from_two_pos(s.starting_pos, s.ending_pos),
+            Annotation::Circle(c) => Rect::from_center_size(c.center, Vec2::splat(c.radius * 2.0)),
+            Annotation::Rect(r) => Rect::from_two_pos(r.p1, r.p2),
+            Annotation::Arrow(a) => Rect::from_two_pos(a.starting_pos, a.ending_pos),
+            Annotation::Pencil(p) => Rect::from_points(&p.points),
+            Annotation::Highlighter(h) => Rect::from_points(&h.points),
+            Annotation::Text(t) => {
+                let identity = RectTransform::identity(Rect::EVERYTHING);
+                let rect = t.render(1.0, identity, painter, false).visual_bounding_rect();
+                if rect.is_positive() {
+                    rect
+                } else {
+                    Rect::from_min_size(t.pos, Vec2::splat(t.size))
+                }
+            }
+            Annotation::Crop(c) => c.get_rect(),
+            Annotation::Step(s) => Rect::from_center_size(s.center, Vec2::splat(s.radius * 2.0)),
+            Annotation::Callout(c) => c.frame(painter).union(Rect::from_pos(c.anchor)),
+            Annotation::Redact(r) => r.get_rect(),
+            Annotation::Eraser(_) | Annotation::Edit(_) => Rect::NOTHING,
+        }
+    }
+
+    pub fn translate(&mut self, delta: Vec2) {
+        self.map_points(|p| p + delta, 1.0);
+    }
+
+    /// Whether the annotation is drawn, as opposed to the eraser, crop and edit entries
+    /// that only record history for undo.
+    pub fn is_drawing(&self) -> bool {
+        !matches!(
+            self,
+            Annotation::Eraser(_) | Annotation::Crop(_) | Annotation::Edit(_)
+        )
+    }
+
+    /// Resizes the annotation so that the rectangle `from` is mapped onto `to`.
+    pub fn transform(&mut self, from: Rect, to: Rect) {
+        let scale_x = if from.width() > f32::EPSILON { to.width() / from.width() } else { 1.0 };
+        let scale_y = if from.height() > f32::EPSILON { to.height() / from.height() } else { 1.0 };
+        let size_scale = scale_x.abs().min(scale_y.abs());
+        self.map_points(
+            |p| {
+                Pos2::new(
+                    to.min.x + (p.x - from.min.x) * scale_x,
+                    to.min.y + (p.y - from.min.y) * scale_y,
+                )
+            },
+            size_scale,
+        );
+    }
+
+    /// Applies `f` to every point, and scales radii and font sizes by `size_scale`.
+    fn map_points(&mut self, f: impl Fn(Pos2) -> Pos2, size_scale: f32) {
+        match self {
+            Annotation::Segment(s) => {
+                s.starting_pos = f(s.starting_pos);
+                s.ending_pos = f(s.ending_pos);
+            }
+            Annotation::Circle(c) => {
+                c.center = f(c.center);
+                c.radius *= size_scale;
+            }
+            Annotation::Rect(r) => {
+                r.p1 = f(r.p1);
+                r.p2 = f(r.p2);
+            }
+            Annotation::Arrow(a) => {
+                a.starting_pos = f(a.starting_pos);
+                a.ending_pos = f(a.ending_pos);
+                a.consolidate();
+            }
+            Annotation::Pencil(p) => p.points.iter_mut().for_each(|point| *point = f(*point)),
+            Annotation::Highlighter(h) => h.points.iter_mut().for_each(|point| *point = f(*point)),
+            Annotation::Text(t) => {
+                t.pos = f(t.pos);
+                t.size *= size_scale;
+            }
+            Annotation::Crop(c) => {
+                c.p1 = f(c.p1);
+                c.p2 = f(c.p2);
+            }
+            Annotation::Step(s) => {
+                s.center = f(s.center);
+                s.radius *= size_scale;
+            }
+            Annotation::Callout(c) => {
+                c.anchor = f(c.anchor);
+                c.pos = f(c.pos);
+                c.size *= size_scale;
+            }
+            Annotation::Redact(r) => {
+                r.p1 = f(r.p1);
+                r.p2 = f(r.p2);
+            }
+            Annotation::Eraser(_) | Annotation::Edit(_) => {}
+        }
+    }
+
+    /// Stroke (or text) color, if the annotation has one.
+    pub fn color(&self) -> Option<Color32> {
+        match self {
+            Annotation::Segment(s) => Some(s.color),
+            Annotation::Circle(c) => Some(c.color),
+            Annotation::Rect(r) => Some(r.color),
+            Annotation::Arrow(a) => Some(a.color),
+            Annotation::Pencil(p) => Some(p.color),
+            Annotation::Highlighter(h) => Some(h.color),
+            Annotation::Text(t) => Some(t.color),
+            Annotation::Step(s) => Some(s.color),
+            Annotation::Callout(c) => Some(c.color),
+            Annotation::Eraser(_)
+            | Annotation::Crop(_)
+            | Annotation::Edit(_)
+            | Annotation::Redact(_) => None,
+        }
     }
-    //TODO
+
+    pub fn set_color(&mut self, color: Color32) {
+        match self {
+            Annotation::Segment(s) => s.color = color,
+            Annotation::Circle(c) => c.color = color,
+            Annotation::Rect(r) => r.update_color(color),
+            Annotation::Arrow(a) => a.color = color,
+            Annotation::Pencil(p) => p.color = color,
+            Annotation::Highlighter(h) => h.color = color,
+            Annotation::Text(t) => t.color = color,
+            Annotation::Step(s) => s.color = color,
+            Annotation::Callout(c) => c.color = color,
+            Annotation::Eraser(_)
+            | Annotation::Crop(_)
+            | Annotation::Edit(_)
+            | Annotation::Redact(_) => {}
+        }
+    }
+}
+
+/// Hit tolerance around strokes, in screen points.
+const CLICK_TOLERANCE: f32 = 4.0;
+
+pub fn distance_to_segment(pos: Pos2, a: Pos2, b: Pos2) -> f32 {
+    let ab = b - a;
+    let length_sq = ab.length_sq();
+    if length_sq == 0.0 {
+        return pos.distance(a);
+    }
+    let t = ((pos - a).dot(ab) / length_sq).clamp(0.0, 1.0);
+    pos.distance(a + ab * t)
+}
+
+pub fn distance_to_polyline(pos: Pos2, points: &[Pos2]) -> f32 {
+    match points {
+        [] => f32::INFINITY,
+        [single] => pos.distance(*single),
+        _ => points
+            .windows(2)
+            .map(|w| distance_to_segment(pos, w[0], w[1]))
+            .fold(f32::INFINITY, f32::min),
+    }
+}
+
+/// Resize handles on the corners and edge midpoints of `rect`.
+pub fn control_points(rect: Rect) -> Vec<ControlPoint> {
+    vec![
+        ControlPoint::new(rect.left_top(), Position::LeftTop),
+        ControlPoint::new(rect.center_top(), Position::CenterTop),
+        ControlPoint::new(rect.right_top(), Position::RightTop),
+        ControlPoint::new(rect.left_center(), Position::LeftCenter),
+        ControlPoint::new(rect.right_center(), Position::RightCenter),
+        ControlPoint::new(rect.left_bottom(), Position::LeftBottom),
+        ControlPoint::new(rect.center_bottom(), Position::CenterBottom),
+        ControlPoint::new(rect.right_bottom(), Position::RightBottom),
+    ]
 }
 
-#[derive(Debug, Clone)]
+/// A change to an annotation list, streamed to receivers alongside the frames.
+/// Positions inside the annotations are in capture-space coordinates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AnnotationEvent {
+    Added { index: usize, annotation: Annotation },
+    Removed { index: usize },
+    /// The annotation at `index` was moved, resized or recolored.
+    Updated { index: usize, annotation: Annotation },
+    Cleared,
+    /// Full snapshot, sent to receivers that join while annotations already exist.
+    Sync(Vec<Annotation>),
+    /// Laser pointer or disappearing ink; leaves the annotation list untouched.
+    Ephemeral(EphemeralEvent),
+}
+
+impl AnnotationEvent {
+    pub fn apply(self, annotations: &mut Vec<Annotation>) {
+        match self {
+            AnnotationEvent::Added { index, annotation } => {
+                let index = index.min(annotations.len());
+                annotations.insert(index, annotation);
+            }
+            AnnotationEvent::Removed { index } => {
+                if index < annotations.len() {
+                    annotations.remove(index);
+                }
+            }
+            AnnotationEvent::Updated { index, annotation } => {
+                if let Some(existing) = annotations.get_mut(index) {
+                    *existing = annotation;
+                }
+            }
+            AnnotationEvent::Cleared => annotations.clear(),
+            AnnotationEvent::Sync(all) => *annotations = all,
+            AnnotationEvent::Ephemeral(_) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SegmentAnnotation {
     pub starting_pos: Pos2,
     pub ending_pos: Pos2,
@@ -117,7 +418,7 @@ impl SegmentAnnotation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CircleAnnotation {
     pub center: Pos2,
     pub radius: f32,
@@ -152,7 +453,7 @@ impl CircleAnnotation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RectAnnotation {
     pub p1: Pos2,
     pub p2: Pos2,
@@ -190,7 +491,7 @@ impl RectAnnotation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tip {
     pub line1: (Pos2, Pos2),
     pub line2: (Pos2, Pos2),
@@ -214,7 +515,7 @@ impl Tip {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArrowAnnotation {
     pub starting_pos: Pos2,
     pub ending_pos: Pos2,
@@ -249,20 +550,22 @@ impl ArrowAnnotation {
         self.tip
             .set_line2((tip, self.ending_pos - tip_length * (rot.inverse() * dir)));
     }
-    fn render(&self, scaling: f32, rect_transform: RectTransform) -> Shape {
+    /// Outer ends of the two tip strokes, in original coordinates.
+    pub fn tip_ends(&self) -> [Pos2; 2] {
         let rot = Rot2::from_angle(std::f32::consts::TAU / 10.0);
         let vec = self.ending_pos - self.starting_pos;
         let tip_length = vec.length() / 4.0;
-        let tip = rect_transform.transform_pos(self.ending_pos);
         let dir = vec.normalized();
-        let line1 = [
-            tip,
-            rect_transform.transform_pos(self.ending_pos - tip_length * (rot * dir)),
-        ];
-        let line2 = [
-            tip,
-            rect_transform.transform_pos(self.ending_pos - tip_length * (rot.inverse() * dir)),
-        ];
+        [
+            self.ending_pos - tip_length * (rot * dir),
+            self.ending_pos - tip_length * (rot.inverse() * dir),
+        ]
+    }
+    fn render(&self, scaling: f32, rect_transform: RectTransform) -> Shape {
+        let tip = rect_transform.transform_pos(self.ending_pos);
+        let [end1, end2] = self.tip_ends();
+        let line1 = [tip, rect_transform.transform_pos(end1)];
+        let line2 = [tip, rect_transform.transform_pos(end2)];
         let body = Shape::line_segment(
             [rect_transform.transform_pos(self.starting_pos), tip],
             Stroke::new(self.width * scaling, self.color),
@@ -274,7 +577,7 @@ impl ArrowAnnotation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextAnnotation {
     pub pos: Pos2,
     pub text: String,
@@ -292,13 +595,6 @@ impl TextAnnotation {
         }
     }
 
-    pub fn update_text(&mut self, new_text: &String) {
-        self.text = self.text.clone() + new_text.as_str();
-    }
-
-    pub fn delete_char(&mut self) {
-        self.text.pop();
-    }
     fn render(
         &self,
         scaling: f32,
@@ -352,7 +648,242 @@ impl TextAnnotation {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Numbered badge for step-by-step walkthroughs. `number` is kept in sequence by the editor:
+/// the steps are numbered in the order they appear in the annotation list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepAnnotation {
+    pub center: Pos2,
+    pub radius: f32,
+    pub color: Color32,
+    pub number: u32,
+}
+
+impl StepAnnotation {
+    fn new(center: Pos2, color: Color32, radius: f32) -> Self {
+        Self {
+            center,
+            radius,
+            color,
+            number: 1,
+        }
+    }
+
+    fn render(&self, scaling: f32, to_screen: RectTransform, painter: &Painter) -> Shape {
+        let center = to_screen.transform_pos(self.center);
+        let radius = self.radius * scaling;
+        let text_color = contrasting_color(self.color);
+        let galley = painter.layout_no_wrap(
+            self.number.to_string(),
+            FontId::proportional(radius * STEP_FONT_RATIO),
+            text_color,
+        );
+        let text_pos = center - galley.size() / 2.0;
+        Shape::Vec(vec![
+            Shape::Circle(CircleShape {
+                center,
+                radius,
+                fill: self.color,
+                stroke: Stroke::new(radius * 0.12, text_color),
+            }),
+            Shape::Text(TextShape::new(text_pos, galley, text_color)),
+        ])
+    }
+}
+
+/// Font size of a step number, relative to the badge radius.
+pub const STEP_FONT_RATIO: f32 = 1.1;
+
+/// Black or white, whichever reads better over `background`.
+pub fn contrasting_color(background: Color32) -> Color32 {
+    let [r, g, b, _] = background.to_srgba_unmultiplied();
+    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    if luminance > 150.0 {
+        Color32::BLACK
+    } else {
+        Color32::WHITE
+    }
+}
+
+/// Text box with a background and a tail pointing at `anchor`. `pos` is the top-left corner of the box.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalloutAnnotation {
+    pub anchor: Pos2,
+    pub pos: Pos2,
+    pub text: String,
+    pub size: f32,
+    pub color: Color32,
+    pub fill_color: Color32,
+    pub width: f32,
+}
+
+impl CalloutAnnotation {
+    fn new(anchor: Pos2, color: Color32, fill_color: Color32, width: f32, size: f32) -> Self {
+        Self {
+            anchor,
+            pos: anchor,
+            text: String::new(),
+            size,
+            color,
+            fill_color,
+            width,
+        }
+    }
+
+    pub fn update_pos(&mut self, pos: Pos2) {
+        self.pos = pos;
+    }
+
+    /// Box around the text, in original coordinates.
+    pub fn frame(&self, painter: &Painter) -> Rect {
+        let identity = RectTransform::identity(Rect::EVERYTHING);
+        self.layout(1.0, identity, painter).0
+    }
+
+    /// Half the width of the tail where it leaves the box.
+    pub fn tail_half_width(&self, frame: Rect) -> f32 {
+        frame.width().min(frame.height()) / 4.0
+    }
+
+    fn layout(
+        &self,
+        scaling: f32,
+        to_screen: RectTransform,
+        painter: &Painter,
+    ) -> (Rect, std::sync::Arc<egui::Galley>) {
+        let galley = painter.layout_no_wrap(
+            self.text.clone(),
+            FontId::monospace(self.size * scaling),
+            self.color,
+        );
+        let padding = self.size * CALLOUT_PADDING * scaling;
+        // An empty box still shows the size of one character while typing
+        let text_size = galley.size().max(Vec2::new(self.size * 0.6, self.size) * scaling);
+        let frame = Rect::from_min_size(
+            to_screen.transform_pos(self.pos),
+            text_size + Vec2::splat(padding * 2.0),
+        );
+        (frame, galley)
+    }
+
+    fn render(
+        &self,
+        scaling: f32,
+        to_screen: RectTransform,
+        painter: &Painter,
+        editing: bool,
+    ) -> Shape {
+        let (frame, galley) = self.layout(scaling, to_screen, painter);
+        let stroke = Stroke::new(self.width * scaling, self.color);
+        let anchor = to_screen.transform_pos(self.anchor);
+        let mut shapes = Vec::new();
+
+        // The tail starts from the center of the box, which is then drawn over it
+        if !frame.contains(anchor) {
+            let half_width = self.tail_half_width(frame);
+            let side = (anchor - frame.center()).normalized().rot90() * half_width;
+            shapes.push(Shape::convex_polygon(
+                vec![frame.center() + side, anchor, frame.center() - side],
+                self.fill_color,
+                stroke,
+            ));
+        }
+        shapes.push(Shape::Rect(RectShape::new(
+            frame,
+            self.size * 0.3 * scaling,
+            self.fill_color,
+            stroke,
+        )));
+        let padding = self.size * CALLOUT_PADDING * scaling;
+        shapes.push(Shape::Text(TextShape::new(
+            frame.min + Vec2::splat(padding),
+            galley,
+            self.color,
+        )));
+        if editing {
+            shapes.extend(Shape::dashed_line(
+                &[
+                    frame.left_top(),
+                    frame.right_top(),
+                    frame.right_bottom(),
+                    frame.left_bottom(),
+                    frame.left_top(),
+                ],
+                Stroke::new(1.0, Color32::LIGHT_GRAY),
+                1.0,
+                3.0,
+            ));
+        }
+        Shape::Vec(shapes)
+    }
+}
+
+/// Space between the text of a callout and its border, relative to the font size.
+pub const CALLOUT_PADDING: f32 = 0.4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RedactStyle {
+    Pixelate,
+    Blur,
+}
+
+/// Region hidden in the frames that leave the caster (broadcast, recording, snapshots).
+/// It is applied to the pixels themselves, so it draws nothing except while being placed;
+/// the editor outlines committed regions with [`RedactAnnotation::outline`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactAnnotation {
+    pub p1: Pos2,
+    pub p2: Pos2,
+    pub style: RedactStyle,
+}
+
+impl RedactAnnotation {
+    fn new(pos: Pos2, style: RedactStyle) -> Self {
+        Self {
+            p1: pos,
+            p2: pos,
+            style,
+        }
+    }
+
+    pub fn update_p2(&mut self, p2: Pos2) {
+        self.p2 = p2;
+    }
+
+    pub fn get_rect(&self) -> Rect {
+        Rect::from_two_pos(self.p1, self.p2)
+    }
+
+    fn render(&self, to_screen: RectTransform, editing: bool) -> Shape {
+        if !editing {
+            return Shape::Noop;
+        }
+        let rect = to_screen.transform_rect(self.get_rect());
+        Shape::Vec(vec![
+            Shape::rect_filled(rect, 0.0, Color32::from_black_alpha(120)),
+            self.outline(to_screen, Color32::WHITE),
+        ])
+    }
+
+    /// Dashed border of the region, drawn by the editor so the caster can see what is hidden.
+    /// Not part of `render`, which receivers and snapshots also use.
+    pub fn outline(&self, to_screen: RectTransform, color: Color32) -> Shape {
+        let rect = to_screen.transform_rect(self.get_rect());
+        Shape::Vec(Shape::dashed_line(
+            &[
+                rect.left_top(),
+                rect.right_top(),
+                rect.right_bottom(),
+                rect.left_bottom(),
+                rect.left_top(),
+            ],
+            Stroke::new(1.0, color),
+            4.0,
+            3.0,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PencilAnnotation {
     pub points: Vec<Pos2>,
     pub color: Color32,
@@ -376,6 +907,13 @@ impl PencilAnnotation {
         self.points.push(pos);
     }
 
+    /// Replaces the raw pointer samples with a simplified and smoothed curve.
+    /// `tolerance` is the largest deviation allowed by the simplification.
+    pub fn finish(&mut self, tolerance: f32) {
+        let simplified = stroke::simplify(&self.points, tolerance);
+        self.points = stroke::smooth(&simplified, SMOOTH_SAMPLES);
+    }
+
     pub fn render(&self, scaling: f32, rect_transform: RectTransform) -> Shape {
         let line: Vec<Pos2> = self
             .points
@@ -387,7 +925,7 @@ impl PencilAnnotation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HighlighterAnnotation {
     pub points: Vec<Pos2>,
     pub color: Color32,
@@ -422,7 +960,7 @@ impl HighlighterAnnotation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EraserAnnotation {
     pub annotation: Box<Annotation>,
     pub index: usize,
@@ -434,7 +972,32 @@ impl EraserAnnotation {
     }
 }
 
-#[derive(Debug, Clone)]
+/// History entry for a move, resize or recolor: holds the other version of the
+/// annotation at `index`, swapped back in on undo (and out again on redo).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditAnnotation {
+    pub annotation: Box<Annotation>,
+    pub index: usize,
+}
+
+impl EditAnnotation {
+    pub fn new(index: usize, annotation: Box<Annotation>) -> Self {
+        Self { index, annotation }
+    }
+}
+
+/// The crop currently applied: the last crop entry, limited to the frame.
+pub fn active_crop(annotations: &[Annotation], full: Rect) -> Option<Rect> {
+    let crop = annotations.iter().rev().find_map(|a| match a {
+        Annotation::Crop(c) => Some(c.get_rect()),
+        _ => None,
+    })?;
+    let crop = crop.intersect(full);
+    let crop = Rect::from_min_max(crop.min.round(), crop.max.round());
+    (crop.width() >= 1.0 && crop.height() >= 1.0).then_some(crop)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CropAnnotation {
     pub p1: Pos2,
     pub p2: Pos2,
@@ -503,17 +1066,7 @@ impl CropAnnotation {
         ]
     }
     pub fn get_control_points(&self, to_screen: RectTransform) -> Vec<ControlPoint> {
-        let rect = to_screen.transform_rect(Rect::from_two_pos(self.p1, self.p2));
-        vec![
-            ControlPoint::new(rect.left_top(), Position::LeftTop),
-            ControlPoint::new(rect.center_top(), Position::CenterTop),
-            ControlPoint::new(rect.right_top(), Position::RightTop),
-            ControlPoint::new(rect.left_center(), Position::LeftCenter),
-            ControlPoint::new(rect.right_center(), Position::RightCenter),
-            ControlPoint::new(rect.left_bottom(), Position::LeftBottom),
-            ControlPoint::new(rect.center_bottom(), Position::CenterBottom),
-            ControlPoint::new(rect.right_bottom(), Position::RightBottom),
-        ]
+        control_points(to_screen.transform_rect(Rect::from_two_pos(self.p1, self.p2)))
     }
     pub fn update(&mut self, pos: Pos2) {
         self.p2 = pos;
@@ -531,6 +1084,7 @@ impl CropAnnotation {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Position {
     LeftTop,
     CenterTop,
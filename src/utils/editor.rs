@@ -1,19 +1,28 @@
+use std::collections::{HashMap, VecDeque};
 use std::default::Default;
 use std::ops::RangeInclusive;
 use std::sync::{Arc, Mutex};
 
-use eframe::emath::{Rect, RectTransform};
+use egui::emath::{Rect, RectTransform};
 use egui::color_picker::Alpha;
 use egui::{
-    Color32, ColorImage, DragValue, Event, Image, Key, Painter, PointerButton, Pos2, Rounding,
-    Sense, Shape, Stroke, TextureHandle, TextureOptions, Ui, Vec2, Widget,
+    Color32, ColorImage, Context, DragValue, Event, Image, Key, Painter, PointerButton, Pos2,
+    Rounding, Sense, Shape, Stroke, TextureHandle, TextureOptions, Ui, Vec2, Widget,
 };
 
-use crate::utils::annotations::{Annotation, Position};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::annotations::{
+    control_points, Annotation, AnnotationEvent, Position, RedactAnnotation, RedactStyle,
+};
+use crate::utils::ephemeral::{EphemeralEvent, EphemeralLayer};
 use crate::utils::icons::*;
+use crate::utils::session::AnnotationSession;
+use crate::utils::stroke;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub enum Mode {
+    Callout,
     Crop,
     DrawArrow,
     DrawCircle,
@@ -21,19 +30,51 @@ pub enum Mode {
     DrawLine,
     DrawRect,
     Erase,
+    FadingInk,
     Highlight,
     Idle,
     InsertText,
+    LaserPointer,
+    Redact,
     Redo,
+    Select,
+    Step,
     Undo,
 }
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum FillType {
     Primary,
     Secondary,
     None,
 }
 
+/// Stroke, fill and font settings remembered for each drawing tool.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ToolStyle {
+    pub color: Color32,
+    pub fill_color: Color32,
+    pub fill_type: FillType,
+    pub width: f32,
+    pub font_size: f32,
+}
+
+const MAX_RECENT_COLORS: usize = 8;
+/// Hit tolerance and handle radius for the select tool, in screen points.
+const SELECT_TOLERANCE: f32 = 6.0;
+const HANDLE_RADIUS: f32 = 5.0;
+/// Largest deviation, in screen points, allowed when simplifying pencil strokes.
+const PENCIL_TOLERANCE: f32 = 1.0;
+/// Outline of committed redaction regions in the caster preview.
+const REDACT_OUTLINE_COLOR: Color32 = Color32::from_rgb(255, 200, 0);
+
+/// A move or resize of the selected annotation in progress.
+struct SelectionDrag {
+    original: Annotation,
+    from: Rect,
+    to: Rect,
+    handle: Option<Position>,
+}
+
 pub struct Editor {
     pub captured_image: Arc<Mutex<Option<ColorImage>>>,
     pub texture: Option<TextureHandle>,
@@ -48,6 +89,26 @@ pub struct Editor {
     pub current_font_size: f32,
     pub width_range: RangeInclusive<f32>,
     pub fill_type: FillType,
+    /// Last style used with each drawing tool, restored when the tool is selected again.
+    pub tool_styles: HashMap<Mode, ToolStyle>,
+    /// Most recently used stroke/fill colors, newest first.
+    pub recent_colors: VecDeque<Color32>,
+    /// Index in `annotations` of the annotation picked with the select tool.
+    pub selected: Option<usize>,
+    selection_drag: Option<SelectionDrag>,
+    /// Color of the selection when it was picked or last recolored.
+    selection_color: Option<Color32>,
+    /// Whether the last history entry is a recolor that further recolors can be merged into.
+    merge_recolor: bool,
+    /// Changes to `annotations` not yet collected with [`Editor::take_events`].
+    pub events: Vec<AnnotationEvent>,
+    /// Laser pointer and disappearing ink, kept out of `annotations` and the undo history.
+    pub ephemeral: EphemeralLayer,
+    ephemeral_stroke: Option<u64>,
+    /// Whether pencil strokes that look like a line, arrow, circle or rectangle become that shape.
+    pub snap_shapes: bool,
+    /// How new redaction regions hide what is under them.
+    pub redact_style: RedactStyle,
 }
 
 impl Default for Editor {
@@ -66,6 +127,17 @@ impl Default for Editor {
             current_font_size: 16.0,
             fill_type: FillType::None,
             width_range: Editor::default_width_range(),
+            tool_styles: Editor::default_tool_styles(),
+            recent_colors: VecDeque::new(),
+            selected: None,
+            selection_drag: None,
+            selection_color: None,
+            merge_recolor: false,
+            events: Vec::new(),
+            ephemeral: EphemeralLayer::default(),
+            ephemeral_stroke: None,
+            snap_shapes: false,
+            redact_style: RedactStyle::Pixelate,
         }
     }
 }
@@ -79,8 +151,150 @@ impl Editor {
     fn default_width_range_highlighter() -> RangeInclusive<f32> {
         10.0..=50.0
     }
+    #[inline]
+    fn font_size_range() -> RangeInclusive<f32> {
+        8.0..=96.0
+    }
+    fn default_tool_styles() -> HashMap<Mode, ToolStyle> {
+        HashMap::from([
+            (
+                Mode::Highlight,
+                ToolStyle {
+                    color: Color32::from_rgba_unmultiplied(255, 255, 0, 100),
+                    fill_color: Color32::BLUE,
+                    fill_type: FillType::None,
+                    width: 10.0,
+                    font_size: 16.0,
+                },
+            ),
+            (
+                Mode::Callout,
+                ToolStyle {
+                    color: Color32::BLACK,
+                    fill_color: Color32::from_rgb(255, 250, 205),
+                    fill_type: FillType::Secondary,
+                    width: 1.5,
+                    font_size: 16.0,
+                },
+            ),
+        ])
+    }
+
+    /// Whether the mode draws annotations, and so has its own [`ToolStyle`].
+    fn has_style(mode: Mode) -> bool {
+        matches!(
+            mode,
+            Mode::Callout
+                | Mode::DrawArrow
+                | Mode::DrawCircle
+                | Mode::DrawFree
+                | Mode::DrawLine
+                | Mode::DrawRect
+                | Mode::FadingInk
+                | Mode::Highlight
+                | Mode::InsertText
+                | Mode::Step
+        )
+    }
+
+    /// Fill of new circles and rectangles, according to `fill_type`.
+    fn current_fill(&self) -> Color32 {
+        match self.fill_type {
+            FillType::Primary => self.current_color,
+            FillType::Secondary => self.current_fill_color,
+            FillType::None => Color32::TRANSPARENT,
+        }
+    }
+
+    pub fn current_style(&self) -> ToolStyle {
+        ToolStyle {
+            color: self.current_color,
+            fill_color: self.current_fill_color,
+            fill_type: self.fill_type,
+            width: self.current_width,
+            font_size: self.current_font_size,
+        }
+    }
+
+    pub fn apply_style(&mut self, style: ToolStyle) {
+        self.current_color = style.color;
+        self.current_fill_color = style.fill_color;
+        self.fill_type = style.fill_type;
+        self.current_width = style.width;
+        self.current_font_size = style.font_size;
+    }
+
+    /// Switches tool, saving the style of the previous one and restoring the last style of the new one.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.deselect();
+        if Editor::has_style(self.mode) {
+            self.tool_styles.insert(self.mode, self.current_style());
+        }
+        self.mode = mode;
+        if let Some(style) = self.tool_styles.get(&mode).copied() {
+            self.apply_style(style);
+        }
+        self.width_range = if mode == Mode::Highlight {
+            Editor::default_width_range_highlighter()
+        } else {
+            Editor::default_width_range()
+        };
+        self.current_width = self
+            .current_width
+            .clamp(*self.width_range.start(), *self.width_range.end());
+    }
+
+    /// Saved tool styles, including the unsaved changes to the active tool.
+    pub fn all_tool_styles(&self) -> HashMap<Mode, ToolStyle> {
+        let mut styles = self.tool_styles.clone();
+        if Editor::has_style(self.mode) {
+            styles.insert(self.mode, self.current_style());
+        }
+        styles
+    }
+
+    /// Width and height of the last captured frame, i.e. of the space annotations are drawn in.
+    pub fn capture_size(&self) -> Option<[usize; 2]> {
+        self.captured_image.lock().unwrap().as_ref().map(|image| image.size)
+    }
+
+    /// Replaces annotations and tool styles with those of a saved session.
+    /// The undo history starts over.
+    pub fn load_session(&mut self, session: AnnotationSession) {
+        self.deselect();
+        self.current_annotation = None;
+        self.undone_annotations.clear();
+        self.merge_recolor = false;
+        self.annotations = session.annotations;
+        self.renumber_steps();
+        self.events.clear();
+        self.events.push(AnnotationEvent::Sync(self.annotations.clone()));
+        self.tool_styles.extend(session.tool_styles);
+        if let Some(style) = self.tool_styles.get(&self.mode).copied() {
+            self.apply_style(style);
+        }
+    }
+
+    fn remember_color(&mut self, color: Color32) {
+        if color == Color32::TRANSPARENT {
+            return;
+        }
+        self.recent_colors.retain(|c| *c != color);
+        self.recent_colors.push_front(color);
+        self.recent_colors.truncate(MAX_RECENT_COLORS);
+    }
     pub fn manage(&mut self, ui: &mut Ui) {
-        let image_ratio = self.texture.as_ref().unwrap().aspect_ratio();
+        let Some(to_screen) = self.show_image(ui) else {
+            return;
+        };
+        self.manage_input(ui, to_screen.inverse());
+        self.manage_render(ui.painter(), to_screen);
+    }
+    /// Shows the (cropped) texture fitted to the available space and returns the
+    /// transform from image pixels to screen, or `None` if there is nothing to show yet.
+    pub fn show_image(&mut self, ui: &mut Ui) -> Option<RectTransform> {
+        let texture = self.texture.clone()?;
+        let image_ratio = texture.aspect_ratio();
         let space_ratio = ui.max_rect().aspect_ratio();
         if image_ratio > space_ratio {
             let mut padding = ui.max_rect();
@@ -90,26 +304,17 @@ impl Editor {
             ui.advance_cursor_after_rect(padding);
         }
 
-        let image_res = Image::new(&self.texture.clone().unwrap())
+        let image_res = Image::new(&texture)
             .maintain_aspect_ratio(true)
             .max_size(ui.available_size())
             .shrink_to_fit()
             .ui(ui);
-        let to_screen = RectTransform::from_to(self.crop_rect, image_res.rect);
         ui.set_clip_rect(image_res.rect);
-        self.manage_input(ui, to_screen.inverse());
-        self.manage_render(ui.painter(), to_screen);
+        Some(RectTransform::from_to(self.crop_rect, image_res.rect))
     }
     pub fn manage_input(&mut self, ui: &mut Ui, to_original: RectTransform) {
-        if self.mode != Mode::Highlight
-            && self.width_range == Editor::default_width_range_highlighter()
-        {
-            self.width_range = Editor::default_width_range();
-            if !self.width_range.contains(&self.current_width) {
-                self.current_width = *self.width_range.end();
-            }
-        }
         match self.mode {
+            Mode::Callout => self.manage_callout(ui, to_original),
             Mode::Crop => self.manage_crop(ui, to_original),
             Mode::DrawArrow => self.manage_arrow(ui, to_original),
             Mode::DrawCircle => self.manage_circle(ui, to_original),
@@ -117,9 +322,14 @@ impl Editor {
             Mode::DrawLine => self.manage_segment(ui, to_original),
             Mode::DrawRect => self.manage_rect(ui, to_original),
             Mode::Erase => self.manage_eraser(ui, to_original),
+            Mode::FadingInk => self.manage_ephemeral(ui, to_original),
             Mode::Highlight => self.manage_highlighter(ui, to_original),
             Mode::Idle => {}
             Mode::InsertText => self.manage_text(ui, to_original),
+            Mode::LaserPointer => self.manage_ephemeral(ui, to_original),
+            Mode::Redact => self.manage_redact(ui, to_original),
+            Mode::Select => self.manage_select(ui, to_original),
+            Mode::Step => self.manage_step(ui, to_original),
             Mode::Redo => {}
             Mode::Undo => {}
         }
@@ -134,9 +344,40 @@ impl Editor {
             .collect();
         painter.extend(shapes);
 
+        // Redactions only change the outgoing frames: outline them so they can be seen and selected
+        let redactions: Vec<Shape> = self
+            .annotations
+            .iter()
+            .filter_map(|a| match a {
+                Annotation::Redact(r) => Some(r.outline(to_screen, REDACT_OUTLINE_COLOR)),
+                _ => None,
+            })
+            .collect();
+        painter.extend(redactions);
+
         if let Some(a) = &self.current_annotation {
             painter.add(a.render(to_screen.scale()[0], to_screen, painter, true));
         }
+
+        if let Some(selected) = self.selected.and_then(|i| self.annotations.get(i)) {
+            let color = painter.ctx().style().visuals.selection.stroke.color;
+            let rect = to_screen.transform_rect(selected.bounds(painter));
+            painter.add(Shape::dashed_line(
+                &[
+                    rect.left_top(),
+                    rect.right_top(),
+                    rect.right_bottom(),
+                    rect.left_bottom(),
+                    rect.left_top(),
+                ],
+                Stroke::new(1.0, color),
+                4.0,
+                3.0,
+            ));
+            for cp in control_points(rect) {
+                painter.circle(cp.pos, HANDLE_RADIUS, Color32::WHITE, Stroke::new(1.0, color));
+            }
+        }
     }
 
     // fn get_input(
@@ -156,7 +397,22 @@ impl Editor {
     // }
 
     
-    pub fn update_texture(&mut self, ui: &mut Ui, crop: Option<Rect>) {
+    /// Replaces the captured image with a new frame, keeping the current crop.
+    pub fn set_frame(&mut self, ctx: &Context, image: ColorImage) {
+        let full = Rect::from_min_size(
+            Pos2::ZERO,
+            Vec2::new(image.width() as f32, image.height() as f32),
+        );
+        let crop = if full.contains_rect(self.crop_rect) && self.crop_rect.is_positive() {
+            Some(self.crop_rect)
+        } else {
+            None
+        };
+        *self.captured_image.lock().unwrap() = Some(image);
+        self.update_texture(ctx, crop);
+    }
+
+    pub fn update_texture(&mut self, ctx: &Context, crop: Option<Rect>) {
         let Some(image) = self.captured_image.lock().unwrap().clone() else {
             panic!()
         };
@@ -169,18 +425,102 @@ impl Editor {
             )
         }
         let cropped_image = image.region(&self.crop_rect, None);
-        self.texture = Some(ui.ctx().load_texture(
-            "image",
-            cropped_image,
-            TextureOptions::default(),
-        ));
+        match self.texture.as_mut() {
+            Some(texture) => texture.set(cropped_image, TextureOptions::default()),
+            None => {
+                self.texture = Some(ctx.load_texture(
+                    "image",
+                    cropped_image,
+                    TextureOptions::default(),
+                ))
+            }
+        }
     }
 
-    fn add_annotation(&mut self, annotation: Annotation) {
+    /// Drains the annotation changes made since the last call.
+    pub fn take_events(&mut self) -> Vec<AnnotationEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn push_annotation(&mut self, mut annotation: Annotation) {
+        self.merge_recolor = false;
+        if let Annotation::Step(ref mut step) = annotation {
+            step.number = self.step_count() + 1;
+        }
+        self.events.push(AnnotationEvent::Added {
+            index: self.annotations.len(),
+            annotation: annotation.clone(),
+        });
         self.annotations.push(annotation);
+    }
+
+    fn pop_annotation(&mut self) -> Option<Annotation> {
+        let popped = self.annotations.pop()?;
+        self.events.push(AnnotationEvent::Removed {
+            index: self.annotations.len(),
+        });
+        self.renumber_steps();
+        Some(popped)
+    }
+
+    fn insert_annotation(&mut self, index: usize, annotation: Annotation) {
+        self.events.push(AnnotationEvent::Added {
+            index,
+            annotation: annotation.clone(),
+        });
+        self.annotations.insert(index, annotation);
+        self.renumber_steps();
+    }
+
+    fn remove_annotation(&mut self, index: usize) -> Annotation {
+        self.events.push(AnnotationEvent::Removed { index });
+        let removed = self.annotations.remove(index);
+        self.renumber_steps();
+        removed
+    }
+
+    fn step_count(&self) -> u32 {
+        self.annotations
+            .iter()
+            .filter(|a| matches!(a, Annotation::Step(_)))
+            .count() as u32
+    }
+
+    /// Numbers the step markers in list order, e.g. after one has been erased or restored.
+    fn renumber_steps(&mut self) {
+        let mut number = 0;
+        for (index, annotation) in self.annotations.iter_mut().enumerate() {
+            if let Annotation::Step(step) = annotation {
+                number += 1;
+                if step.number != number {
+                    step.number = number;
+                    self.events.push(AnnotationEvent::Updated {
+                        index,
+                        annotation: annotation.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn add_annotation(&mut self, annotation: Annotation) {
+        if Editor::has_style(self.mode) {
+            self.remember_color(self.current_color);
+            if self.fill_type == FillType::Secondary {
+                self.remember_color(self.current_fill_color);
+            }
+        }
+        self.push_annotation(annotation);
         self.undone_annotations = Vec::new();
         self.current_annotation = None;
     }
+
+    pub fn clear_annotations(&mut self) {
+        self.annotations.clear();
+        self.undone_annotations.clear();
+        self.current_annotation = None;
+        self.events.push(AnnotationEvent::Cleared);
+    }
     fn manage_crop(&mut self, ui: &mut Ui, to_original: RectTransform) {
         let mut crop = None;
         if let Some(Annotation::Crop(ref mut c)) = self.current_annotation.as_mut() {
@@ -191,7 +531,7 @@ impl Editor {
                 };
                 let pos = to_original.transform_pos_clamped(input);
                 c.update(pos);
-                if input_res.drag_released_by(PointerButton::Primary) {
+                if input_res.drag_stopped_by(PointerButton::Primary) {
                     if c.p1 != c.p2 {
                         c.update_resize(false);
                         c.reset_points();
@@ -240,7 +580,7 @@ impl Editor {
                     c.p2 = to_original.to().clamp(c.p2);
                 }
 
-                if point_response.drag_released_by(PointerButton::Primary) {
+                if point_response.drag_stopped_by(PointerButton::Primary) {
                     c.reset_points();
                 }
             });
@@ -271,7 +611,7 @@ impl Editor {
         }
 
         if let Some(crop_rect) = crop {
-            self.update_texture(ui, Some(crop_rect));
+            self.update_texture(ui.ctx(), Some(crop_rect));
             self.add_annotation(self.current_annotation.clone().unwrap());
         }
     }
@@ -293,7 +633,7 @@ impl Editor {
         }
         if let Some(Annotation::Segment(ref mut s)) = self.current_annotation.as_mut() {
             s.update_ending(pos);
-            if input_res.drag_released_by(PointerButton::Primary) {
+            if input_res.drag_stopped_by(PointerButton::Primary) {
                 if s.starting_pos != s.ending_pos {
                     self.add_annotation(self.current_annotation.clone().unwrap());
                 } else {
@@ -308,11 +648,7 @@ impl Editor {
         let Some(input) = input_res.interact_pointer_pos() else {
             return;
         };
-        let fill = match self.fill_type {
-            FillType::Primary => self.current_color,
-            FillType::Secondary => self.current_fill_color,
-            FillType::None => Color32::TRANSPARENT,
-        };
+        let fill = self.current_fill();
         let pos = to_original.transform_pos_clamped(input);
         if input_res.drag_started_by(PointerButton::Primary) {
             self.current_annotation = Some(Annotation::circle(
@@ -326,7 +662,7 @@ impl Editor {
 
         if let Some(Annotation::Circle(ref mut c)) = self.current_annotation.as_mut() {
             c.update_radius(pos);
-            if input_res.drag_released_by(PointerButton::Primary) {
+            if input_res.drag_stopped_by(PointerButton::Primary) {
                 if c.radius != 0.0 {
                     self.add_annotation(self.current_annotation.clone().unwrap());
                 } else {
@@ -344,11 +680,7 @@ impl Editor {
 
         let pos = to_original.transform_pos_clamped(input);
         if input_res.drag_started_by(PointerButton::Primary) {
-            let fill = match self.fill_type {
-                FillType::Primary => self.current_color,
-                FillType::Secondary => self.current_fill_color,
-                FillType::None => Color32::TRANSPARENT,
-            };
+            let fill = self.current_fill();
             self.current_annotation = Some(Annotation::rect(
                 pos,
                 self.current_color,
@@ -360,7 +692,7 @@ impl Editor {
 
         if let Some(Annotation::Rect(ref mut r)) = self.current_annotation.as_mut() {
             r.update_p2(pos);
-            if input_res.drag_released_by(PointerButton::Primary) {
+            if input_res.drag_stopped_by(PointerButton::Primary) {
                 if r.p1 != r.p2 {
                     self.add_annotation(self.current_annotation.clone().unwrap());
                 } else {
@@ -388,13 +720,13 @@ impl Editor {
 
         if let Some(Annotation::Arrow(ref mut a)) = self.current_annotation.as_mut() {
             a.update_ending(pos);
-            if input_res.drag_released_by(PointerButton::Primary) {
+            if input_res.drag_stopped_by(PointerButton::Primary) {
                 if a.starting_pos != a.ending_pos {
                     a.consolidate();
-                    self.annotations
-                        .push(self.current_annotation.clone().unwrap());
+                    self.add_annotation(self.current_annotation.clone().unwrap());
+                } else {
+                    self.current_annotation = None;
                 }
-                self.current_annotation = None;
             }
         }
     }
@@ -415,11 +747,24 @@ impl Editor {
             return;
         }
 
+        let fill = self.current_fill();
         if let Some(Annotation::Pencil(ref mut p)) = self.current_annotation.as_mut() {
             p.update_points(pos);
-            if input_res.drag_released_by(PointerButton::Primary) {
+            if input_res.drag_stopped_by(PointerButton::Primary) {
                 if p.points.len() > 1 {
-                    self.add_annotation(self.current_annotation.clone().unwrap());
+                    let recognized = if self.snap_shapes {
+                        stroke::recognize(&p.points, p.color, p.width, fill)
+                    } else {
+                        None
+                    };
+                    match recognized {
+                        Some(shape) => self.add_annotation(shape),
+                        None => {
+                            // Tolerance of about one screen pixel, whatever the preview zoom
+                            p.finish(PENCIL_TOLERANCE * to_original.scale().x);
+                            self.add_annotation(self.current_annotation.clone().unwrap());
+                        }
+                    }
                 } else {
                     self.current_annotation = None;
                 }
@@ -427,10 +772,42 @@ impl Editor {
         }
     }
 
+    /// Laser pointer and disappearing ink: the stroke lives in `ephemeral` and is streamed
+    /// to receivers, but is never added to `annotations`.
+    fn manage_ephemeral(&mut self, ui: &mut Ui, to_original: RectTransform) {
+        let input_res = ui.interact(*to_original.from(), ui.id(), Sense::click_and_drag());
+        let Some(input) = input_res.interact_pointer_pos() else {
+            return;
+        };
+
+        let pos = to_original.transform_pos_clamped(input);
+        let event = if input_res.drag_started_by(PointerButton::Primary) {
+            let event = if self.mode == Mode::LaserPointer {
+                self.ephemeral.begin_laser(pos)
+            } else {
+                self.ephemeral
+                    .begin_ink(pos, self.current_color, self.current_width)
+            };
+            if let EphemeralEvent::Begin { id, .. } = event {
+                self.ephemeral_stroke = Some(id);
+            }
+            event
+        } else if let Some(id) = self.ephemeral_stroke {
+            if input_res.drag_stopped_by(PointerButton::Primary) {
+                self.ephemeral_stroke = None;
+                self.ephemeral.end(id)
+            } else if input_res.dragged_by(PointerButton::Primary) {
+                self.ephemeral.extend(id, pos)
+            } else {
+                return;
+            }
+        } else {
+            return;
+        };
+        self.events.push(AnnotationEvent::Ephemeral(event));
+    }
+
     fn manage_highlighter(&mut self, ui: &mut Ui, to_original: RectTransform) {
-        if self.width_range != Editor::default_width_range_highlighter() {
-            self.width_range = Editor::default_width_range_highlighter();
-        }
         let input_res = ui.interact(*to_original.from(), ui.id(), Sense::click_and_drag());
         let Some(input) = input_res.interact_pointer_pos() else {
             return;
@@ -448,7 +825,7 @@ impl Editor {
 
         if let Some(Annotation::Highlighter(ref mut h)) = self.current_annotation.as_mut() {
             h.update_points(pos);
-            if input_res.drag_released_by(PointerButton::Primary) {
+            if input_res.drag_stopped_by(PointerButton::Primary) {
                 if h.points.len() > 1 {
                     self.add_annotation(self.current_annotation.clone().unwrap());
                 } else {
@@ -462,60 +839,146 @@ impl Editor {
         let input_res = ui.interact(*to_original.from(), ui.id(), Sense::click());
 
         if input_res.interact_pointer_pos().is_none() {
-            if self.current_annotation.is_some() {
-                let x = ui.input(|s| s.events.clone());
-                for event in &x {
-                    match event {
-                        Event::Text(text_to_insert) => {
-                            if let Some(Annotation::Text(ref mut t)) =
-                                self.current_annotation.as_mut()
-                            {
-                                t.update_text(text_to_insert)
-                            }
-                        }
-                        Event::Key {
-                            key: Key::Backspace,
-                            pressed: true,
-                            ..
-                        } => {
-                            if let Some(Annotation::Text(ref mut t)) =
-                                self.current_annotation.as_mut()
-                            {
-                                t.delete_char()
-                            }
-                        }
-                        Event::Key {
-                            key: Key::Enter,
-                            pressed: true,
-                            modifiers: egui::Modifiers::NONE,
-                            ..
-                        } => {
-                            self.add_annotation(self.current_annotation.clone().unwrap());
-                            return;
-                        }
-                        Event::Key {
-                            key: Key::Enter,
-                            pressed: true,
-                            modifiers: egui::Modifiers::SHIFT,
-                            ..
-                        } => {
-                            if let Some(Annotation::Text(ref mut t)) =
-                                self.current_annotation.as_mut()
-                            {
-                                t.update_text(&"\n".to_string());
-                            }
-                        }
-                        _ => {}
+            self.manage_typing(ui);
+            return;
+        }
+
+        let pos = to_original.transform_pos_clamped(input_res.interact_pointer_pos().unwrap());
+        if input_res.clicked() {
+            self.current_annotation = Some(Annotation::text(
+                pos,
+                self.current_color,
+                self.current_font_size,
+            ));
+        }
+    }
+
+    /// Text of the annotation being typed, if it is a text or a callout.
+    fn typed_text(&mut self) -> Option<&mut String> {
+        match self.current_annotation.as_mut() {
+            Some(Annotation::Text(t)) => Some(&mut t.text),
+            Some(Annotation::Callout(c)) => Some(&mut c.text),
+            _ => None,
+        }
+    }
+
+    /// Keyboard input for the text being typed: Enter adds the annotation, Shift+Enter starts a new line.
+    fn manage_typing(&mut self, ui: &mut Ui) {
+        if self.current_annotation.is_none() {
+            return;
+        }
+        let x = ui.input(|s| s.events.clone());
+        for event in &x {
+            match event {
+                Event::Text(text_to_insert) => {
+                    if let Some(text) = self.typed_text() {
+                        text.push_str(text_to_insert);
+                    }
+                }
+                Event::Key {
+                    key: Key::Backspace,
+                    pressed: true,
+                    ..
+                } => {
+                    if let Some(text) = self.typed_text() {
+                        text.pop();
+                    }
+                }
+                Event::Key {
+                    key: Key::Enter,
+                    pressed: true,
+                    modifiers: egui::Modifiers::NONE,
+                    ..
+                } => {
+                    self.add_annotation(self.current_annotation.clone().unwrap());
+                    return;
+                }
+                Event::Key {
+                    key: Key::Enter,
+                    pressed: true,
+                    modifiers: egui::Modifiers::SHIFT,
+                    ..
+                } => {
+                    if let Some(text) = self.typed_text() {
+                        text.push('\n');
                     }
                 }
+                _ => {}
             }
+        }
+    }
+
+    fn manage_redact(&mut self, ui: &mut Ui, to_original: RectTransform) {
+        let input_res = ui.interact(*to_original.from(), ui.id(), Sense::click_and_drag());
+        let Some(input) = input_res.interact_pointer_pos() else {
+            return;
+        };
+
+        let pos = to_original.transform_pos_clamped(input);
+        if input_res.drag_started_by(PointerButton::Primary) {
+            self.current_annotation = Some(Annotation::redact(pos, self.redact_style));
             return;
         }
 
-        let pos = to_original.transform_pos_clamped(input_res.interact_pointer_pos().unwrap());
+        if let Some(Annotation::Redact(ref mut r)) = self.current_annotation.as_mut() {
+            r.update_p2(pos);
+            if input_res.drag_stopped_by(PointerButton::Primary) {
+                if r.get_rect().is_positive() {
+                    self.add_annotation(self.current_annotation.clone().unwrap());
+                } else {
+                    self.current_annotation = None;
+                }
+            }
+        }
+    }
+
+    /// Regions to hide in outgoing frames.
+    pub fn redactions(&self) -> Vec<RedactAnnotation> {
+        self.annotations
+            .iter()
+            .filter_map(|a| match a {
+                Annotation::Redact(r) => Some(r.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn manage_step(&mut self, ui: &mut Ui, to_original: RectTransform) {
+        let input_res = ui.interact(*to_original.from(), ui.id(), Sense::click());
+        let Some(input) = input_res.interact_pointer_pos() else {
+            return;
+        };
         if input_res.clicked() {
-            self.current_annotation = Some(Annotation::text(pos, self.current_color, 32.0));
+            let pos = to_original.transform_pos_clamped(input);
+            self.add_annotation(Annotation::step(
+                pos,
+                self.current_color,
+                self.current_font_size,
+            ));
+        }
+    }
+
+    /// Drag from the point to highlight to where the box goes, then type the text.
+    fn manage_callout(&mut self, ui: &mut Ui, to_original: RectTransform) {
+        let input_res = ui.interact(*to_original.from(), ui.id(), Sense::click_and_drag());
+        let Some(input) = input_res.interact_pointer_pos() else {
+            self.manage_typing(ui);
             return;
+        };
+
+        let pos = to_original.transform_pos_clamped(input);
+        if input_res.drag_started_by(PointerButton::Primary) {
+            self.current_annotation = Some(Annotation::callout(
+                pos,
+                self.current_color,
+                self.current_fill(),
+                self.current_width,
+                self.current_font_size,
+            ));
+            return;
+        }
+        if let Some(Annotation::Callout(ref mut c)) = self.current_annotation.as_mut() {
+            c.update_pos(pos);
         }
     }
 
@@ -534,15 +997,49 @@ impl Editor {
                     ui.painter(),
                 )
             }) {
-                let removed = self.annotations.remove(index);
-                self.add_annotation(Annotation::eraser(removed, index));
+                self.erase(index);
             }
         }
     }
 
+    /// Removes the annotation at `index`, recorded as an eraser entry so it can be undone.
+    fn erase(&mut self, index: usize) {
+        let removed = self.remove_annotation(index);
+        self.add_annotation(Annotation::eraser(removed, index));
+    }
+
     pub fn tool_button(&mut self, ui: &mut Ui, image: &Image<'_>, mode: Mode) -> egui::Response {
         let size_points = egui::Vec2::splat(24.0);
+        let (response, rect, tint) = self.tool_button_frame(ui, size_points, mode);
+        let image = image
+            .clone()
+            .maintain_aspect_ratio(true)
+            .tint(tint)
+            .fit_to_exact_size(size_points);
+        image.paint_at(ui, rect);
+        response
+    }
+
+    /// Like [`Editor::tool_button`], for tools that have no icon and are drawn with a glyph.
+    pub fn glyph_tool_button(&mut self, ui: &mut Ui, glyph: &str, mode: Mode) -> egui::Response {
+        let size_points = egui::Vec2::splat(24.0);
+        let (response, rect, tint) = self.tool_button_frame(ui, size_points, mode);
+        ui.painter().text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            glyph,
+            egui::FontId::proportional(18.0),
+            tint,
+        );
+        response
+    }
 
+    fn tool_button_frame(
+        &mut self,
+        ui: &mut Ui,
+        size_points: Vec2,
+        mode: Mode,
+    ) -> (egui::Response, Rect, Color32) {
         let (id, rect) = ui.allocate_space(size_points);
         let mut response = ui.interact(rect, id, Sense::click());
         let tint = if response.hovered() || self.mode == mode {
@@ -556,14 +1053,9 @@ impl Editor {
         } else {
             ui.visuals().widgets.inactive.fg_stroke.color
         };
-        let image = image
-            .clone()
-            .maintain_aspect_ratio(true)
-            .tint(tint)
-            .fit_to_exact_size(size_points);
-        image.paint_at(ui, rect);
 
         response = response.on_hover_text(match mode {
+            Mode::Callout => "Callout",
             Mode::Crop => "Crop",
             Mode::DrawArrow => "Arrow",
             Mode::DrawCircle => "Circle",
@@ -571,34 +1063,40 @@ impl Editor {
             Mode::DrawLine => "Line",
             Mode::DrawRect => "Rectangle",
             Mode::Erase => "Erase annotation",
+            Mode::FadingInk => "Disappearing ink",
             Mode::Highlight => "Highlighter",
             Mode::Idle => "Cursor",
             Mode::InsertText => "Text",
+            Mode::LaserPointer => "Laser pointer",
+            Mode::Redact => "Redact (pixelate or blur the shared frame)",
             Mode::Redo => "Redo",
+            Mode::Select => "Select, move and resize (Del to delete)",
+            Mode::Step => "Numbered step",
             Mode::Undo => "Undo",
         });
 
         if response.clicked() {
             match mode {
-                Mode::Undo => self.undo(ui),
-                Mode::Redo => self.redo(ui),
+                Mode::Undo => self.undo(ui.ctx()),
+                Mode::Redo => self.redo(ui.ctx()),
                 _ => {
                     if self.mode == mode {
-                        self.mode = Mode::Idle;
+                        self.set_mode(Mode::Idle);
                     } else {
-                        self.mode = mode;
+                        self.set_mode(mode);
                     }
                 }
             }
             self.current_annotation = None;
         }
-        response
+        (response, rect, tint)
     }
 
     pub fn show_tool_buttons(&mut self, ui: &mut Ui) {
         //dark mode
         if ui.visuals().dark_mode {
             self.tool_button(ui, &CURSOR_DARK, Mode::Idle);
+            self.tool_button(ui, &SELECT_DARK, Mode::Select);
 
             self.tool_button(ui, &ARROW_DARK, Mode::DrawArrow);
             self.tool_button(ui, &CIRCLE_DARK, Mode::DrawCircle);
@@ -609,6 +1107,11 @@ impl Editor {
             self.tool_button(ui, &PENCIL_DARK, Mode::DrawFree);
             self.tool_button(ui, &RECTANGLE_DARK, Mode::DrawRect);
             self.tool_button(ui, &TEXT_DARK, Mode::InsertText);
+            self.glyph_tool_button(ui, "🔦", Mode::LaserPointer);
+            self.glyph_tool_button(ui, "✒", Mode::FadingInk);
+            self.glyph_tool_button(ui, "🔢", Mode::Step);
+            self.glyph_tool_button(ui, "💬", Mode::Callout);
+            self.glyph_tool_button(ui, "🔒", Mode::Redact);
             //TODO: render differently
             self.tool_button(ui, &UNDO_DARK, Mode::Undo);
             self.tool_button(ui, &REDO_DARK, Mode::Redo);
@@ -616,6 +1119,7 @@ impl Editor {
         //light mode
         else {
             self.tool_button(ui, &CURSOR, Mode::Idle);
+            self.tool_button(ui, &SELECT, Mode::Select);
 
             self.tool_button(ui, &ARROW, Mode::DrawArrow);
             self.tool_button(ui, &CIRCLE, Mode::DrawCircle);
@@ -626,6 +1130,11 @@ impl Editor {
             self.tool_button(ui, &PENCIL, Mode::DrawFree);
             self.tool_button(ui, &RECTANGLE, Mode::DrawRect);
             self.tool_button(ui, &TEXT, Mode::InsertText);
+            self.glyph_tool_button(ui, "🔦", Mode::LaserPointer);
+            self.glyph_tool_button(ui, "✒", Mode::FadingInk);
+            self.glyph_tool_button(ui, "🔢", Mode::Step);
+            self.glyph_tool_button(ui, "💬", Mode::Callout);
+            self.glyph_tool_button(ui, "🔒", Mode::Redact);
             //TODO: render differently
             self.tool_button(ui, &UNDO, Mode::Undo);
             self.tool_button(ui, &REDO, Mode::Redo);
@@ -677,45 +1186,515 @@ impl Editor {
         ui.add(
             DragValue::new(&mut self.current_width)
                 .speed(0.1)
-                .clamp_range(self.width_range.clone()),
+                .range(self.width_range.clone()),
         )
         .on_hover_text("Width");
         egui::color_picker::color_edit_button_srgba(ui, &mut self.current_color, Alpha::OnlyBlend)
             .on_hover_text("Stroke");
         ui.add_space(ui.spacing().item_spacing.y);
     }
+    pub fn show_font_size_picker(&mut self, ui: &mut Ui) {
+        ui.add_enabled(
+            matches!(self.mode, Mode::InsertText | Mode::Step | Mode::Callout),
+            DragValue::new(&mut self.current_font_size)
+                .speed(0.5)
+                .range(Editor::font_size_range())
+                .suffix(" pt"),
+        )
+        .on_hover_text("Font size")
+        .on_disabled_hover_text("Font size (text, step and callout tools only)");
+    }
+    pub fn show_redact_style(&mut self, ui: &mut Ui) {
+        ui.add_enabled_ui(self.mode == Mode::Redact, |ui| {
+            egui::ComboBox::from_id_source("redact_style")
+                .selected_text(match self.redact_style {
+                    RedactStyle::Pixelate => "Pixelate",
+                    RedactStyle::Blur => "Blur",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.redact_style, RedactStyle::Pixelate, "Pixelate");
+                    ui.selectable_value(&mut self.redact_style, RedactStyle::Blur, "Blur");
+                });
+        });
+    }
+    pub fn show_snap_toggle(&mut self, ui: &mut Ui) {
+        ui.add_enabled(
+            self.mode == Mode::DrawFree,
+            egui::Checkbox::new(&mut self.snap_shapes, "Snap"),
+        )
+        .on_hover_text("Turn rough lines, arrows, circles and rectangles into clean shapes")
+        .on_disabled_hover_text("Shape snapping (pencil tool only)");
+    }
+    /// Swatches of the recently used colors: click to use as stroke, right click to use as fill.
+    pub fn show_recent_colors(&mut self, ui: &mut Ui) {
+        let size = Vec2::splat(ui.spacing().interact_size.y);
+        for color in self.recent_colors.clone() {
+            let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+            ui.painter().rect(
+                rect,
+                Rounding::same(2.0),
+                color,
+                Stroke::new(1.0, ui.visuals().widgets.inactive.bg_stroke.color),
+            );
+            let response = response.on_hover_text("Left click: stroke, right click: fill");
+            if response.clicked() {
+                self.current_color = color;
+            }
+            if response.secondary_clicked() {
+                self.current_fill_color = color;
+                self.fill_type = FillType::Secondary;
+            }
+        }
+    }
 
-    fn undo(&mut self, ui: &mut Ui) {
-        if self.annotations.len() > 0 {
-            let undone = self.annotations.pop().unwrap();
+    pub fn undo(&mut self, ctx: &Context) {
+        self.deselect();
+        if let Some(mut undone) = self.pop_annotation() {
             if let Annotation::Eraser(e) = undone.clone() {
-                self.annotations.insert(e.index, *e.annotation);
+                self.insert_annotation(e.index, *e.annotation);
             }
-            if let Annotation::Crop(_) = undone.clone() {
+            if let Annotation::Edit(ref mut e) = undone {
+                self.swap_edit(e.index, &mut e.annotation);
+            }
+            if let Annotation::Crop(_) = undone {
                 if let Some(Annotation::Crop(old_crop)) = self
                     .annotations
                     .iter()
-                    .filter(|a| matches!(a, Annotation::Crop(_)))
-                    .last()
+                    .rev()
+                    .find(|a| matches!(a, Annotation::Crop(_)))
                 {
-                    self.update_texture(ui, Some(old_crop.get_rect()));
+                    self.update_texture(ctx, Some(old_crop.get_rect()));
                 } else {
-                    self.update_texture(ui, None);
+                    self.update_texture(ctx, None);
                 }
             }
             self.undone_annotations.push(undone);
         }
     }
-    fn redo(&mut self, ui: &mut Ui) {
-        if self.undone_annotations.len() > 0 {
-            let redo = self.undone_annotations.pop().unwrap();
+    pub fn redo(&mut self, ctx: &Context) {
+        self.deselect();
+        if let Some(mut redo) = self.undone_annotations.pop() {
             if let Annotation::Eraser(e) = &redo {
-                self.annotations.remove(e.index);
+                self.remove_annotation(e.index);
+            }
+            if let Annotation::Edit(ref mut e) = redo {
+                self.swap_edit(e.index, &mut e.annotation);
             }
             if let Annotation::Crop(c) = &redo {
-                self.update_texture(ui, Some(c.get_rect()));
+                self.update_texture(ctx, Some(c.get_rect()));
             }
-            self.annotations.push(redo);
+            self.push_annotation(redo);
         }
     }
+
+    /// Exchanges the annotation at `index` with the version stored in an edit entry.
+    fn swap_edit(&mut self, index: usize, other: &mut Annotation) {
+        if let Some(current) = self.annotations.get_mut(index) {
+            std::mem::swap(current, other);
+            self.events.push(AnnotationEvent::Updated {
+                index,
+                annotation: current.clone(),
+            });
+            self.renumber_steps();
+        }
+    }
+
+    /// Records that the annotation at `index` changed from `previous` to its current state.
+    fn commit_edit(&mut self, index: usize, previous: Annotation) {
+        self.events.push(AnnotationEvent::Updated {
+            index,
+            annotation: self.annotations[index].clone(),
+        });
+        self.add_annotation(Annotation::edit(previous, index));
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = Some(index);
+        self.selection_drag = None;
+        self.merge_recolor = false;
+        self.selection_color = self.annotations[index].color();
+        if let Some(color) = self.selection_color {
+            self.current_color = color;
+        }
+    }
+
+    pub fn deselect(&mut self) {
+        self.selected = None;
+        self.selection_drag = None;
+        self.selection_color = None;
+        self.merge_recolor = false;
+    }
+
+    /// Removes the selected annotation, recorded like an eraser so it can be undone.
+    pub fn delete_selected(&mut self) {
+        if let Some(index) = self.selected.take() {
+            self.deselect();
+            self.erase(index);
+        }
+    }
+
+    /// Applies `color` to the selection; consecutive recolors share one history entry.
+    fn recolor_selected(&mut self, color: Color32) {
+        let Some(index) = self.selected else {
+            return;
+        };
+        let previous = self.annotations[index].clone();
+        self.annotations[index].set_color(color);
+        let mergeable = self.merge_recolor
+            && matches!(self.annotations.last(), Some(Annotation::Edit(e)) if e.index == index);
+        if mergeable {
+            self.events.push(AnnotationEvent::Updated {
+                index,
+                annotation: self.annotations[index].clone(),
+            });
+        } else {
+            self.commit_edit(index, previous);
+        }
+        self.selection_color = Some(color);
+        self.merge_recolor = true;
+    }
+
+    fn manage_select(&mut self, ui: &mut Ui, to_original: RectTransform) {
+        let to_screen = to_original.inverse();
+        let scale = to_original.scale()[0];
+        let painter = ui.painter().clone();
+
+        if let Some(index) = self.selected {
+            if index >= self.annotations.len() {
+                self.deselect();
+            }
+        }
+
+        // Recolor through the stroke color picker
+        if self.selected.is_some()
+            && self.selection_color.is_some()
+            && self.selection_color != Some(self.current_color)
+        {
+            self.recolor_selected(self.current_color);
+        }
+
+        // Body: click to select, drag to move
+        let input_res = ui.interact(*to_original.from(), ui.id(), Sense::click_and_drag());
+        if let Some(input) = input_res.interact_pointer_pos() {
+            let pos = to_original.transform_pos_clamped(input);
+            if input_res.clicked() || input_res.drag_started_by(PointerButton::Primary) {
+                let hit = self
+                    .annotations
+                    .iter()
+                    .rposition(|a| a.hit_test(pos, SELECT_TOLERANCE * scale, &painter));
+                match hit {
+                    Some(index) if self.selected != Some(index) => self.select(index),
+                    Some(_) => {}
+                    None => self.deselect(),
+                }
+                if input_res.drag_started_by(PointerButton::Primary) {
+                    if let Some(index) = self.selected {
+                        let bounds = self.annotations[index].bounds(&painter);
+                        self.selection_drag = Some(SelectionDrag {
+                            original: self.annotations[index].clone(),
+                            from: bounds,
+                            to: bounds,
+                            handle: None,
+                        });
+                    }
+                }
+            }
+        }
+        if input_res.dragged_by(PointerButton::Primary) {
+            if let Some(drag) = self.selection_drag.as_mut() {
+                if drag.handle.is_none() {
+                    drag.to = drag.to.translate(input_res.drag_delta() * scale);
+                }
+            }
+        }
+        let mut drag_stopped = input_res.drag_stopped_by(PointerButton::Primary);
+
+        // Handles: drag to resize
+        if let Some(index) = self.selected {
+            let bounds = to_screen.transform_rect(self.annotations[index].bounds(&painter));
+            let size = Vec2::splat(HANDLE_RADIUS * 2.0);
+            for (i, cp) in control_points(bounds).into_iter().enumerate() {
+                let point_rect = Rect::from_center_size(cp.pos, size);
+                let point_response =
+                    ui.interact(point_rect, ui.id().with("select").with(i), Sense::drag());
+                if point_response.drag_started_by(PointerButton::Primary) {
+                    let bounds = self.annotations[index].bounds(&painter);
+                    self.selection_drag = Some(SelectionDrag {
+                        original: self.annotations[index].clone(),
+                        from: bounds,
+                        to: bounds,
+                        handle: Some(cp.label),
+                    });
+                }
+                if point_response.dragged_by(PointerButton::Primary) {
+                    if let Some(drag) = self.selection_drag.as_mut() {
+                        let delta = point_response.drag_delta() * scale;
+                        match cp.label {
+                            Position::LeftTop => drag.to.min += delta,
+                            Position::CenterTop => drag.to.min.y += delta.y,
+                            Position::RightTop => {
+                                drag.to.min.y += delta.y;
+                                drag.to.max.x += delta.x;
+                            }
+                            Position::LeftCenter => drag.to.min.x += delta.x,
+                            Position::RightCenter => drag.to.max.x += delta.x,
+                            Position::LeftBottom => {
+                                drag.to.min.x += delta.x;
+                                drag.to.max.y += delta.y;
+                            }
+                            Position::CenterBottom => drag.to.max.y += delta.y,
+                            Position::RightBottom => drag.to.max += delta,
+                        }
+                    }
+                }
+                drag_stopped |= point_response.drag_stopped_by(PointerButton::Primary);
+            }
+        }
+
+        // Live preview of the move/resize, committed to the history on release
+        if let (Some(index), Some(drag)) = (self.selected, self.selection_drag.as_ref()) {
+            let mut moved = drag.original.clone();
+            match drag.handle {
+                None => moved.translate(drag.to.min - drag.from.min),
+                Some(_) => moved.transform(drag.from, drag.to),
+            }
+            self.annotations[index] = moved;
+        }
+        if drag_stopped {
+            if let (Some(index), Some(drag)) = (self.selected, self.selection_drag.take()) {
+                if drag.from != drag.to {
+                    self.commit_edit(index, drag.original);
+                }
+            }
+        }
+
+        // Keys typed into a text field (toolbar paths, the text tool) are not meant for the selection
+        let delete = !ui.ctx().wants_keyboard_input()
+            && ui.input(|i| i.key_pressed(Key::Delete) || i.key_pressed(Key::Backspace));
+        if delete && self.selection_drag.is_none() {
+            self.delete_selected();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{CentralPanel, Id, Modifiers, RawInput, TextEdit};
+
+    fn editor_with_rects(count: usize) -> Editor {
+        let mut editor = Editor::default();
+        for i in 0..count {
+            let offset = i as f32 * 20.0;
+            let mut rect = Annotation::rect(Pos2::new(offset, offset), Color32::RED, Color32::TRANSPARENT, 2.0);
+            if let Annotation::Rect(ref mut r) = rect {
+                r.update_p2(Pos2::new(offset + 10.0, offset + 10.0));
+            }
+            editor.add_annotation(rect);
+        }
+        editor
+    }
+
+    fn first_corner(editor: &Editor) -> Vec<Pos2> {
+        editor
+            .annotations
+            .iter()
+            .filter_map(|a| match a {
+                Annotation::Rect(r) => Some(r.p1),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn delete_key() -> Event {
+        Event::Key {
+            key: Key::Delete,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    /// Runs one frame of the select tool; with `text_focus` a text field holds the keyboard focus.
+    fn run_select_frame(ctx: &Context, editor: &mut Editor, events: Vec<Event>, text_focus: bool) {
+        let input = RawInput { events, ..Default::default() };
+        let _ = ctx.run(input, |ctx| {
+            CentralPanel::default().show(ctx, |ui| {
+                if text_focus {
+                    let mut path = String::new();
+                    let response = ui.add(TextEdit::singleline(&mut path).id(Id::new("path")));
+                    response.request_focus();
+                }
+                let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.0));
+                editor.manage_select(ui, RectTransform::from_to(rect, rect));
+            });
+        });
+    }
+
+    #[test]
+    fn delete_selected_can_be_undone_and_redone() {
+        let ctx = Context::default();
+        let mut editor = editor_with_rects(3);
+        editor.select(1);
+        editor.delete_selected();
+
+        assert_eq!(editor.selected, None);
+        assert_eq!(first_corner(&editor), vec![Pos2::new(0.0, 0.0), Pos2::new(40.0, 40.0)]);
+
+        editor.undo(&ctx);
+        assert_eq!(
+            first_corner(&editor),
+            vec![Pos2::new(0.0, 0.0), Pos2::new(20.0, 20.0), Pos2::new(40.0, 40.0)]
+        );
+
+        editor.redo(&ctx);
+        assert_eq!(first_corner(&editor), vec![Pos2::new(0.0, 0.0), Pos2::new(40.0, 40.0)]);
+    }
+
+    #[test]
+    fn delete_key_removes_the_selection() {
+        let ctx = Context::default();
+        let mut editor = editor_with_rects(2);
+        editor.select(0);
+        run_select_frame(&ctx, &mut editor, vec![delete_key()], false);
+
+        assert_eq!(first_corner(&editor), vec![Pos2::new(20.0, 20.0)]);
+    }
+
+    #[test]
+    fn delete_key_in_a_text_field_keeps_the_selection() {
+        let ctx = Context::default();
+        let mut editor = editor_with_rects(2);
+        editor.select(0);
+        // The focus requested in the first frame is in place when the key arrives
+        run_select_frame(&ctx, &mut editor, Vec::new(), true);
+        run_select_frame(&ctx, &mut editor, vec![delete_key()], true);
+
+        assert_eq!(editor.selected, Some(0));
+        assert_eq!(first_corner(&editor).len(), 2);
+    }
+
+    /// Three step markers with a callout between the first and the second.
+    fn editor_with_steps() -> Editor {
+        let mut editor = Editor::default();
+        editor.add_annotation(Annotation::step(Pos2::new(10.0, 10.0), Color32::RED, 12.0));
+        editor.add_annotation(Annotation::callout(Pos2::new(50.0, 50.0), Color32::RED, Color32::WHITE, 2.0, 16.0));
+        editor.add_annotation(Annotation::step(Pos2::new(20.0, 20.0), Color32::RED, 12.0));
+        editor.add_annotation(Annotation::step(Pos2::new(30.0, 30.0), Color32::RED, 12.0));
+        editor
+    }
+
+    /// The number and x coordinate of each step marker, in list order.
+    fn steps(annotations: &[Annotation]) -> Vec<(u32, f32)> {
+        annotations
+            .iter()
+            .filter_map(|a| match a {
+                Annotation::Step(s) => Some((s.number, s.center.x)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The events in a compact form: `+index kind`, `-index` or `~index kind`; a step's kind includes its number.
+    fn describe(events: &[AnnotationEvent]) -> Vec<String> {
+        let kind = |annotation: &Annotation| match annotation {
+            Annotation::Step(s) => format!("step {}", s.number),
+            Annotation::Callout(_) => "callout".to_string(),
+            Annotation::Eraser(_) => "eraser".to_string(),
+            other => format!("{:?}", other),
+        };
+        events
+            .iter()
+            .map(|event| match event {
+                AnnotationEvent::Added { index, annotation } => format!("+{} {}", index, kind(annotation)),
+                AnnotationEvent::Removed { index } => format!("-{}", index),
+                AnnotationEvent::Updated { index, annotation } => format!("~{} {}", index, kind(annotation)),
+                other => format!("{:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn steps_are_numbered_in_order() {
+        let mut editor = editor_with_steps();
+        assert_eq!(steps(&editor.annotations), vec![(1, 10.0), (2, 20.0), (3, 30.0)]);
+        assert_eq!(
+            describe(&editor.take_events()),
+            ["+0 step 1", "+1 callout", "+2 step 2", "+3 step 3"]
+        );
+    }
+
+    #[test]
+    fn erasing_a_step_renumbers_the_following_ones() {
+        let ctx = Context::default();
+        let mut editor = editor_with_steps();
+        // Receivers replay the events on their own copy of the list
+        let mut mirror = editor.annotations.clone();
+        editor.take_events();
+
+        editor.erase(2);
+        assert_eq!(steps(&editor.annotations), vec![(1, 10.0), (2, 30.0)]);
+        let events = editor.take_events();
+        assert_eq!(describe(&events), ["-2", "~2 step 2", "+3 eraser"]);
+        events.into_iter().for_each(|event| event.apply(&mut mirror));
+        assert_eq!(steps(&mirror), steps(&editor.annotations));
+
+        editor.undo(&ctx);
+        assert_eq!(steps(&editor.annotations), vec![(1, 10.0), (2, 20.0), (3, 30.0)]);
+        let events = editor.take_events();
+        assert_eq!(describe(&events), ["-3", "+2 step 2", "~3 step 3"]);
+        events.into_iter().for_each(|event| event.apply(&mut mirror));
+        assert_eq!(steps(&mirror), steps(&editor.annotations));
+
+        editor.redo(&ctx);
+        assert_eq!(steps(&editor.annotations), vec![(1, 10.0), (2, 30.0)]);
+        let events = editor.take_events();
+        assert_eq!(describe(&events), ["-2", "~2 step 2", "+3 eraser"]);
+        events.into_iter().for_each(|event| event.apply(&mut mirror));
+        assert_eq!(steps(&mirror), steps(&editor.annotations));
+    }
+
+    #[test]
+    fn erasing_the_first_step_renumbers_all_the_others() {
+        let mut editor = editor_with_steps();
+        editor.take_events();
+        editor.erase(0);
+
+        assert_eq!(steps(&editor.annotations), vec![(1, 20.0), (2, 30.0)]);
+        assert_eq!(describe(&editor.take_events()), ["-0", "~1 step 1", "~2 step 2", "+3 eraser"]);
+    }
+
+    #[test]
+    fn undo_and_redo_of_steps_and_callouts() {
+        let ctx = Context::default();
+        let mut editor = editor_with_steps();
+        editor.take_events();
+
+        // Undoing the last step and the one before leaves the callout in place
+        editor.undo(&ctx);
+        editor.undo(&ctx);
+        assert_eq!(steps(&editor.annotations), vec![(1, 10.0)]);
+        assert!(matches!(editor.annotations.last(), Some(Annotation::Callout(_))));
+        assert_eq!(describe(&editor.take_events()), ["-3", "-2"]);
+
+        editor.undo(&ctx);
+        assert_eq!(editor.annotations.len(), 1);
+        assert_eq!(describe(&editor.take_events()), ["-1"]);
+
+        // Redo restores them in order, with the numbers they had
+        editor.redo(&ctx);
+        editor.redo(&ctx);
+        editor.redo(&ctx);
+        assert_eq!(steps(&editor.annotations), vec![(1, 10.0), (2, 20.0), (3, 30.0)]);
+        assert!(matches!(editor.annotations[1], Annotation::Callout(_)));
+        assert_eq!(describe(&editor.take_events()), ["+1 callout", "+2 step 2", "+3 step 3"]);
+
+        // A new step after an undo takes the next free number and clears the redo history
+        editor.undo(&ctx);
+        editor.add_annotation(Annotation::step(Pos2::new(40.0, 40.0), Color32::RED, 12.0));
+        assert_eq!(steps(&editor.annotations), vec![(1, 10.0), (2, 20.0), (3, 40.0)]);
+        editor.redo(&ctx);
+        assert_eq!(editor.annotations.len(), 4);
+    }
 }
@@ -0,0 +1,318 @@
+use std::time::{Duration, Instant};
+
+use egui::emath::{Pos2, RectTransform};
+use egui::{Color32, Shape, Stroke};
+use serde::{Deserialize, Serialize};
+
+/// How long the laser trail stays visible behind the pointer.
+const LASER_TRAIL: Duration = Duration::from_millis(500);
+/// How long fading ink stays fully visible after the stroke ends, and how long it then takes to vanish.
+const INK_HOLD: Duration = Duration::from_secs(3);
+const INK_FADE: Duration = Duration::from_secs(1);
+
+const LASER_COLOR: Color32 = Color32::from_rgb(255, 40, 40);
+const LASER_WIDTH: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EphemeralKind {
+    Laser,
+    Ink,
+}
+
+/// Live updates to ephemeral strokes. They are streamed like annotation events,
+/// but never enter the annotation list or the undo history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum EphemeralEvent {
+    Begin {
+        id: u64,
+        kind: EphemeralKind,
+        pos: Pos2,
+        color: Color32,
+        width: f32,
+    },
+    Extend {
+        id: u64,
+        pos: Pos2,
+    },
+    End {
+        id: u64,
+    },
+}
+
+struct EphemeralStroke {
+    id: u64,
+    kind: EphemeralKind,
+    points: Vec<(Pos2, Instant)>,
+    color: Color32,
+    width: f32,
+    ended_at: Option<Instant>,
+}
+
+impl EphemeralStroke {
+    /// When the stroke ended or, if its `End` never arrived (e.g. the caster disconnected
+    /// mid-drag), when it last moved. A held stroke is extended every frame, so it stays fresh.
+    fn last_update(&self) -> Instant {
+        self.ended_at
+            .or_else(|| self.points.last().map(|(_, t)| *t))
+            .expect("a stroke starts with one point")
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        let lifetime = match self.kind {
+            EphemeralKind::Laser => LASER_TRAIL,
+            EphemeralKind::Ink => INK_HOLD + INK_FADE,
+        };
+        now.saturating_duration_since(self.last_update()) > lifetime
+    }
+
+    fn render(&self, now: Instant, to_screen: RectTransform) -> Vec<Shape> {
+        let scaling = to_screen.scale()[0];
+        match self.kind {
+            EphemeralKind::Laser => {
+                // Trail: each piece fades and thins out with its age
+                let mut shapes: Vec<Shape> = self
+                    .points
+                    .windows(2)
+                    .filter_map(|w| {
+                        let age = now.duration_since(w[1].1);
+                        if age > LASER_TRAIL {
+                            return None;
+                        }
+                        let life = 1.0 - age.as_secs_f32() / LASER_TRAIL.as_secs_f32();
+                        Some(Shape::line_segment(
+                            [to_screen.transform_pos(w[0].0), to_screen.transform_pos(w[1].0)],
+                            Stroke::new(self.width * scaling * life, self.color.gamma_multiply(life)),
+                        ))
+                    })
+                    .collect();
+                // Glowing dot while the pointer is held down
+                if let (None, Some((last, _))) = (self.ended_at, self.points.last()) {
+                    let center = to_screen.transform_pos(*last);
+                    for (radius, alpha) in [(4.0, 0.15), (2.5, 0.35), (1.5, 1.0)] {
+                        shapes.push(Shape::circle_filled(
+                            center,
+                            self.width * radius,
+                            self.color.gamma_multiply(alpha),
+                        ));
+                    }
+                    shapes.push(Shape::circle_filled(center, self.width * 0.6, Color32::WHITE));
+                }
+                shapes
+            }
+            EphemeralKind::Ink => {
+                let age = now.saturating_duration_since(self.last_update()).saturating_sub(INK_HOLD);
+                let opacity = 1.0 - (age.as_secs_f32() / INK_FADE.as_secs_f32()).min(1.0);
+                let line = self
+                    .points
+                    .iter()
+                    .map(|(p, _)| to_screen.transform_pos(*p))
+                    .collect();
+                vec![Shape::line(
+                    line,
+                    Stroke::new(self.width * scaling, self.color.gamma_multiply(opacity)),
+                )]
+            }
+        }
+    }
+}
+
+/// Laser pointer and disappearing-ink strokes, rendered according to their age.
+#[derive(Default)]
+pub struct EphemeralLayer {
+    strokes: Vec<EphemeralStroke>,
+    next_id: u64,
+}
+
+/// The `begin_*`, `extend` and `end` methods update the layer and return the event
+/// to forward to receivers, which replay it with [`EphemeralLayer::apply`].
+impl EphemeralLayer {
+    pub fn begin_laser(&mut self, pos: Pos2) -> EphemeralEvent {
+        self.begin(EphemeralKind::Laser, pos, LASER_COLOR, LASER_WIDTH)
+    }
+
+    pub fn begin_ink(&mut self, pos: Pos2, color: Color32, width: f32) -> EphemeralEvent {
+        self.begin(EphemeralKind::Ink, pos, color, width)
+    }
+
+    fn begin(&mut self, kind: EphemeralKind, pos: Pos2, color: Color32, width: f32) -> EphemeralEvent {
+        let id = self.next_id;
+        self.next_id += 1;
+        let event = EphemeralEvent::Begin {
+            id,
+            kind,
+            pos,
+            color,
+            width,
+        };
+        self.apply(event.clone());
+        event
+    }
+
+    pub fn extend(&mut self, id: u64, pos: Pos2) -> EphemeralEvent {
+        let event = EphemeralEvent::Extend { id, pos };
+        self.apply(event.clone());
+        event
+    }
+
+    pub fn end(&mut self, id: u64) -> EphemeralEvent {
+        let event = EphemeralEvent::End { id };
+        self.apply(event.clone());
+        event
+    }
+
+    /// Applies an event (generated locally or received from the caster), timestamped on arrival.
+    pub fn apply(&mut self, event: EphemeralEvent) {
+        let now = Instant::now();
+        self.strokes.retain(|s| !s.is_expired(now));
+        match event {
+            EphemeralEvent::Begin {
+                id,
+                kind,
+                pos,
+                color,
+                width,
+            } => self.strokes.push(EphemeralStroke {
+                id,
+                kind,
+                points: vec![(pos, now)],
+                color,
+                width,
+                ended_at: None,
+            }),
+            EphemeralEvent::Extend { id, pos } => {
+                if let Some(stroke) = self.strokes.iter_mut().find(|s| s.id == id) {
+                    if stroke.kind == EphemeralKind::Laser {
+                        stroke.points.retain(|(_, t)| now.duration_since(*t) <= LASER_TRAIL);
+                    }
+                    stroke.points.push((pos, now));
+                }
+            }
+            EphemeralEvent::End { id } => {
+                if let Some(stroke) = self.strokes.iter_mut().find(|s| s.id == id) {
+                    stroke.ended_at = Some(now);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.strokes.clear();
+    }
+
+    /// Whether something is still visible or animating, so the UI should keep repainting.
+    pub fn is_active(&self) -> bool {
+        let now = Instant::now();
+        self.strokes.iter().any(|s| !s.is_expired(now))
+    }
+
+    pub fn render(&self, to_screen: RectTransform) -> Vec<Shape> {
+        let now = Instant::now();
+        self.strokes
+            .iter()
+            .filter(|s| !s.is_expired(now))
+            .flat_map(|s| s.render(now, to_screen))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(kind: EphemeralKind, last_point: Instant, ended_at: Option<Instant>) -> EphemeralStroke {
+        EphemeralStroke {
+            id: 0,
+            kind,
+            points: vec![(Pos2::ZERO, last_point - Duration::from_millis(100)), (Pos2::new(5.0, 5.0), last_point)],
+            color: Color32::RED,
+            width: 2.0,
+            ended_at,
+        }
+    }
+
+    #[test]
+    fn ended_strokes_expire_after_their_lifetime() {
+        let ended = Instant::now();
+        let laser = stroke(EphemeralKind::Laser, ended, Some(ended));
+        assert!(!laser.is_expired(ended + LASER_TRAIL));
+        assert!(laser.is_expired(ended + LASER_TRAIL + Duration::from_millis(1)));
+
+        let ink = stroke(EphemeralKind::Ink, ended, Some(ended));
+        assert!(!ink.is_expired(ended + INK_HOLD));
+        assert!(ink.is_expired(ended + INK_HOLD + INK_FADE + Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn unended_strokes_expire_once_they_stop_moving() {
+        let moved = Instant::now();
+        let laser = stroke(EphemeralKind::Laser, moved, None);
+        assert!(!laser.is_expired(moved + LASER_TRAIL / 2));
+        assert!(laser.is_expired(moved + LASER_TRAIL + Duration::from_millis(1)));
+
+        let ink = stroke(EphemeralKind::Ink, moved, None);
+        assert!(!ink.is_expired(moved + INK_HOLD));
+        assert!(ink.is_expired(moved + INK_HOLD + INK_FADE + Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn abandoned_ink_fades_out() {
+        let moved = Instant::now();
+        let ink = stroke(EphemeralKind::Ink, moved, None);
+        let to_screen = RectTransform::identity(egui::Rect::from_min_size(Pos2::ZERO, egui::vec2(100.0, 100.0)));
+        let alpha = |now| match &ink.render(now, to_screen)[..] {
+            [Shape::Path(path)] => match path.stroke.color {
+                egui::epaint::ColorMode::Solid(color) => color.a(),
+                _ => panic!("expected a solid stroke"),
+            },
+            other => panic!("expected one line, got {:?}", other),
+        };
+        assert_eq!(alpha(moved + INK_HOLD), 255);
+        assert!(alpha(moved + INK_HOLD + INK_FADE / 2) < 255);
+    }
+
+    #[test]
+    fn layer_without_an_end_becomes_inactive() {
+        let mut layer = EphemeralLayer::default();
+        let EphemeralEvent::Begin { id, .. } = layer.begin_laser(Pos2::ZERO) else {
+            panic!("expected a begin event");
+        };
+        assert!(layer.is_active());
+
+        // The caster disconnected without sending `End`
+        layer.strokes.iter_mut().for_each(|s| {
+            s.points.iter_mut().for_each(|(_, t)| *t -= LASER_TRAIL * 2);
+        });
+        assert!(!layer.is_active());
+        assert!(layer.render(RectTransform::identity(egui::Rect::EVERYTHING)).is_empty());
+
+        // The next event drops it
+        layer.apply(EphemeralEvent::End { id });
+        assert!(layer.strokes.is_empty());
+    }
+
+    #[test]
+    fn events_for_unknown_strokes_are_ignored() {
+        let mut layer = EphemeralLayer::default();
+        layer.begin_ink(Pos2::ZERO, Color32::RED, 2.0);
+
+        layer.apply(EphemeralEvent::Extend { id: 42, pos: Pos2::new(1.0, 1.0) });
+        layer.apply(EphemeralEvent::End { id: 42 });
+        assert_eq!(layer.strokes.len(), 1);
+        assert_eq!(layer.strokes[0].points.len(), 1);
+        assert_eq!(layer.strokes[0].ended_at, None);
+    }
+
+    #[test]
+    fn extend_and_end_update_the_stroke() {
+        let mut layer = EphemeralLayer::default();
+        let EphemeralEvent::Begin { id, .. } = layer.begin_ink(Pos2::ZERO, Color32::RED, 2.0) else {
+            panic!("expected a begin event");
+        };
+        layer.extend(id, Pos2::new(3.0, 4.0));
+        layer.end(id);
+
+        assert_eq!(layer.strokes[0].points.len(), 2);
+        assert!(layer.strokes[0].ended_at.is_some());
+        assert!(layer.is_active());
+    }
+}
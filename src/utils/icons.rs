@@ -22,6 +22,7 @@ pub static ref HIGHLIGHT :Image<'static> = Image::new(egui::include_image!("../a
 pub static ref LINE :Image<'static> = Image::new(egui::include_image!("../assets/icons/light/line.png"));
 pub static ref PENCIL :Image<'static> = Image::new(egui::include_image!("../assets/icons/light/pencil.png"));
 pub static ref RECTANGLE :Image<'static> = Image::new(egui::include_image!("../assets/icons/light/rectangle.png"));
+pub static ref SELECT :Image<'static> = Image::new(egui::include_image!("../assets/icons/light/select.png"));
 pub static ref REDO :Image<'static> = Image::new(egui::include_image!("../assets/icons/light/redo.png"));
 pub static ref TEXT :Image<'static> = Image::new(egui::include_image!("../assets/icons/light/text.png"));
 pub static ref UNDO :Image<'static> = Image::new(egui::include_image!("../assets/icons/light/undo.png"));
@@ -38,6 +39,7 @@ pub static ref HIGHLIGHT_DARK :Image<'static> = Image::new(egui::include_image!(
 pub static ref LINE_DARK :Image<'static> = Image::new(egui::include_image!("../assets/icons/dark/line_dark.png"));
 pub static ref PENCIL_DARK :Image<'static> = Image::new(egui::include_image!("../assets/icons/dark/pencil_dark.png"));
 pub static ref RECTANGLE_DARK :Image<'static> = Image::new(egui::include_image!("../assets/icons/dark/rectangle_dark.png"));
+pub static ref SELECT_DARK :Image<'static> = Image::new(egui::include_image!("../assets/icons/dark/select_dark.png"));
 pub static ref REDO_DARK :Image<'static> = Image::new(egui::include_image!("../assets/icons/dark/redo_dark.png"));
 pub static ref TEXT_DARK :Image<'static> = Image::new(egui::include_image!("../assets/icons/dark/text_dark.png"));
 pub static ref UNDO_DARK :Image<'static> = Image::new(egui::include_image!("../assets/icons/dark/undo_dark.png"));
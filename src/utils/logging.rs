@@ -0,0 +1,473 @@
+use std::cell::Cell;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use directories::ProjectDirs;
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+
+/// Environment variable that overrides the verbosity chosen in the settings, with the same
+/// syntax as `RUST_LOG`: a global level and per-target levels, e.g.
+/// `warn,screen_stream_x::core::network=debug`.
+pub const LOG_ENV_VAR: &str = "SCREENSTREAMX_LOG";
+
+/// Target of every log record emitted by this crate.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+const LOG_FILE_NAME: &str = "screen_stream_x.log";
+/// Size after which the log file is rotated.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+/// Rotated files kept next to the current one (`screen_stream_x.1.log` is the most recent).
+const ROTATED_FILES: usize = 2;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+thread_local! {
+    /// Set while this thread holds the log file, so that a panic raised while writing it
+    /// (which the panic hook logs) does not lock the file a second time.
+    static WRITING_FILE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Verbosity of the application's own messages, saved in the settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [LogLevel::Error, LogLevel::Warn, LogLevel::Info, LogLevel::Debug, LogLevel::Trace];
+
+    fn to_filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.to_filter(), f)
+    }
+}
+
+/// Maximum level of each target; the longest matching target prefix wins.
+#[derive(Clone, Debug)]
+struct LogFilter {
+    default: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// `level` for this crate; dependencies (eframe, winit, ...) only report warnings and errors.
+    fn for_app(level: LogLevel) -> Self {
+        let level = level.to_filter();
+        Self { default: level.min(LevelFilter::Warn), targets: vec![(CRATE_TARGET.to_string(), level)] }
+    }
+
+    /// Parses a `RUST_LOG`-style spec. Returns `None` if any directive is invalid.
+    fn parse(spec: &str) -> Option<Self> {
+        let mut filter = Self { default: LevelFilter::Error, targets: Vec::new() };
+        for directive in spec.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => filter.targets.push((target.trim().to_string(), level.trim().parse().ok()?)),
+                None => match directive.parse() {
+                    Ok(level) => filter.default = level,
+                    // A bare target enables everything for it, as in `RUST_LOG`
+                    Err(_) => filter.targets.push((directive.to_string(), LevelFilter::Trace)),
+                },
+            }
+        }
+        Some(filter)
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| {
+                target == prefix || target.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+/// The log file with its current size, rotated when it grows past `MAX_LOG_SIZE`.
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        // A line longer than `MAX_LOG_SIZE` goes into an empty file rather than rotating it away
+        if self.size > 0 && self.size + line.len() as u64 > MAX_LOG_SIZE {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts `name.1.log` to `name.2.log` and so on, dropping the oldest, then starts a new file.
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    path.with_extension(format!("{}.log", index))
+}
+
+struct Logger {
+    filter: RwLock<LogFilter>,
+    /// Set when the filter comes from `LOG_ENV_VAR`, which takes precedence over the settings.
+    from_env: bool,
+    file: Option<Mutex<LogFile>>,
+}
+
+impl Logger {
+    /// Runs `f` on the log file, unless there is none or this thread is already using it.
+    /// A panic while writing poisons the lock, but the file is still usable.
+    fn with_file(&self, f: impl FnOnce(&mut LogFile)) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        if WRITING_FILE.with(Cell::get) {
+            return;
+        }
+
+        struct Writing;
+        impl Drop for Writing {
+            fn drop(&mut self) {
+                WRITING_FILE.with(|writing| writing.set(false));
+            }
+        }
+        WRITING_FILE.with(|writing| writing.set(true));
+        let _writing = Writing;
+        f(&mut file.lock().unwrap_or_else(PoisonError::into_inner));
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.read().unwrap().level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("{} {:<5} {}: {}\n", timestamp(), record.level(), record.target(), record.args());
+        let _ = io::stderr().write_all(line.as_bytes());
+        self.with_file(|file| {
+            if let Err(e) = file.write_line(&line) {
+                let _ = writeln!(io::stderr(), "Failed to write log file: {}", e);
+            }
+        });
+    }
+
+    fn flush(&self) {
+        self.with_file(|file| {
+            let _ = file.file.flush();
+        });
+    }
+}
+
+/// Installs the logger, writing to stderr and to the log file in the data directory.
+/// `level` is used unless `LOG_ENV_VAR` is set. Panics are logged too, so they end up in
+/// the file attached to bug reports. Later calls do nothing.
+pub fn init(level: LogLevel) {
+    if LOGGER.get().is_some() {
+        return;
+    }
+
+    let env_filter = std::env::var(LOG_ENV_VAR).ok().and_then(|spec| {
+        let filter = LogFilter::parse(&spec);
+        if filter.is_none() {
+            eprintln!("Ignoring invalid {}: {}", LOG_ENV_VAR, spec);
+        }
+        filter
+    });
+    let file = log_file_path().and_then(|path| match LogFile::open(path.clone()) {
+        Ok(file) => Some(Mutex::new(file)),
+        Err(e) => {
+            eprintln!("Logging to stderr only, cannot open {}: {}", path.display(), e);
+            None
+        }
+    });
+    let logger = Logger {
+        from_env: env_filter.is_some(),
+        filter: RwLock::new(env_filter.unwrap_or_else(|| LogFilter::for_app(level))),
+        file,
+    };
+    let max_level = logger.filter.read().unwrap().max_level();
+
+    if log::set_logger(LOGGER.get_or_init(|| logger)).is_ok() {
+        log::set_max_level(max_level);
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            log::error!("{}", info);
+            log::logger().flush();
+            default_hook(info);
+        }));
+    }
+}
+
+/// Changes the verbosity at runtime, e.g. from the settings. Ignored when `LOG_ENV_VAR` is set.
+pub fn set_level(level: LogLevel) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    if logger.from_env {
+        return;
+    }
+    let filter = LogFilter::for_app(level);
+    log::set_max_level(filter.max_level());
+    *logger.filter.write().unwrap() = filter;
+}
+
+/// Whether `LOG_ENV_VAR` overrides the level chosen in the settings.
+pub fn is_level_from_env() -> bool {
+    LOGGER.get().is_some_and(|logger| logger.from_env)
+}
+
+/// Path of the current log file (e.g. `~/.local/share/screen_stream_x/logs/screen_stream_x.log` on Linux).
+pub fn log_file_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "screen_stream_x").map(|dirs| dirs.data_dir().join("logs").join(LOG_FILE_NAME))
+}
+
+/// The rotated and current log files concatenated, oldest first, to attach to a bug report.
+pub fn read_diagnostic_log() -> io::Result<String> {
+    let path = log_file_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory available"))?;
+    log::logger().flush();
+
+    let mut log = format!(
+        "{} {} on {} ({})\n",
+        CRATE_TARGET,
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let files = (1..=ROTATED_FILES).rev().map(|index| rotated_path(&path, index)).chain([path.clone()]);
+    for file in files {
+        match fs::read(&file) {
+            Ok(bytes) => log.push_str(&String::from_utf8_lossy(&bytes)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(log)
+}
+
+/// Current UTC time as `YYYY-MM-DD hh:mm:ss.mmm`.
+fn timestamp() -> String {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = elapsed.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let time = seconds % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date in the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::TempDir;
+
+    #[test]
+    fn parses_global_and_per_target_levels() {
+        let filter = LogFilter::parse("warn, screen_stream_x::core=debug ,eframe=off").unwrap();
+        assert_eq!(filter.level_for("winit"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("screen_stream_x::core"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("screen_stream_x::core::network"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("screen_stream_x::app"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("eframe"), LevelFilter::Off);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+    }
+
+    #[test]
+    fn bare_target_enables_everything_for_it() {
+        let filter = LogFilter::parse("screen_stream_x").unwrap();
+        assert_eq!(filter.level_for("screen_stream_x::core::caster"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("eframe"), LevelFilter::Error);
+        assert_eq!(LogFilter::parse("").unwrap().level_for("anything"), LevelFilter::Error);
+    }
+
+    #[test]
+    fn invalid_levels_reject_the_whole_spec() {
+        assert!(LogFilter::parse("screen_stream_x=verbose").is_none());
+        assert!(LogFilter::parse("info,eframe=").is_none());
+        assert!(LogFilter::parse("debug,=,warn").is_none());
+    }
+
+    #[test]
+    fn longest_prefix_wins() {
+        let filter = LogFilter::parse("screen_stream_x::core::network=trace,screen_stream_x=info,screen_stream_x::core=error").unwrap();
+        assert_eq!(filter.level_for("screen_stream_x::core::network::wire"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("screen_stream_x::core::caster"), LevelFilter::Error);
+        assert_eq!(filter.level_for("screen_stream_x::utils"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn prefixes_only_match_at_module_boundaries() {
+        let filter = LogFilter::parse("error,screen_stream_x::core=debug").unwrap();
+        assert_eq!(filter.level_for("screen_stream_x::core_extra"), LevelFilter::Error);
+        assert_eq!(filter.level_for("screen_stream_x::cor"), LevelFilter::Error);
+        assert_eq!(filter.level_for("screen_stream_x"), LevelFilter::Error);
+    }
+
+    #[test]
+    fn app_level_keeps_dependencies_at_warnings() {
+        let filter = LogFilter::for_app(LogLevel::Trace);
+        assert_eq!(filter.level_for(CRATE_TARGET), LevelFilter::Trace);
+        assert_eq!(filter.level_for("winit::event_loop"), LevelFilter::Warn);
+        let filter = LogFilter::for_app(LogLevel::Error);
+        assert_eq!(filter.level_for("winit::event_loop"), LevelFilter::Error);
+    }
+
+    #[test]
+    fn rotation_shifts_files_and_drops_the_oldest() {
+        let dir = TempDir::new("logging_rotation");
+        let path = dir.join(LOG_FILE_NAME);
+        let mut log = LogFile::open(path.clone()).unwrap();
+        for line in ["first\n", "second\n", "third\n"] {
+            log.write_line(line).unwrap();
+            log.rotate().unwrap();
+        }
+        log.write_line("current\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "current\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "third\n");
+        assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "second\n");
+        assert!(!rotated_path(&path, 3).exists());
+        assert_eq!(log.size, "current\n".len() as u64);
+
+        // Reopening continues the current file
+        drop(log);
+        let log = LogFile::open(path).unwrap();
+        assert_eq!(log.size, "current\n".len() as u64);
+    }
+
+    #[test]
+    fn oversized_line_does_not_rotate_an_empty_file() {
+        let dir = TempDir::new("logging_oversized");
+        let path = dir.join(LOG_FILE_NAME);
+        let mut log = LogFile::open(path.clone()).unwrap();
+        let long_line = "x".repeat(MAX_LOG_SIZE as usize + 1);
+
+        log.write_line(&long_line).unwrap();
+        assert!(!rotated_path(&path, 1).exists());
+        log.write_line("next\n").unwrap();
+        assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), long_line);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "next\n");
+    }
+
+    #[test]
+    fn nested_file_access_is_skipped_instead_of_deadlocking() {
+        let dir = TempDir::new("logging_nested");
+        let logger = Logger {
+            filter: RwLock::new(LogFilter::for_app(LogLevel::Info)),
+            from_env: false,
+            file: Some(Mutex::new(LogFile::open(dir.join(LOG_FILE_NAME)).unwrap())),
+        };
+        let mut nested_ran = false;
+        logger.with_file(|file| {
+            file.write_line("outer\n").unwrap();
+            // As the panic hook would do while a line is being written
+            logger.with_file(|_| nested_ran = true);
+        });
+        assert!(!nested_ran);
+
+        // Once the outer write is done the file is available again
+        logger.with_file(|file| file.write_line("after\n").unwrap());
+        assert_eq!(fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap(), "outer\nafter\n");
+    }
+
+    #[test]
+    fn file_survives_a_panic_while_writing() {
+        let dir = TempDir::new("logging_poisoned");
+        let logger = Logger {
+            filter: RwLock::new(LogFilter::for_app(LogLevel::Info)),
+            from_env: false,
+            file: Some(Mutex::new(LogFile::open(dir.join(LOG_FILE_NAME)).unwrap())),
+        };
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            logger.with_file(|_| panic!("panic while writing the log"));
+        }));
+        assert!(panicked.is_err());
+
+        logger.with_file(|file| file.write_line("still logging\n").unwrap());
+        assert_eq!(fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap(), "still logging\n");
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+        // 2000 is a leap year, 1900 and 2100 are not
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(-25_508), (1900, 3, 1));
+        assert_eq!(civil_from_days(47_541), (2100, 3, 1));
+        assert_eq!(civil_from_days(19_722), (2023, 12, 31));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+    }
+}
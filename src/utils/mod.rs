@@ -1,4 +1,14 @@
 pub mod annotations;
 pub mod multi_monitor;
 pub mod editor;
-pub mod icons;
\ No newline at end of file
+pub mod ephemeral;
+pub mod icons;
+pub mod logging;
+pub mod redaction;
+pub mod session;
+#[cfg(feature = "gui")]
+pub mod snapshot;
+pub mod stroke;
+pub mod svg;
+#[cfg(test)]
+pub mod test_support;
\ No newline at end of file
@@ -1,6 +1,62 @@
 // src/utils/multi_monitor.rs
 use scrap::Display;
 
+/// Monitors that can be captured; empty (with a warning in the log) if they cannot be listed.
 pub fn get_available_monitors() -> Vec<Display> {
-    Display::all().expect("Failed to get displays")
-}
\ No newline at end of file
+    Display::all().unwrap_or_else(|e| {
+        log::warn!("Failed to list monitors: {}", e);
+        Vec::new()
+    })
+}
+
+/// The monitor captured for `display`: the primary one if `None`, otherwise the index in `Display::all()`.
+fn open(display: Option<usize>) -> Option<Display> {
+    match display {
+        Some(index) => get_available_monitors().into_iter().nth(index),
+        None => Display::primary().ok(),
+    }
+}
+
+/// Size of the monitor captured for `display`, `None` if it is not connected.
+pub fn monitor_size(display: Option<usize>) -> Option<(usize, usize)> {
+    open(display).map(|display| (display.width(), display.height()))
+}
+
+/// Identifies the monitor captured for `display` (as in `CaptureSource::display`) for the settings
+/// stored per monitor; empty if the monitor is not connected. See `monitor_key`.
+pub fn monitor_id(display: Option<usize>) -> String {
+    monitor_size(display).map_or_else(String::new, |(width, height)| monitor_key(display, width, height))
+}
+
+/// scrap does not expose stable monitor names, so a monitor is told apart by its index in
+/// `Display::all()` and its resolution: two monitors with the same resolution get different keys,
+/// and a resolution change on the same monitor starts a new set of settings. The primary
+/// monitor has its own key, since its index is not known.
+fn monitor_key(display: Option<usize>, width: usize, height: usize) -> String {
+    match display {
+        Some(index) => format!("{}:{}x{}", index, width, height),
+        None => format!("primary:{}x{}", width, height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitors_with_the_same_resolution_have_different_keys() {
+        let first = monitor_key(Some(0), 1920, 1080);
+        let second = monitor_key(Some(1), 1920, 1080);
+        let primary = monitor_key(None, 1920, 1080);
+        assert_ne!(first, second);
+        assert_ne!(first, primary);
+        assert_ne!(second, primary);
+    }
+
+    #[test]
+    fn keys_depend_on_the_resolution() {
+        assert_eq!(monitor_key(Some(1), 2560, 1440), "1:2560x1440");
+        assert_eq!(monitor_key(None, 1366, 768), "primary:1366x768");
+        assert_ne!(monitor_key(Some(1), 2560, 1440), monitor_key(Some(1), 1920, 1080));
+    }
+}
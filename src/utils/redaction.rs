@@ -0,0 +1,308 @@
+use egui::{Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::annotations::{RedactAnnotation, RedactStyle};
+
+/// Side of the blocks of a pixelated region, in capture pixels.
+const PIXELATE_BLOCK: usize = 16;
+/// Standard deviation of the Gaussian blur, in capture pixels.
+const BLUR_SIGMA: f32 = 10.0;
+
+/// How a privacy mask hides the screen under it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaskStyle {
+    Solid,
+    Blur,
+}
+
+/// A region of a monitor that is never shared, in monitor pixels.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PrivacyMask {
+    pub rect: Rect,
+    pub style: MaskStyle,
+}
+
+/// Pixelates or blurs the redacted regions of a tightly packed RGBA frame, in place.
+pub fn redact_rgba(data: &mut [u8], width: usize, height: usize, regions: &[RedactAnnotation]) {
+    for region in regions {
+        let Some(bounds) = pixel_bounds(region.get_rect(), width, height) else {
+            continue;
+        };
+        match region.style {
+            RedactStyle::Pixelate => pixelate(data, width, bounds),
+            RedactStyle::Blur => blur(data, width, bounds),
+        }
+    }
+}
+
+/// Hides the privacy masks in a tightly packed RGBA frame, in place. `origin` is the position
+/// of the frame on the monitor, so masks stay pinned to the screen whatever the capture area.
+pub fn mask_rgba(data: &mut [u8], width: usize, height: usize, origin: Pos2, masks: &[PrivacyMask]) {
+    for mask in masks {
+        let Some(bounds) = pixel_bounds(mask.rect.translate(-origin.to_vec2()), width, height) else {
+            continue;
+        };
+        match mask.style {
+            MaskStyle::Solid => fill(data, width, bounds, [0, 0, 0, 255]),
+            MaskStyle::Blur => blur(data, width, bounds),
+        }
+    }
+}
+
+/// The pixels covered by `rect` inside a `width` x `height` frame, as `[x0, y0, x1, y1]`.
+fn pixel_bounds(rect: Rect, width: usize, height: usize) -> Option<[usize; 4]> {
+    let frame = Rect::from_min_size(Pos2::ZERO, Vec2::new(width as f32, height as f32));
+    let rect = rect.intersect(frame);
+    if !rect.is_positive() {
+        return None;
+    }
+    Some([
+        rect.min.x.floor() as usize,
+        rect.min.y.floor() as usize,
+        (rect.max.x.ceil() as usize).min(width),
+        (rect.max.y.ceil() as usize).min(height),
+    ])
+}
+
+fn fill(data: &mut [u8], width: usize, [x0, y0, x1, y1]: [usize; 4], color: [u8; 4]) {
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let i = (y * width + x) * 4;
+            data[i..i + 4].copy_from_slice(&color);
+        }
+    }
+}
+
+/// Fills each block of the region with its average color.
+fn pixelate(data: &mut [u8], width: usize, [x0, y0, x1, y1]: [usize; 4]) {
+    for by in (y0..y1).step_by(PIXELATE_BLOCK) {
+        for bx in (x0..x1).step_by(PIXELATE_BLOCK) {
+            let (ex, ey) = ((bx + PIXELATE_BLOCK).min(x1), (by + PIXELATE_BLOCK).min(y1));
+            let mut sum = [0u32; 4];
+            for y in by..ey {
+                for x in bx..ex {
+                    let i = (y * width + x) * 4;
+                    for c in 0..4 {
+                        sum[c] += data[i + c] as u32;
+                    }
+                }
+            }
+            let count = ((ex - bx) * (ey - by)) as u32;
+            let average = sum.map(|s| (s / count) as u8);
+            for y in by..ey {
+                for x in bx..ex {
+                    let i = (y * width + x) * 4;
+                    data[i..i + 4].copy_from_slice(&average);
+                }
+            }
+        }
+    }
+}
+
+/// Separable Gaussian blur of the region. Samples outside the region are clamped to its
+/// edge, so pixels around it never leak in and nothing inside leaks out.
+fn blur(data: &mut [u8], width: usize, [x0, y0, x1, y1]: [usize; 4]) {
+    let radius = (BLUR_SIGMA * 3.0).ceil() as isize;
+    let kernel: Vec<f32> = (-radius..=radius)
+        .map(|d| (-(d * d) as f32 / (2.0 * BLUR_SIGMA * BLUR_SIGMA)).exp())
+        .collect();
+    let total: f32 = kernel.iter().sum();
+    let (w, h) = (x1 - x0, y1 - y0);
+
+    let mut region: Vec<[f32; 4]> = Vec::with_capacity(w * h);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let i = (y * width + x) * 4;
+            region.push([0, 1, 2, 3].map(|c| data[i + c] as f32));
+        }
+    }
+
+    let pass = |source: &[[f32; 4]], horizontal: bool| -> Vec<[f32; 4]> {
+        let mut out = vec![[0.0; 4]; w * h];
+        for y in 0..h {
+            for x in 0..w {
+                let mut acc = [0.0; 4];
+                for (k, weight) in kernel.iter().enumerate() {
+                    let d = k as isize - radius;
+                    let (sx, sy) = if horizontal {
+                        ((x as isize + d).clamp(0, w as isize - 1) as usize, y)
+                    } else {
+                        (x, (y as isize + d).clamp(0, h as isize - 1) as usize)
+                    };
+                    let sample = source[sy * w + sx];
+                    for c in 0..4 {
+                        acc[c] += sample[c] * weight;
+                    }
+                }
+                out[y * w + x] = acc.map(|v| v / total);
+            }
+        }
+        out
+    };
+    let blurred = pass(&pass(&region, true), false);
+
+    for y in 0..h {
+        for x in 0..w {
+            let i = ((y0 + y) * width + x0 + x) * 4;
+            for c in 0..4 {
+                data[i + c] = blurred[y * w + x][c].round() as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A frame whose pixels are all different, so any change is visible.
+    fn gradient(width: usize, height: usize) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| [(i * 7 % 256) as u8, (i * 13 % 256) as u8, (i * 29 % 256) as u8, 255])
+            .collect()
+    }
+
+    fn pixel(data: &[u8], width: usize, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * width + x) * 4;
+        data[i..i + 4].try_into().unwrap()
+    }
+
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Rect {
+        Rect::from_min_max(Pos2::new(x0, y0), Pos2::new(x1, y1))
+    }
+
+    fn region(rect: Rect, style: RedactStyle) -> RedactAnnotation {
+        RedactAnnotation { p1: rect.min, p2: rect.max, style }
+    }
+
+    /// Pixels that differ between the two frames, as `(x, y)`.
+    fn changed(before: &[u8], after: &[u8], width: usize) -> Vec<(usize, usize)> {
+        before
+            .chunks_exact(4)
+            .zip(after.chunks_exact(4))
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, _)| (i % width, i / width))
+            .collect()
+    }
+
+    #[test]
+    fn bounds_are_clipped_to_the_frame() {
+        assert_eq!(pixel_bounds(rect(-10.0, -5.0, 20.0, 30.0), 64, 48), Some([0, 0, 20, 30]));
+        assert_eq!(pixel_bounds(rect(50.0, 40.0, 100.0, 100.0), 64, 48), Some([50, 40, 64, 48]));
+        assert_eq!(pixel_bounds(rect(-100.0, -100.0, 200.0, 200.0), 64, 48), Some([0, 0, 64, 48]));
+        assert_eq!(pixel_bounds(rect(70.0, 10.0, 90.0, 20.0), 64, 48), None);
+        assert_eq!(pixel_bounds(rect(-30.0, -30.0, -1.0, -1.0), 64, 48), None);
+        assert_eq!(pixel_bounds(rect(10.0, 10.0, 10.0, 20.0), 64, 48), None);
+    }
+
+    #[test]
+    fn fractional_edges_cover_every_touched_pixel() {
+        assert_eq!(pixel_bounds(rect(2.5, 3.2, 7.1, 8.9), 64, 48), Some([2, 3, 8, 9]));
+        assert_eq!(pixel_bounds(rect(0.1, 0.1, 0.2, 0.2), 64, 48), Some([0, 0, 1, 1]));
+        assert_eq!(pixel_bounds(rect(63.5, 47.5, 64.0, 48.0), 64, 48), Some([63, 47, 64, 48]));
+    }
+
+    #[test]
+    fn regions_outside_the_frame_change_nothing() {
+        let (width, height) = (32, 24);
+        let before = gradient(width, height);
+        let mut data = before.clone();
+        let regions = [
+            region(rect(40.0, 0.0, 60.0, 10.0), RedactStyle::Pixelate),
+            region(rect(-20.0, -20.0, -2.0, -2.0), RedactStyle::Blur),
+        ];
+        redact_rgba(&mut data, width, height, &regions);
+        assert_eq!(data, before);
+    }
+
+    #[test]
+    fn partially_outside_region_is_redacted_inside_the_frame() {
+        let (width, height) = (32, 24);
+        let before = gradient(width, height);
+        let mut data = before.clone();
+        redact_rgba(&mut data, width, height, &[region(rect(-8.0, 16.0, 8.0, 40.0), RedactStyle::Pixelate)]);
+
+        let changed = changed(&before, &data, width);
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|&(x, y)| x < 8 && y >= 16));
+    }
+
+    #[test]
+    fn pixelate_handles_partial_blocks() {
+        // 20x19 is not a multiple of the 16 pixel block: the blocks on the right and bottom edges are smaller
+        let (width, height) = (40, 30);
+        let before = gradient(width, height);
+        let mut data = before.clone();
+        let bounds = [3, 5, 23, 24];
+        pixelate(&mut data, width, bounds);
+
+        for (bx, ex) in [(3, 19), (19, 23)] {
+            for (by, ey) in [(5, 21), (21, 24)] {
+                let mut sum = [0u32; 4];
+                for y in by..ey {
+                    for x in bx..ex {
+                        let p = pixel(&before, width, x, y);
+                        (0..4).for_each(|c| sum[c] += p[c] as u32);
+                    }
+                }
+                let count = ((ex - bx) * (ey - by)) as u32;
+                let average = sum.map(|s| (s / count) as u8);
+                for y in by..ey {
+                    for x in bx..ex {
+                        assert_eq!(pixel(&data, width, x, y), average, "block at {},{}", bx, by);
+                    }
+                }
+            }
+        }
+        assert!(changed(&before, &data, width).iter().all(|&(x, y)| (3..23).contains(&x) && (5..24).contains(&y)));
+    }
+
+    #[test]
+    fn blur_leaves_pixels_outside_the_region_untouched() {
+        let (width, height) = (48, 40);
+        let before = gradient(width, height);
+        let mut data = before.clone();
+        redact_rgba(&mut data, width, height, &[region(rect(10.5, 8.0, 30.0, 25.5), RedactStyle::Blur)]);
+
+        let changed = changed(&before, &data, width);
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|&(x, y)| (10..30).contains(&x) && (8..26).contains(&y)));
+    }
+
+    #[test]
+    fn blur_of_a_flat_region_keeps_its_color() {
+        let (width, height) = (20, 20);
+        let mut data = [90, 120, 200, 255].repeat(width * height);
+        blur(&mut data, width, [2, 2, 18, 18]);
+        assert!(data.chunks_exact(4).all(|p| p == [90, 120, 200, 255]));
+    }
+
+    #[test]
+    fn solid_mask_follows_the_capture_origin() {
+        // The frame is a capture area at 100,50 on the monitor; the mask is in monitor pixels
+        let (width, height) = (32, 24);
+        let before = gradient(width, height);
+        let mut data = before.clone();
+        let mask = PrivacyMask { rect: rect(110.0, 55.0, 120.0, 60.0), style: MaskStyle::Solid };
+        mask_rgba(&mut data, width, height, Pos2::new(100.0, 50.0), &[mask]);
+
+        for y in 0..height {
+            for x in 0..width {
+                let inside = (10..20).contains(&x) && (5..10).contains(&y);
+                let expected = if inside { [0, 0, 0, 255] } else { pixel(&before, width, x, y) };
+                assert_eq!(pixel(&data, width, x, y), expected, "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn mask_outside_the_capture_area_changes_nothing() {
+        let (width, height) = (32, 24);
+        let before = gradient(width, height);
+        let mut data = before.clone();
+        let mask = PrivacyMask { rect: rect(0.0, 0.0, 100.0, 50.0), style: MaskStyle::Solid };
+        mask_rgba(&mut data, width, height, Pos2::new(100.0, 50.0), &[mask]);
+        assert_eq!(data, before);
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use egui::{Pos2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::annotations::Annotation;
+use crate::utils::editor::{Editor, Mode, ToolStyle};
+
+/// Version of the session format written by this build.
+pub const SESSION_VERSION: u32 = 1;
+
+/// Annotations drawn over a capture, saved to reuse them later or on another machine.
+/// Coordinates are in capture pixels, so `resolution` tells which space they belong to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotationSession {
+    pub version: u32,
+    pub resolution: [usize; 2],
+    pub annotations: Vec<Annotation>,
+    pub tool_styles: HashMap<Mode, ToolStyle>,
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u32),
+    /// No frame has been captured yet, so there is no coordinate space to save or compare with.
+    NoCapture,
+    ResolutionMismatch {
+        file: [usize; 2],
+        current: [usize; 2],
+    },
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Io(e) => write!(f, "I/O error: {}", e),
+            SessionError::Format(e) => write!(f, "invalid session file: {}", e),
+            SessionError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported session version {} (this build reads up to {})",
+                v, SESSION_VERSION
+            ),
+            SessionError::NoCapture => write!(f, "no frame captured yet"),
+            SessionError::ResolutionMismatch { file, current } => write!(
+                f,
+                "session was drawn at {}x{}, current capture is {}x{}",
+                file[0], file[1], current[0], current[1]
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<io::Error> for SessionError {
+    fn from(e: io::Error) -> Self {
+        SessionError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SessionError {
+    fn from(e: serde_json::Error) -> Self {
+        SessionError::Format(e)
+    }
+}
+
+impl AnnotationSession {
+    /// Takes the visible annotations and tool styles of the editor. Undo history is not saved.
+    pub fn from_editor(editor: &Editor) -> Result<Self, SessionError> {
+        let resolution = editor.capture_size().ok_or(SessionError::NoCapture)?;
+        Ok(Self {
+            version: SESSION_VERSION,
+            resolution,
+            annotations: editor
+                .annotations
+                .iter()
+                .filter(|a| a.is_drawing())
+                .cloned()
+                .collect(),
+            tool_styles: editor.all_tool_styles(),
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SessionError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SessionError> {
+        let session: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if session.version > SESSION_VERSION {
+            return Err(SessionError::UnsupportedVersion(session.version));
+        }
+        Ok(session)
+    }
+
+    /// Checks that the session matches the capture resolution. With `rescale`, annotations
+    /// drawn at another resolution are stretched onto the current one instead of rejected.
+    pub fn fit_to(&mut self, current: [usize; 2], rescale: bool) -> Result<(), SessionError> {
+        if self.resolution == current {
+            return Ok(());
+        }
+        if !rescale {
+            return Err(SessionError::ResolutionMismatch {
+                file: self.resolution,
+                current,
+            });
+        }
+        let from = Rect::from_min_size(
+            Pos2::ZERO,
+            Vec2::new(self.resolution[0] as f32, self.resolution[1] as f32),
+        );
+        let to = Rect::from_min_size(Pos2::ZERO, Vec2::new(current[0] as f32, current[1] as f32));
+        self.annotations.iter_mut().for_each(|a| a.transform(from, to));
+        self.resolution = current;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::TempDir;
+    use egui::{Color32, ColorImage};
+
+    fn rect_session(resolution: [usize; 2]) -> AnnotationSession {
+        let mut rect = Annotation::rect(Pos2::new(100.0, 50.0), Color32::RED, Color32::TRANSPARENT, 3.0);
+        if let Annotation::Rect(r) = &mut rect {
+            r.update_p2(Pos2::new(300.0, 150.0));
+        }
+        AnnotationSession {
+            version: SESSION_VERSION,
+            resolution,
+            annotations: vec![rect],
+            tool_styles: HashMap::new(),
+        }
+    }
+
+    fn rect_corners(session: &AnnotationSession) -> (Pos2, Pos2) {
+        match &session.annotations[0] {
+            Annotation::Rect(r) => (r.p1, r.p2),
+            other => panic!("expected a rectangle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut editor = Editor::default();
+        *editor.captured_image.lock().unwrap() = Some(ColorImage::new([640, 480], Color32::BLACK));
+        editor.annotations = rect_session([640, 480]).annotations;
+        editor.set_mode(Mode::DrawRect);
+        editor.current_color = Color32::GREEN;
+
+        let dir = TempDir::new("session_round_trip");
+        let file = dir.join("annotations.json");
+        AnnotationSession::from_editor(&editor).unwrap().save(&file).unwrap();
+        let loaded = AnnotationSession::load(&file).unwrap();
+
+        assert_eq!(loaded.version, SESSION_VERSION);
+        assert_eq!(loaded.resolution, [640, 480]);
+        assert_eq!(rect_corners(&loaded), (Pos2::new(100.0, 50.0), Pos2::new(300.0, 150.0)));
+        assert_eq!(loaded.tool_styles[&Mode::DrawRect].color, Color32::GREEN);
+    }
+
+    #[test]
+    fn session_without_capture_cannot_be_saved() {
+        assert!(matches!(
+            AnnotationSession::from_editor(&Editor::default()),
+            Err(SessionError::NoCapture)
+        ));
+    }
+
+    #[test]
+    fn future_version_is_rejected() {
+        let dir = TempDir::new("session_future_version");
+        let file = dir.join("annotations.json");
+        AnnotationSession { version: SESSION_VERSION + 1, ..rect_session([640, 480]) }
+            .save(&file)
+            .unwrap();
+
+        match AnnotationSession::load(&file) {
+            Err(SessionError::UnsupportedVersion(version)) => assert_eq!(version, SESSION_VERSION + 1),
+            other => panic!("expected an unsupported version, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mismatched_resolution_is_rejected() {
+        let mut session = rect_session([640, 480]);
+        match session.fit_to([1280, 720], false) {
+            Err(SessionError::ResolutionMismatch { file, current }) => {
+                assert_eq!(file, [640, 480]);
+                assert_eq!(current, [1280, 720]);
+            }
+            other => panic!("expected a resolution mismatch, got {:?}", other),
+        }
+        assert_eq!(session.resolution, [640, 480]);
+        assert_eq!(rect_corners(&session), (Pos2::new(100.0, 50.0), Pos2::new(300.0, 150.0)));
+    }
+
+    #[test]
+    fn rescale_maps_annotations_onto_the_current_resolution() {
+        let mut session = rect_session([640, 480]);
+        session.fit_to([1280, 960], true).unwrap();
+
+        assert_eq!(session.resolution, [1280, 960]);
+        assert_eq!(rect_corners(&session), (Pos2::new(200.0, 100.0), Pos2::new(600.0, 300.0)));
+    }
+
+    #[test]
+    fn same_resolution_fits_without_rescale() {
+        let mut session = rect_session([640, 480]);
+        session.fit_to([640, 480], false).unwrap();
+        assert_eq!(rect_corners(&session), (Pos2::new(100.0, 50.0), Pos2::new(300.0, 150.0)));
+    }
+}
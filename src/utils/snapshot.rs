@@ -0,0 +1,237 @@
+use std::fmt;
+use std::path::Path;
+
+use egui::emath::RectTransform;
+use egui::epaint::{ClippedShape, Primitive, Vertex};
+use egui::{Color32, ColorImage, Context, LayerId, Painter, Pos2, Rect, TextureId, Vec2};
+use image::{imageops, DynamicImage, ImageFormat, RgbaImage};
+
+use crate::utils::annotations::{active_crop, Annotation};
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// No frame has been captured or received yet.
+    NoFrame,
+    UnsupportedFormat(String),
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Clipboard(arboard::Error),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::NoFrame => write!(f, "no frame available yet"),
+            SnapshotError::UnsupportedFormat(path) => {
+                write!(f, "unsupported image format for {} (use .png or .jpg)", path)
+            }
+            SnapshotError::Io(e) => write!(f, "I/O error: {}", e),
+            SnapshotError::Image(e) => write!(f, "image error: {}", e),
+            SnapshotError::Clipboard(e) => write!(f, "clipboard error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(e: std::io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for SnapshotError {
+    fn from(e: image::ImageError) -> Self {
+        SnapshotError::Image(e)
+    }
+}
+
+impl From<arboard::Error> for SnapshotError {
+    fn from(e: arboard::Error) -> Self {
+        SnapshotError::Clipboard(e)
+    }
+}
+
+/// Draws the annotations over the frame at full capture resolution, then applies the last crop.
+///
+/// Annotations are tessellated by egui exactly as on screen, and the resulting triangles are
+/// filled in software, so strokes, fills and text look the same as in the preview.
+pub fn compose(ctx: &Context, frame: &ColorImage, annotations: &[Annotation]) -> RgbaImage {
+    let full = Rect::from_min_size(
+        Pos2::ZERO,
+        Vec2::new(frame.width() as f32, frame.height() as f32),
+    );
+    let identity = RectTransform::from_to(full, full);
+    let painter = Painter::new(ctx.clone(), LayerId::background(), full);
+    let shapes = annotations
+        .iter()
+        .filter(|a| !matches!(a, Annotation::Crop(_)))
+        .map(|a| ClippedShape {
+            clip_rect: full,
+            shape: a.render(1.0, identity, &painter, false),
+        })
+        .collect();
+    let primitives = ctx.tessellate(shapes, ctx.pixels_per_point());
+
+    let font_image = ctx.fonts(|fonts| fonts.image());
+    // Same coverage-to-alpha mapping egui uses when uploading the font texture
+    let coverage: Vec<f32> = font_image.pixels.iter().map(|c| c.powf(0.55)).collect();
+    let atlas = Atlas {
+        size: font_image.size,
+        coverage: &coverage,
+    };
+
+    let mut canvas = Canvas {
+        width: frame.width(),
+        height: frame.height(),
+        pixels: frame.pixels.iter().map(|c| c.to_array().map(f32::from)).collect(),
+    };
+    for clipped in primitives {
+        let Primitive::Mesh(mesh) = clipped.primitive else {
+            continue;
+        };
+        if mesh.texture_id != TextureId::default() {
+            continue;
+        }
+        for triangle in mesh.indices.chunks_exact(3) {
+            canvas.fill_triangle(
+                [
+                    &mesh.vertices[triangle[0] as usize],
+                    &mesh.vertices[triangle[1] as usize],
+                    &mesh.vertices[triangle[2] as usize],
+                ],
+                clipped.clip_rect,
+                &atlas,
+            );
+        }
+    }
+
+    let image = canvas.into_image();
+    match active_crop(annotations, full) {
+        Some(crop) => imageops::crop_imm(
+            &image,
+            crop.min.x as u32,
+            crop.min.y as u32,
+            crop.width() as u32,
+            crop.height() as u32,
+        )
+        .to_image(),
+        None => image,
+    }
+}
+
+/// Saves as PNG or JPEG, depending on the file extension.
+pub fn save(image: &RgbaImage, path: &Path) -> Result<(), SnapshotError> {
+    match ImageFormat::from_path(path) {
+        Ok(ImageFormat::Png) => image.save_with_format(path, ImageFormat::Png)?,
+        // JPEG has no alpha channel
+        Ok(ImageFormat::Jpeg) => DynamicImage::ImageRgba8(image.clone())
+            .to_rgb8()
+            .save_with_format(path, ImageFormat::Jpeg)?,
+        _ => return Err(SnapshotError::UnsupportedFormat(path.display().to_string())),
+    }
+    Ok(())
+}
+
+pub fn copy_to_clipboard(image: &RgbaImage) -> Result<(), SnapshotError> {
+    arboard::Clipboard::new()?.set_image(arboard::ImageData {
+        width: image.width() as usize,
+        height: image.height() as usize,
+        bytes: image.as_raw().into(),
+    })?;
+    Ok(())
+}
+
+struct Atlas<'a> {
+    size: [usize; 2],
+    coverage: &'a [f32],
+}
+
+impl Atlas<'_> {
+    fn sample(&self, uv: Pos2) -> f32 {
+        let x = ((uv.x * self.size[0] as f32) as usize).min(self.size[0] - 1);
+        let y = ((uv.y * self.size[1] as f32) as usize).min(self.size[1] - 1);
+        self.coverage[y * self.size[0] + x]
+    }
+}
+
+/// Premultiplied RGBA pixels, kept as floats while blending.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Canvas {
+    fn fill_triangle(&mut self, [a, b, c]: [&Vertex; 3], clip: Rect, atlas: &Atlas) {
+        let area = edge(a.pos, b.pos, c.pos);
+        if area.abs() < f32::EPSILON {
+            return;
+        }
+        let bounds = Rect::from_points(&[a.pos, b.pos, c.pos])
+            .intersect(clip)
+            .intersect(Rect::from_min_size(
+                Pos2::ZERO,
+                Vec2::new(self.width as f32, self.height as f32),
+            ));
+        if !bounds.is_positive() {
+            return;
+        }
+
+        for y in bounds.min.y.floor() as usize..bounds.max.y.ceil() as usize {
+            for x in bounds.min.x.floor() as usize..bounds.max.x.ceil() as usize {
+                let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let wa = edge(b.pos, c.pos, p) / area;
+                let wb = edge(c.pos, a.pos, p) / area;
+                let wc = 1.0 - wa - wb;
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let uv = Pos2::new(
+                    wa * a.uv.x + wb * b.uv.x + wc * c.uv.x,
+                    wa * a.uv.y + wb * b.uv.y + wc * c.uv.y,
+                );
+                let alpha = atlas.sample(uv);
+                let src = interpolate([a.color, b.color, c.color], [wa, wb, wc]).map(|v| v * alpha);
+                let dst = &mut self.pixels[y * self.width + x];
+                let keep = 1.0 - src[3] / 255.0;
+                for i in 0..4 {
+                    dst[i] = src[i] + dst[i] * keep;
+                }
+            }
+        }
+    }
+
+    fn into_image(self) -> RgbaImage {
+        let bytes = self
+            .pixels
+            .iter()
+            .flat_map(|p| {
+                let color = Color32::from_rgba_premultiplied(
+                    p[0].round() as u8,
+                    p[1].round() as u8,
+                    p[2].round() as u8,
+                    p[3].round() as u8,
+                );
+                color.to_srgba_unmultiplied()
+            })
+            .collect();
+        RgbaImage::from_raw(self.width as u32, self.height as u32, bytes)
+            .expect("canvas size matches its pixels")
+    }
+}
+
+/// Twice the signed area of the triangle `a b p`.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn interpolate(colors: [Color32; 3], weights: [f32; 3]) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    for (color, weight) in colors.iter().zip(weights) {
+        for (o, v) in out.iter_mut().zip(color.to_array()) {
+            *o += v as f32 * weight;
+        }
+    }
+    out
+}
@@ -0,0 +1,334 @@
+use egui::{Color32, Pos2, Rect};
+
+use crate::utils::annotations::{distance_to_polyline, distance_to_segment, Annotation};
+
+/// Number of points a stroke is resampled to before shape recognition.
+const RECOGNITION_SAMPLES: usize = 64;
+/// Maximum normalized error for a stroke to be replaced by a circle or rectangle.
+const CLOSED_SHAPE_TOLERANCE: f32 = 0.15;
+/// Maximum deviation from the chord, relative to its length, for a stroke to count as a line.
+const LINE_TOLERANCE: f32 = 0.08;
+
+/// Ramer–Douglas–Peucker: drops the points closer than `epsilon` to the simplified line.
+pub fn simplify(points: &[Pos2], epsilon: f32) -> Vec<Pos2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let (index, distance) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i + 1, distance_to_segment(*p, first, last)))
+        .fold((0, 0.0), |max, cur| if cur.1 > max.1 { cur } else { max });
+
+    if distance <= epsilon {
+        return vec![first, last];
+    }
+    let mut left = simplify(&points[..=index], epsilon);
+    let right = simplify(&points[index..], epsilon);
+    left.pop();
+    left.extend(right);
+    left
+}
+
+/// Catmull-Rom spline through the points, with `samples` points per segment.
+pub fn smooth(points: &[Pos2], samples: usize) -> Vec<Pos2> {
+    if points.len() < 3 || samples < 2 {
+        return points.to_vec();
+    }
+    let mut out = Vec::with_capacity((points.len() - 1) * samples + 1);
+    for i in 0..points.len() - 1 {
+        let p0 = points[i.saturating_sub(1)];
+        let p1 = points[i];
+        let p2 = points[i + 1];
+        let p3 = points[(i + 2).min(points.len() - 1)];
+        for s in 0..samples {
+            let t = s as f32 / samples as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            let coord = |a: f32, b: f32, c: f32, d: f32| {
+                0.5 * (2.0 * b
+                    + (c - a) * t
+                    + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2
+                    + (3.0 * b - a - 3.0 * c + d) * t3)
+            };
+            out.push(Pos2::new(
+                coord(p0.x, p1.x, p2.x, p3.x),
+                coord(p0.y, p1.y, p2.y, p3.y),
+            ));
+        }
+    }
+    out.push(points[points.len() - 1]);
+    out
+}
+
+/// Recognizes a roughly drawn line, arrow, circle or rectangle and returns the clean annotation.
+/// Returns `None` if the stroke does not look like any of them.
+pub fn recognize(points: &[Pos2], color: Color32, width: f32, fill: Color32) -> Option<Annotation> {
+    let samples = resample(points, RECOGNITION_SAMPLES);
+    if samples.len() < 2 {
+        return None;
+    }
+    let bounds = Rect::from_points(&samples);
+    let size = bounds.width().max(bounds.height());
+    if size < f32::EPSILON {
+        return None;
+    }
+    let (first, last) = (samples[0], samples[samples.len() - 1]);
+
+    if first.distance(last) < 0.25 * size {
+        return recognize_closed(&samples, bounds, color, width, fill);
+    }
+
+    let chord = first.distance(last);
+    let deviation = samples
+        .iter()
+        .map(|p| distance_to_segment(*p, first, last))
+        .fold(0.0, f32::max);
+    if deviation < LINE_TOLERANCE * chord {
+        let mut segment = Annotation::segment(first, color, width);
+        if let Annotation::Segment(s) = &mut segment {
+            s.update_ending(last);
+        }
+        return Some(segment);
+    }
+
+    recognize_arrow(points, bounds, color, width)
+}
+
+fn recognize_closed(
+    samples: &[Pos2],
+    bounds: Rect,
+    color: Color32,
+    width: f32,
+    fill: Color32,
+) -> Option<Annotation> {
+    let n = samples.len() as f32;
+    let center = samples.iter().fold(Pos2::ZERO, |acc, p| acc + p.to_vec2() / n);
+    let radius = samples.iter().map(|p| p.distance(center)).sum::<f32>() / n;
+    let circle_error =
+        samples.iter().map(|p| (p.distance(center) - radius).abs()).sum::<f32>() / n / radius;
+
+    let border = [
+        bounds.left_top(),
+        bounds.right_top(),
+        bounds.right_bottom(),
+        bounds.left_bottom(),
+        bounds.left_top(),
+    ];
+    let half_side = bounds.width().min(bounds.height()) / 2.0;
+    let rect_error = if half_side > f32::EPSILON {
+        samples.iter().map(|p| distance_to_polyline(*p, &border)).sum::<f32>() / n / half_side
+    } else {
+        f32::INFINITY
+    };
+
+    if circle_error.min(rect_error) > CLOSED_SHAPE_TOLERANCE {
+        return None;
+    }
+    if circle_error < rect_error {
+        let mut circle = Annotation::circle(center, color, width, fill);
+        if let Annotation::Circle(c) = &mut circle {
+            c.radius = radius;
+        }
+        Some(circle)
+    } else {
+        let mut rect = Annotation::rect(bounds.min, color, fill, width);
+        if let Annotation::Rect(r) = &mut rect {
+            r.update_p2(bounds.max);
+        }
+        Some(rect)
+    }
+}
+
+/// An arrow drawn in one stroke: a long shaft followed by a short head around its end.
+fn recognize_arrow(points: &[Pos2], bounds: Rect, color: Color32, width: f32) -> Option<Annotation> {
+    let corners = simplify(points, 0.05 * bounds.width().hypot(bounds.height()));
+    if !(3..=5).contains(&corners.len()) {
+        return None;
+    }
+    let (start, tip) = (corners[0], corners[1]);
+    let shaft = start.distance(tip);
+    let head_is_short = corners[2..]
+        .iter()
+        .all(|p| p.distance(tip) < 0.5 * shaft);
+    if !head_is_short {
+        return None;
+    }
+    let mut arrow = Annotation::arrow(start, color, width);
+    if let Annotation::Arrow(a) = &mut arrow {
+        a.update_ending(tip);
+        a.consolidate();
+    }
+    Some(arrow)
+}
+
+/// Evenly spaced points along the polyline, so that averages are weighted by length.
+fn resample(points: &[Pos2], count: usize) -> Vec<Pos2> {
+    let length: f32 = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    if points.len() < 2 || length < f32::EPSILON {
+        return points.to_vec();
+    }
+    let step = length / (count - 1) as f32;
+    let mut out = vec![points[0]];
+    let mut walked = 0.0;
+    let mut next = step;
+    for w in points.windows(2) {
+        let segment = w[0].distance(w[1]);
+        while next <= walked + segment && out.len() < count - 1 {
+            out.push(w[0].lerp(w[1], (next - walked) / segment));
+            next += step;
+        }
+        walked += segment;
+    }
+    out.push(points[points.len() - 1]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    /// Deterministic hand jitter of up to `amount` points in each direction.
+    fn jitter(i: usize, amount: f32) -> egui::Vec2 {
+        let noise = |seed: usize| ((seed * 7919) % 101) as f32 / 50.0 - 1.0;
+        egui::vec2(noise(i), noise(i + 37)) * amount
+    }
+
+    /// `steps` jittered points from `a` to `b`, `b` excluded.
+    fn noisy_segment(a: Pos2, b: Pos2, steps: usize, offset: usize) -> Vec<Pos2> {
+        (0..steps)
+            .map(|i| a.lerp(b, i as f32 / steps as f32) + jitter(offset + i, 1.5))
+            .collect()
+    }
+
+    fn recognize_points(points: &[Pos2]) -> Option<Annotation> {
+        recognize(points, Color32::RED, 2.0, Color32::TRANSPARENT)
+    }
+
+    #[test]
+    fn simplify_collapses_collinear_points() {
+        let points: Vec<Pos2> = (0..=10).map(|i| Pos2::new(i as f32 * 10.0, i as f32 * 5.0)).collect();
+        assert_eq!(simplify(&points, 0.5), vec![points[0], points[10]]);
+    }
+
+    #[test]
+    fn simplify_keeps_corners() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(50.0, 0.2),
+            Pos2::new(100.0, 0.0),
+            Pos2::new(100.0, 50.0),
+            Pos2::new(100.0, 100.0),
+        ];
+        assert_eq!(
+            simplify(&points, 1.0),
+            vec![Pos2::new(0.0, 0.0), Pos2::new(100.0, 0.0), Pos2::new(100.0, 100.0)]
+        );
+    }
+
+    #[test]
+    fn smooth_keeps_the_endpoints() {
+        let points = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(30.0, 40.0),
+            Pos2::new(60.0, -10.0),
+            Pos2::new(90.0, 20.0),
+        ];
+        let smoothed = smooth(&points, 6);
+
+        assert_eq!(smoothed.len(), 3 * 6 + 1);
+        assert_eq!(smoothed.first(), points.first());
+        assert_eq!(smoothed.last(), points.last());
+        // The spline passes through every control point
+        for (i, p) in points.iter().enumerate() {
+            assert!(smoothed[i * 6].distance(*p) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn recognizes_a_noisy_line() {
+        let mut points = noisy_segment(Pos2::new(10.0, 10.0), Pos2::new(210.0, 110.0), 40, 0);
+        points.push(Pos2::new(210.0, 110.0));
+
+        match recognize_points(&points) {
+            Some(Annotation::Segment(segment)) => {
+                assert!(segment.starting_pos.distance(points[0]) < 1e-3);
+                assert_eq!(segment.ending_pos, Pos2::new(210.0, 110.0));
+            }
+            other => panic!("expected a segment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recognizes_a_noisy_rectangle() {
+        let corners = [
+            Pos2::new(0.0, 0.0),
+            Pos2::new(200.0, 0.0),
+            Pos2::new(200.0, 100.0),
+            Pos2::new(0.0, 100.0),
+            Pos2::new(0.0, 0.0),
+        ];
+        let points: Vec<Pos2> = corners
+            .windows(2)
+            .enumerate()
+            .flat_map(|(i, w)| noisy_segment(w[0], w[1], 20, i * 20))
+            .chain([Pos2::new(2.0, 3.0)])
+            .collect();
+
+        match recognize_points(&points) {
+            Some(Annotation::Rect(rect)) => {
+                assert!(rect.p1.distance(Pos2::new(0.0, 0.0)) < 3.0);
+                assert!(rect.p2.distance(Pos2::new(200.0, 100.0)) < 3.0);
+            }
+            other => panic!("expected a rectangle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recognizes_a_noisy_circle() {
+        let center = Pos2::new(100.0, 100.0);
+        let points: Vec<Pos2> = (0..=60)
+            .map(|i| {
+                let angle = i as f32 / 60.0 * TAU;
+                center + egui::vec2(angle.cos(), angle.sin()) * 50.0 + jitter(i, 2.0)
+            })
+            .collect();
+
+        match recognize_points(&points) {
+            Some(Annotation::Circle(circle)) => {
+                assert!(circle.center.distance(center) < 3.0);
+                assert!((circle.radius - 50.0).abs() < 3.0);
+            }
+            other => panic!("expected a circle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recognizes_an_arrow() {
+        let (start, tip) = (Pos2::new(0.0, 0.0), Pos2::new(200.0, 0.0));
+        let points: Vec<Pos2> = noisy_segment(start, tip, 40, 0)
+            .into_iter()
+            .chain(noisy_segment(tip, Pos2::new(170.0, -25.0), 8, 40))
+            .chain(noisy_segment(Pos2::new(170.0, -25.0), tip, 8, 48))
+            .chain(noisy_segment(tip, Pos2::new(170.0, 25.0), 8, 56))
+            .chain([Pos2::new(170.0, 25.0)])
+            .collect();
+
+        match recognize_points(&points) {
+            Some(Annotation::Arrow(arrow)) => {
+                assert!(arrow.starting_pos.distance(start) < 3.0);
+                assert!(arrow.ending_pos.distance(tip) < 5.0);
+            }
+            other => panic!("expected an arrow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn scribble_is_not_recognized() {
+        let points: Vec<Pos2> = (0..=12)
+            .map(|i| Pos2::new(i as f32 * 15.0, if i % 2 == 0 { 0.0 } else { 60.0 }))
+            .collect();
+        assert!(recognize_points(&points).is_none());
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt::Write;
+use std::io::Cursor;
+
+use base64::Engine;
+use egui::{Color32, ColorImage, Pos2, Rect, Vec2};
+use image::error::{ParameterError, ParameterErrorKind};
+use image::{ImageError, ImageFormat, RgbaImage};
+
+use crate::utils::annotations::{
+    active_crop, contrasting_color, Annotation, CALLOUT_PADDING, STEP_FONT_RATIO,
+};
+
+/// Writes the annotations as an SVG document in capture-pixel coordinates.
+///
+/// The view box covers the frame (`size`), or the last crop if there is one. With `background`,
+/// the frame is embedded under the annotations as a base64 PNG.
+pub fn to_svg(
+    annotations: &[Annotation],
+    size: [usize; 2],
+    background: Option<&ColorImage>,
+) -> Result<String, ImageError> {
+    let full = Rect::from_min_size(Pos2::ZERO, Vec2::new(size[0] as f32, size[1] as f32));
+    let view = active_crop(annotations, full).unwrap_or(full);
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        view.width(),
+        view.height(),
+        view.min.x,
+        view.min.y,
+        view.width(),
+        view.height()
+    );
+
+    if let Some(frame) = background {
+        let _ = writeln!(
+            svg,
+            r#"  <image x="0" y="0" width="{}" height="{}" href="data:image/png;base64,{}"/>"#,
+            frame.width(),
+            frame.height(),
+            encode_png_base64(frame)?
+        );
+    }
+
+    for (i, annotation) in annotations.iter().enumerate() {
+        write_annotation(&mut svg, annotation, i);
+    }
+
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+fn write_annotation(svg: &mut String, annotation: &Annotation, id: usize) {
+    let _ = match annotation {
+        Annotation::Segment(s) => writeln!(
+            svg,
+            r#"  <line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-linecap="round"/>"#,
+            s.starting_pos.x,
+            s.starting_pos.y,
+            s.ending_pos.x,
+            s.ending_pos.y,
+            stroke(s.color, s.width)
+        ),
+        Annotation::Circle(c) => writeln!(
+            svg,
+            r#"  <circle cx="{}" cy="{}" r="{}" {} {}/>"#,
+            c.center.x,
+            c.center.y,
+            c.radius,
+            fill(c.fill_color),
+            stroke(c.color, c.width)
+        ),
+        Annotation::Rect(r) => {
+            let rect = Rect::from_two_pos(r.p1, r.p2);
+            writeln!(
+                svg,
+                r#"  <rect x="{}" y="{}" width="{}" height="{}" {} {}/>"#,
+                rect.min.x,
+                rect.min.y,
+                rect.width(),
+                rect.height(),
+                fill(r.fill_color),
+                stroke(r.color, r.width)
+            )
+        }
+        Annotation::Arrow(a) => {
+            // The tip is a marker drawn in the direction of the path, sized like on screen
+            // (a quarter of the arrow length, at 36 degrees from the body)
+            let length = (a.ending_pos - a.starting_pos).length() / 4.0;
+            let angle = std::f32::consts::TAU / 10.0;
+            let (dx, dy) = (length * angle.cos(), length * angle.sin());
+            let _ = writeln!(
+                svg,
+                r#"  <marker id="arrow-tip-{id}" markerUnits="userSpaceOnUse" orient="auto" overflow="visible"><path d="M {} {} L 0 0 L {} {}" fill="none" {} stroke-linecap="round" stroke-linejoin="round"/></marker>"#,
+                -dx,
+                -dy,
+                -dx,
+                dy,
+                stroke(a.color, a.width)
+            );
+            writeln!(
+                svg,
+                r#"  <path d="M {} {} L {} {}" fill="none" {} stroke-linecap="round" marker-end="url(#arrow-tip-{id})"/>"#,
+                a.starting_pos.x,
+                a.starting_pos.y,
+                a.ending_pos.x,
+                a.ending_pos.y,
+                stroke(a.color, a.width)
+            )
+        }
+        Annotation::Pencil(p) => writeln!(
+            svg,
+            r#"  <polyline points="{}" fill="none" {} stroke-linecap="round" stroke-linejoin="round"/>"#,
+            points(&p.points),
+            stroke(p.color, p.width)
+        ),
+        Annotation::Highlighter(h) => writeln!(
+            svg,
+            r#"  <polyline points="{}" fill="none" {} stroke-linecap="round" stroke-linejoin="round"/>"#,
+            points(&h.points),
+            stroke(h.color, h.width)
+        ),
+        Annotation::Text(t) => {
+            svg.push_str("  ");
+            write_text(svg, t.pos, &t.text, t.size, t.color);
+            svg.push('\n');
+            Ok(())
+        }
+        Annotation::Step(st) => {
+            let text_color = contrasting_color(st.color);
+            let (fill_color, fill_opacity) = paint(st.color);
+            let (text_fill, _) = paint(text_color);
+            writeln!(
+                svg,
+                r#"  <g><circle cx="{}" cy="{}" r="{}" fill="{}" fill-opacity="{}" {}/><text x="{}" y="{}" font-family="sans-serif" font-size="{}" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text></g>"#,
+                st.center.x,
+                st.center.y,
+                st.radius,
+                fill_color,
+                fill_opacity,
+                stroke(text_color, st.radius * 0.12),
+                st.center.x,
+                st.center.y,
+                st.radius * STEP_FONT_RATIO,
+                text_fill,
+                st.number
+            )
+        }
+        Annotation::Callout(c) => {
+            // Monospace glyphs are about 0.6 em wide, which gives the size of the box without laying out the text
+            let lines: Vec<&str> = if c.text.is_empty() { vec![""] } else { c.text.lines().collect() };
+            let columns = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0).max(1);
+            let padding = c.size * CALLOUT_PADDING;
+            let frame = Rect::from_min_size(
+                c.pos,
+                Vec2::new(columns as f32 * c.size * 0.6, lines.len() as f32 * c.size)
+                    + Vec2::splat(padding * 2.0),
+            );
+            let _ = write!(svg, "  <g>");
+            if !frame.contains(c.anchor) {
+                let side = (c.anchor - frame.center()).normalized().rot90() * c.tail_half_width(frame);
+                let (a, b) = (frame.center() + side, frame.center() - side);
+                let _ = write!(
+                    svg,
+                    r#"<polygon points="{},{} {},{} {},{}" {} {} stroke-linejoin="round"/>"#,
+                    a.x,
+                    a.y,
+                    c.anchor.x,
+                    c.anchor.y,
+                    b.x,
+                    b.y,
+                    fill(c.fill_color),
+                    stroke(c.color, c.width)
+                );
+            }
+            let _ = write!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {} {}/>"#,
+                frame.min.x,
+                frame.min.y,
+                frame.width(),
+                frame.height(),
+                c.size * 0.3,
+                fill(c.fill_color),
+                stroke(c.color, c.width)
+            );
+            write_text(svg, c.pos + Vec2::splat(padding), &c.text, c.size, c.color);
+            svg.push_str("</g>\n");
+            Ok(())
+        }
+        // History entries are not drawn, and redactions are already applied to the frame
+        Annotation::Eraser(_) | Annotation::Crop(_) | Annotation::Edit(_) | Annotation::Redact(_) => {
+            Ok(())
+        }
+    };
+}
+
+/// Monospace text with its top-left corner at `pos`, one `tspan` per line.
+fn write_text(svg: &mut String, pos: Pos2, text: &str, size: f32, color: Color32) {
+    let (color, opacity) = paint(color);
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" font-family="monospace" font-size="{}" fill="{}" fill-opacity="{}" dominant-baseline="text-before-edge" xml:space="preserve">"#,
+        pos.x, pos.y, size, color, opacity
+    );
+    for (i, line) in text.lines().enumerate() {
+        let dy = if i == 0 { 0.0 } else { size };
+        let _ = write!(
+            svg,
+            r#"<tspan x="{}" dy="{}">{}</tspan>"#,
+            pos.x,
+            dy,
+            escape(line)
+        );
+    }
+    svg.push_str("</text>");
+}
+
+/// SVG color and opacity of a (premultiplied) `Color32`.
+fn paint(color: Color32) -> (String, f32) {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    (format!("#{:02x}{:02x}{:02x}", r, g, b), a as f32 / 255.0)
+}
+
+fn stroke(color: Color32, width: f32) -> String {
+    let (color, opacity) = paint(color);
+    format!(
+        r#"stroke="{}" stroke-opacity="{}" stroke-width="{}""#,
+        color, opacity, width
+    )
+}
+
+fn fill(color: Color32) -> String {
+    if color.a() == 0 {
+        return r#"fill="none""#.to_string();
+    }
+    let (color, opacity) = paint(color);
+    format!(r#"fill="{}" fill-opacity="{}""#, color, opacity)
+}
+
+fn points(points: &[Pos2]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Fails with a dimension mismatch if the pixels do not fill the frame size.
+fn encode_png_base64(frame: &ColorImage) -> Result<String, ImageError> {
+    let bytes = frame
+        .pixels
+        .iter()
+        .flat_map(|c| c.to_srgba_unmultiplied())
+        .collect();
+    let image = RgbaImage::from_raw(frame.width() as u32, frame.height() as u32, bytes).ok_or_else(|| {
+        ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch))
+    })?;
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::annotations::CropAnnotation;
+
+    const HEADER: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="640" height="480" viewBox="0 0 640 480">"#;
+
+    fn segment(from: Pos2, to: Pos2) -> Annotation {
+        let mut segment = Annotation::segment(from, Color32::RED, 3.0);
+        if let Annotation::Segment(s) = &mut segment {
+            s.ending_pos = to;
+        }
+        segment
+    }
+
+    fn body(svg: &str) -> Vec<&str> {
+        let lines: Vec<&str> = svg.lines().collect();
+        assert_eq!(lines.first(), Some(&HEADER));
+        assert_eq!(lines.last(), Some(&"</svg>"));
+        lines[1..lines.len() - 1].to_vec()
+    }
+
+    #[test]
+    fn line() {
+        let svg = to_svg(&[segment(Pos2::new(10.0, 20.0), Pos2::new(110.5, 40.0))], [640, 480], None).unwrap();
+        assert_eq!(
+            body(&svg),
+            [r##"  <line x1="10" y1="20" x2="110.5" y2="40" stroke="#ff0000" stroke-opacity="1" stroke-width="3" stroke-linecap="round"/>"##]
+        );
+    }
+
+    #[test]
+    fn arrow_with_marker() {
+        let mut arrow = Annotation::arrow(Pos2::new(0.0, 0.0), Color32::BLUE, 2.0);
+        if let Annotation::Arrow(a) = &mut arrow {
+            a.ending_pos = Pos2::new(40.0, 0.0);
+        }
+        let svg = to_svg(&[segment(Pos2::ZERO, Pos2::ZERO), arrow], [640, 480], None).unwrap();
+        let body = body(&svg);
+        assert_eq!(body.len(), 3);
+
+        // The marker id is unique per annotation and the tip is a quarter of the arrow long, at 36 degrees
+        let (dx, dy) = (10.0 * 36f32.to_radians().cos(), 10.0 * 36f32.to_radians().sin());
+        assert_eq!(
+            body[1],
+            format!(
+                r##"  <marker id="arrow-tip-1" markerUnits="userSpaceOnUse" orient="auto" overflow="visible"><path d="M {} {} L 0 0 L {} {}" fill="none" stroke="#0000ff" stroke-opacity="1" stroke-width="2" stroke-linecap="round" stroke-linejoin="round"/></marker>"##,
+                -dx, -dy, -dx, dy
+            )
+        );
+        assert_eq!(
+            body[2],
+            r##"  <path d="M 0 0 L 40 0" fill="none" stroke="#0000ff" stroke-opacity="1" stroke-width="2" stroke-linecap="round" marker-end="url(#arrow-tip-1)"/>"##
+        );
+    }
+
+    #[test]
+    fn polyline() {
+        let mut pencil = Annotation::pencil(Pos2::new(1.0, 2.0), Color32::from_rgb(0x12, 0x34, 0x56), 4.0);
+        if let Annotation::Pencil(p) = &mut pencil {
+            p.points.extend([Pos2::new(3.0, 4.0), Pos2::new(5.5, 6.0)]);
+        }
+        let svg = to_svg(&[pencil], [640, 480], None).unwrap();
+        assert_eq!(
+            body(&svg),
+            [r##"  <polyline points="1,2 3,4 5.5,6" fill="none" stroke="#123456" stroke-opacity="1" stroke-width="4" stroke-linecap="round" stroke-linejoin="round"/>"##]
+        );
+    }
+
+    #[test]
+    fn multi_line_text_is_escaped() {
+        let mut text = Annotation::text(Pos2::new(5.0, 6.0), Color32::from_rgba_unmultiplied(255, 255, 255, 128), 20.0);
+        if let Annotation::Text(t) = &mut text {
+            t.text = "a < b && c\n<tag>".to_string();
+        }
+        let svg = to_svg(&[text], [640, 480], None).unwrap();
+        let opacity = Color32::from_rgba_unmultiplied(255, 255, 255, 128).to_srgba_unmultiplied()[3] as f32 / 255.0;
+        assert_eq!(
+            body(&svg),
+            [format!(
+                r##"  <text x="5" y="6" font-family="monospace" font-size="20" fill="#ffffff" fill-opacity="{}" dominant-baseline="text-before-edge" xml:space="preserve"><tspan x="5" dy="0">a &lt; b &amp;&amp; c</tspan><tspan x="5" dy="20">&lt;tag&gt;</tspan></text>"##,
+                opacity
+            )]
+        );
+    }
+
+    #[test]
+    fn escape_handles_markup_characters() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("<a href='x'>&amp;</a>"), "&lt;a href='x'&gt;&amp;amp;&lt;/a&gt;");
+    }
+
+    #[test]
+    fn crop_sets_the_view_box() {
+        let crop = Annotation::Crop(CropAnnotation::new(Pos2::new(100.4, 50.0), Pos2::new(300.0, 700.0)));
+        let svg = to_svg(&[crop], [640, 480], None).unwrap();
+        // The crop is clipped to the frame and rounded to whole pixels, and draws nothing itself
+        assert_eq!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"200\" height=\"430\" viewBox=\"100 50 200 430\">\n</svg>\n"
+        );
+    }
+
+    #[test]
+    fn background_is_an_embedded_png() {
+        let frame = ColorImage::new([4, 3], Color32::from_rgb(10, 20, 30));
+        let svg = to_svg(&[], [4, 3], Some(&frame)).unwrap();
+        let line = svg.lines().nth(1).unwrap();
+        let prefix = r#"  <image x="0" y="0" width="4" height="3" href="data:image/png;base64,"#;
+        assert!(line.starts_with(prefix) && line.ends_with(r#""/>"#), "{}", line);
+
+        let encoded = &line[prefix.len()..line.len() - 3];
+        let png = base64::engine::general_purpose::STANDARD.decode(encoded).unwrap();
+        let decoded = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap().to_rgba8();
+        assert_eq!(decoded.dimensions(), (4, 3));
+        assert!(decoded.pixels().all(|pixel| pixel.0 == [10, 20, 30, 255]));
+    }
+
+    #[test]
+    fn background_with_missing_pixels_is_an_error() {
+        let mut frame = ColorImage::new([4, 3], Color32::BLACK);
+        frame.pixels.pop();
+        assert!(matches!(
+            to_svg(&[], [4, 3], Some(&frame)),
+            Err(ImageError::Parameter(e)) if e.kind() == ParameterErrorKind::DimensionMismatch
+        ));
+    }
+}
@@ -0,0 +1,30 @@
+// src/utils/test_support.rs
+
+//! Fixtures shared by the unit tests.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A directory of its own in the temp directory, removed with its contents when the test ends.
+/// `name` must be unique among the tests, which run in parallel.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("screen_stream_x_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    /// Path of `file_name` inside the directory.
+    pub fn join(&self, file_name: &str) -> PathBuf {
+        self.0.join(file_name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}